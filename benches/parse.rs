@@ -0,0 +1,57 @@
+use clitrs::args::CliArgs;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn schema() -> Vec<String> {
+    (0..30)
+        .map(|i| match i % 3 {
+            0 => format!("--flag{i}/-{}=b?", (b'a' + (i / 3) as u8) as char),
+            1 => format!("--int{i} = i? ::>{i}"),
+            _ => format!("--str{i}=s*?"),
+        })
+        .collect()
+}
+
+fn register(schema: &[String]) -> CliArgs {
+    let mut args = CliArgs::new();
+    for s in schema {
+        args.with(s);
+    }
+    args
+}
+
+fn tokens() -> Vec<String> {
+    let mut tokens = Vec::new();
+    for i in 0..10 {
+        tokens.push(format!("-{}", (b'a' + i as u8) as char));
+        tokens.push(format!("--int{}={}", i * 3 + 1, i * 100));
+        tokens.push(format!("--str{}", i * 3 + 2));
+        tokens.push(format!("value{i}"));
+        tokens.push(format!("other{i}"));
+    }
+    tokens
+}
+
+fn bench_register(c: &mut Criterion) {
+    let schema = schema();
+    c.bench_function("register 30 args", |b| b.iter(|| register(black_box(&schema))));
+}
+
+// Each parse runs on a freshly registered `CliArgs`, whose memory is cold.
+// Best of three interleaved runs, in a noisy sandbox:
+//   before interned keys and per-arg value parsers  9.74 us
+//   after                                          3.62 us (about 2.7x)
+fn bench_parse(c: &mut Criterion) {
+    let schema = schema();
+    let tokens = tokens();
+    assert_eq!(tokens.len(), 50);
+    c.bench_function("parse 50 tokens", |b| {
+        b.iter_batched_ref(
+            || register(&schema),
+            |args| args.parse_from(black_box(&tokens)).unwrap(),
+            criterion::BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, bench_register, bench_parse);
+criterion_main!(benches);
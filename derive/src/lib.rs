@@ -0,0 +1,241 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Expr, ExprArray, ExprLit, Fields, GenericArgument, Lit, LitChar, LitStr, Meta, PathArguments, Type};
+
+/// Implements `clitrs::args::CliSchema` for a struct with named fields,
+/// registering one arg per field.
+///
+/// The long key is the field name in kebab case. The arg kind follows the
+/// field type: `bool` is an optional flag, `i32` and `String` are required
+/// values, `Option<i32>`/`Option<String>` optional values and
+/// `Vec<i32>`/`Vec<String>` optional greedy values.
+///
+/// Field attributes: `#[cli(short = 'n')]`, `#[cli(long = "name")]`,
+/// `#[cli(default = "18")]`, a default makes the arg optional,
+/// `#[cli(env = "APP_NAME")]`, see `CliArgs::set_env`, and for string
+/// fields `#[cli(choices = ["fast", "slow"])]`, see `CliArgs::set_choices`.
+/// Doc comments become the arg's description. A key or default that would
+/// not read back from the schema is a compile error.
+#[proc_macro_derive(CliSchema, attributes(cli))]
+pub fn derive_cli_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "CliSchema needs a struct with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "CliSchema can only be derived for structs")),
+    };
+
+    let mut registrations = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let name = ident.to_string();
+
+        let mut long = name.trim_start_matches("r#").replace('_', "-");
+        let mut short: Option<char> = None;
+        let mut default: Option<String> = None;
+        let mut env: Option<String> = None;
+        let mut choices: Option<(Vec<String>, Span)> = None;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("cli")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("short") {
+                    let lit = meta.value()?.parse::<LitChar>()?;
+                    if !lit.value().is_alphanumeric() {
+                        return Err(syn::Error::new_spanned(lit, "expected a letter or digit as short key"));
+                    }
+                    short = Some(lit.value());
+                }
+                else if meta.path.is_ident("long") {
+                    let lit = meta.value()?.parse::<LitStr>()?;
+                    long = lit.value();
+                    if !is_key_name(&long) {
+                        return Err(syn::Error::new_spanned(lit, "expected a long key of letters, digits, `-` and `_`, without the leading `--`"));
+                    }
+                }
+                else if meta.path.is_ident("default") {
+                    let lit = meta.value()?.parse::<LitStr>()?;
+                    if starts_description(&lit.value()) {
+                        return Err(syn::Error::new_spanned(lit, "a default cannot contain ` #`, it would start the description"));
+                    }
+                    default = Some(lit.value());
+                }
+                else if meta.path.is_ident("env") {
+                    let lit = meta.value()?.parse::<LitStr>()?;
+                    if lit.value().is_empty() || lit.value().contains(['=', '\0']) {
+                        return Err(syn::Error::new_spanned(lit, "expected an environment variable name"));
+                    }
+                    env = Some(lit.value());
+                }
+                else if meta.path.is_ident("choices") {
+                    let array = meta.value()?.parse::<ExprArray>()?;
+                    let values = array.elems.iter()
+                        .map(|elem| match elem {
+                            Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => Ok(s.value()),
+                            _ => Err(syn::Error::new_spanned(elem, "expected a string literal")),
+                        })
+                        .collect::<syn::Result<Vec<String>>>()?;
+                    choices = Some((values, meta.path.get_ident().map_or_else(Span::call_site, |ident| ident.span())));
+                }
+                else {
+                    return Err(meta.error("unsupported cli attribute, expected `short`, `long`, `default`, `env` or `choices`"));
+                }
+                Ok(())
+            })?;
+        }
+
+        let (type_code, modifier) = arg_kind(&field.ty).ok_or_else(|| {
+            syn::Error::new_spanned(
+                &field.ty,
+                format!("field `{}` has an unsupported type, expected bool, i32, String or an Option/Vec of i32 or String", name),
+            )
+        })?;
+        if let Some((_, span)) = &choices {
+            if type_code != 's' {
+                return Err(syn::Error::new(*span, format!("field `{}` has choices, which only a String field can have", name)));
+            }
+        }
+        let modifier = match (modifier, &default) {
+            ("", Some(_)) => "?",
+            (modifier, _) => modifier,
+        };
+
+        let key = format!("--{}", long);
+        let mut schema = key.clone();
+        if let Some(short) = short {
+            schema.push_str(&format!("/-{}", short));
+        }
+        schema.push('=');
+        schema.push(type_code);
+        schema.push_str(modifier);
+        if let Some(default) = default {
+            schema.push_str(" ::>");
+            schema.push_str(&default);
+        }
+        let doc = doc_comment(&field.attrs);
+        if !doc.is_empty() {
+            schema.push_str(" # ");
+            schema.push_str(&doc);
+        }
+        let schema = LitStr::new(&schema, Span::call_site());
+        let env = env.map(|env| quote! { let _ = args.set_env(#key, #env); });
+        let choices = choices.map(|(choices, _)| quote! { let _ = args.set_choices(#key, &[#(#choices),*], false); });
+        registrations.push(quote! {
+            args.with(#schema);
+            #env
+            #choices
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::clitrs::args::CliSchema for #ident #ty_generics #where_clause {
+            fn register(args: &mut ::clitrs::args::CliArgs) {
+                #( #registrations )*
+            }
+        }
+    })
+}
+
+/// Whether `long` can follow the `--` of a key in a schema, which would
+/// otherwise split it into other keys, a type or a description.
+fn is_key_name(long: &str) -> bool {
+    !long.is_empty() && !long.starts_with('-') && long.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Whether `default` has a `#` after whitespace, which starts the
+/// description in a schema.
+fn starts_description(default: &str) -> bool {
+    default.chars().zip(default.chars().skip(1)).any(|(prev, c)| prev.is_whitespace() && c == '#')
+}
+
+/// The doc comment lines of a field joined into one line.
+fn doc_comment(attrs: &[syn::Attribute]) -> String {
+    let lines: Vec<String> = attrs.iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match &a.meta {
+            Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    lines.join(" ")
+}
+
+/// The schema type code and modifier for a field type.
+fn arg_kind(ty: &Type) -> Option<(char, &'static str)> {
+    let (name, inner) = last_segment(ty)?;
+    match (name.as_str(), inner) {
+        ("bool", None) => Some(('b', "?")),
+        ("i32", None) => Some(('i', "")),
+        ("String", None) => Some(('s', "")),
+        ("Option", Some(inner)) => scalar_code(inner).map(|code| (code, "?")),
+        ("Vec", Some(inner)) => scalar_code(inner).map(|code| (code, "*?")),
+        _ => None,
+    }
+}
+
+fn scalar_code(ty: &Type) -> Option<char> {
+    match last_segment(ty)? {
+        (name, None) if name == "i32" => Some('i'),
+        (name, None) if name == "String" => Some('s'),
+        _ => None,
+    }
+}
+
+/// The last path segment of a type and its single generic type argument, if any.
+fn last_segment(ty: &Type) -> Option<(String, Option<&Type>)> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    let inner = match &segment.arguments {
+        PathArguments::None => None,
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match args.args.first()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some((segment.ident.to_string(), inner))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{arg_kind, is_key_name, starts_description};
+
+    fn kind(ty: &str) -> Option<(char, &'static str)> {
+        arg_kind(&syn::parse_str(ty).unwrap())
+    }
+
+    #[test]
+    fn kinds_by_type() {
+        assert_eq!(kind("bool"), Some(('b', "?")));
+        assert_eq!(kind("i32"), Some(('i', "")));
+        assert_eq!(kind("std::string::String"), Some(('s', "")));
+        assert_eq!(kind("Option<i32>"), Some(('i', "?")));
+        assert_eq!(kind("Vec<String>"), Some(('s', "*?")));
+        assert_eq!(kind("u64"), None);
+        assert_eq!(kind("Option<bool>"), None);
+        assert_eq!(kind("Vec<Vec<String>>"), None);
+    }
+
+    #[test]
+    fn keys_and_defaults_read_back() {
+        assert!(is_key_name("dry-run") && is_key_name("out_dir"));
+        assert!(!is_key_name("") && !is_key_name("--out") && !is_key_name("bad key"));
+        assert!(!is_key_name("a=b") && !is_key_name("a/b") && !is_key_name("a#b"));
+        assert!(!starts_description("#fff") && !starts_description("a#b") && !starts_description("say hi"));
+        assert!(starts_description("a # b") && starts_description("a\t#b"));
+    }
+}
@@ -0,0 +1,57 @@
+use clitrs::args::{CliArgs, CliSchema};
+
+#[derive(CliSchema)]
+#[allow(dead_code)]
+struct Config {
+    /// Who to greet,
+    /// on two lines.
+    #[cli(short = 'n')]
+    name: String,
+    #[cli(short = 'a', default = "18")]
+    age: i32,
+    verbose: bool,
+    dry_run: bool,
+    #[cli(long = "out")]
+    output: Option<String>,
+    files: Vec<String>,
+    #[cli(env = "CLITRS_DERIVE_LEVEL", choices = ["low", "high"])]
+    level: Option<String>,
+}
+
+#[test]
+fn registers_fields() {
+    let mut args = Config::cli();
+    assert_eq!(args.schema_error(), None);
+    args.parse("-n alp --dry-run --files a b").unwrap();
+
+    assert_eq!(args.get_str("--name").unwrap(), Some("alp"));
+    assert_eq!(args.get_int("-a").unwrap(), Some(18));
+    assert_eq!(args.get_bool("--verbose").unwrap(), None);
+    assert_eq!(args.get_bool("--dry-run").unwrap(), Some(true));
+    assert_eq!(args.get_str("--out").unwrap(), None);
+    assert_eq!(args.get_string_multi("--files").unwrap(), ["a", "b"]);
+    assert_eq!(args.description("--name").unwrap(), Some("Who to greet, on two lines."));
+    assert_eq!(args.description("--age").unwrap(), None);
+
+    std::env::set_var("CLITRS_DERIVE_LEVEL", "high");
+    let mut args = Config::cli();
+    args.parse("-n alp").unwrap();
+    assert_eq!(args.get_str("--level").unwrap(), Some("high"));
+    assert!(Config::cli().parse("-n alp --level mid").is_err());
+}
+
+#[test]
+fn rejects_misuse_at_compile_time() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
+
+#[test]
+fn registers_into_existing_args() {
+    let mut args = CliArgs::new();
+    args.with("--extra=i?");
+    Config::register(&mut args);
+    args.parse("--name alp --extra 1").unwrap();
+    assert_eq!(args.get_int("--extra").unwrap(), Some(1));
+    assert_eq!(args.get_str("-n").unwrap(), Some("alp"));
+}
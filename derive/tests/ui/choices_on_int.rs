@@ -0,0 +1,9 @@
+use clitrs::args::CliSchema;
+
+#[derive(CliSchema)]
+struct Config {
+    #[cli(choices = ["1", "2"])]
+    level: i32,
+}
+
+fn main() {}
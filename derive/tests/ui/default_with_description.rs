@@ -0,0 +1,9 @@
+use clitrs::args::CliSchema;
+
+#[derive(CliSchema)]
+struct Config {
+    #[cli(default = "a # b")]
+    tag: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use clitrs::args::CliSchema;
+
+#[derive(CliSchema)]
+struct Config {
+    #[cli(long = "bad key")]
+    name: String,
+}
+
+fn main() {}
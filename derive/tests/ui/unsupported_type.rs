@@ -0,0 +1,9 @@
+use clitrs::args::CliSchema;
+
+#[derive(CliSchema)]
+struct Config {
+    name: String,
+    port: u16,
+}
+
+fn main() {}
@@ -1,12 +1,93 @@
-use std::{env, fs::File, fmt::Debug};
+use std::{borrow::Cow, env, error::Error, fs::{self, File}, mem};
+use std::io::{self, Read};
+use std::fmt::{self, Debug, Display, Formatter};
 use std::collections::HashMap;
-use lazy_static::lazy_static;
-use regex::Regex;
 
-#[derive(Debug)]
+pub use self::color::ColorChoice;
+use self::completions::Completer;
+use self::exit::ExitCodes;
+pub use self::completions::ValueHint;
+pub use self::config::ConfigError;
+#[cfg(any(feature = "definition-toml", feature = "definition-yaml"))]
+pub use self::definition::DefinitionError;
+pub use self::events::{ParseEvent, ParseEvents};
+pub use self::expand::UnknownVar;
+pub(crate) use self::expand::expand_home;
+pub use self::map::MapError;
+pub use self::os_args::NonUtf8;
+pub use self::matches::{Matches, MatchesError};
+pub use self::positional::PositionalError;
+pub use self::subcommands::CliSubcommands;
+pub use self::suggest::rank_candidates;
+pub use self::tokenize::{tokenize, TokenizeError};
+pub use self::transform::Transform;
+pub use self::tuple::Tuple;
+use self::events::{Indexed, Tokenizer};
+use self::help::{Example, Meta};
+use self::keys::Keys;
+use self::schema::{split_schema, SchemaParts};
+use self::sealed::Vals;
+use self::validate::Validator;
+use crate::term::{self, Stream};
+
+#[cfg(feature = "clap-interop")]
+mod clap_interop;
+mod color;
+mod completions;
+mod config;
+#[cfg(any(feature = "definition-toml", feature = "definition-yaml"))]
+mod definition;
+mod events;
+mod exit;
+mod expand;
+mod help;
+mod json;
+mod keys;
+mod map;
+mod matches;
+mod os_args;
+mod positional;
+mod schema;
+mod secret;
+mod subcommands;
+mod suggest;
+mod tokenize;
+mod transform;
+mod tuple;
+mod validate;
+mod verbosity;
+
+/// `Debug` is implemented by hand to redact secrets, see [`CliArgs::set_secret`].
+#[derive(Clone)]
 pub struct ArgSettings<T: Debug> {
     optional: bool,
     default_val: Option<T>,
+    greedy: bool,
+    nargs: Option<usize>,
+    description: Option<String>,
+    file_value: bool,
+    env: Option<String>,
+    hide_default: bool,
+    choices: Vec<String>,
+    ignore_case: bool,
+    value_aliases: Vec<(String, String)>, // alias and its canonical value
+    value_hint: Option<ValueHint>,
+    completer: Option<Completer>,
+    validators: Vec<Validator>,
+    expand_env: Option<UnknownVar>,
+    expand_tilde: Option<bool>,
+    transforms: Vec<Transform>,
+    secret: bool,
+    non_empty: bool,
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+    tuple: Option<Tuple>,
+    default_missing: Option<String>,
+    hyphen_values: bool,
+    raw: bool,
+    join_rest: bool,
+    map_keys: Vec<String>,
+    post_transform: Option<Transform>,
 }
 
 impl<T: Debug> Default for ArgSettings<T> {
@@ -14,21 +95,45 @@ impl<T: Debug> Default for ArgSettings<T> {
         Self {
             optional: false,
             default_val: None,
+            greedy: false,
+            nargs: None,
+            description: None,
+            file_value: false,
+            env: None,
+            hide_default: false,
+            choices: Vec::new(),
+            ignore_case: false,
+            value_aliases: Vec::new(),
+            value_hint: None,
+            completer: None,
+            validators: Vec::new(),
+            expand_env: None,
+            expand_tilde: None,
+            transforms: Vec::new(),
+            secret: false,
+            non_empty: false,
+            min_len: None,
+            max_len: None,
+            tuple: None,
+            default_missing: None,
+            hyphen_values: false,
+            raw: false,
+            join_rest: false,
+            map_keys: Vec::new(),
+            post_transform: None,
         }
     }
 }
 
 impl<T: Clone + Debug> ArgSettings<T> {
+    #[allow(clippy::result_unit_err)]
     pub fn apply(&self, vals: &mut Vec<T>) -> Result<(), ()> {
 
         let mut ok = true;
         if vals.is_empty() { // no val was given
             if self.optional { // arg was optional
-                match &self.default_val {
-                    Some(d) => { // default val was provided
-                        vals.push(d.clone());
-                    },
-                    None => {},
+                if let Some(d) = &self.default_val { // default val was provided
+                    vals.push(d.clone());
                 }
             }
             else { // arg was not optional
@@ -40,12 +145,51 @@ impl<T: Clone + Debug> ArgSettings<T> {
                 }
             }
         }
-        
+
         if ok { Ok(()) } else { Err(()) }
     }
 }
 
-#[derive(Debug)]
+impl<T: Debug> ArgSettings<T> {
+    /// Whether values go to the arg as given, with nothing to transform,
+    /// resolve or check before the conversion to its type.
+    fn is_plain(&self) -> bool {
+        self.transforms.is_empty()
+            && self.validators.is_empty()
+            && self.value_aliases.is_empty()
+            && self.choices.is_empty()
+            && self.tuple.is_none()
+            && self.map_keys.is_empty()
+            && !self.secret
+            && !self.non_empty
+            && self.min_len.is_none()
+            && self.max_len.is_none()
+    }
+}
+
+impl ArgSettings<String> {
+    /// The canonical value `val` is an alias of, or `val` itself.
+    fn canonical<'v>(&self, val: Cow<'v, str>) -> Cow<'v, str> {
+        match self.value_aliases.iter().find(|(alias, _)| *alias == val) {
+            Some((_, canonical)) => Cow::Owned(canonical.clone()),
+            None => val,
+        }
+    }
+
+    /// The declared choice matching `val`, in its declared casing, or `val`
+    /// itself when there are no choices.
+    fn resolve_choice(&self, val: String) -> Option<String> {
+        if self.choices.is_empty() {
+            return Some(val);
+        }
+        let lower = val.to_lowercase();
+        self.choices.iter()
+            .find(|c| **c == val || (self.ignore_case && c.to_lowercase() == lower))
+            .cloned()
+    }
+}
+
+#[derive(Clone)]
 pub enum Arg {
     Bool { vals: Vec<bool>, settings: ArgSettings<bool> },
     Int { vals: Vec<i32>, settings: ArgSettings<i32> },
@@ -53,6 +197,7 @@ pub enum Arg {
 }
 
 impl Arg {
+    #[allow(clippy::result_unit_err)]
     pub fn apply_settings(&mut self) -> Result<(), ()> {
         match self {
             Arg::Bool { vals, settings } => settings.apply(vals)?,
@@ -61,304 +206,3045 @@ impl Arg {
         };
         Ok(())
     }
-}
-
-#[derive(Debug)]
-pub enum ArgError {
-    WrongKey,
-    WrongType,
-}
-
-#[derive(Default, Debug)]
-pub struct CliArgs {
-    keys: HashMap<String, usize>,
-    args: Vec<Arg>,
-}
 
-impl CliArgs {
-    pub fn new() -> Self {
-        Default::default()
+    /// Greedy args keep consuming bare tokens until the next key or `--`.
+    pub fn is_greedy(&self) -> bool {
+        match self {
+            Arg::Bool { .. } => false,
+            Arg::Int { settings, .. } => settings.greedy,
+            Arg::String { settings, .. } => settings.greedy,
+        }
     }
 
-    pub fn with(&mut self, schema: &str) -> &mut Self {
-        let (key_l, key_s, arg_base) = Self::parse_schema(schema);        
-        let ind = self.args.len();
-    
-        if let Some(key_s) = key_s {
-            self.keys.insert(key_s, ind);   
-        }
-        if let Some(key_l) = key_l {
-            self.keys.insert(key_l, ind);   
+    /// The exact number of values each occurrence of the arg consumes, if fixed.
+    pub fn nargs(&self) -> Option<usize> {
+        match self {
+            Arg::Bool { .. } => None,
+            Arg::Int { settings, .. } => settings.nargs,
+            Arg::String { settings, .. } => settings.nargs,
         }
-        self.args.push(arg_base);
-    
-        self
     }
 
-    pub fn help(&self) -> String {
-        todo!()
+    /// Whether the arg takes another bare token after `taken` of them.
+    fn wants_more(&self, taken: usize) -> bool {
+        match self.nargs() {
+            Some(n) => taken < n,
+            None => self.is_greedy() || taken == 0,
+        }
     }
 
-    pub fn parse_cmd(&mut self) -> Result<(), ()> {
-        let args_vec: Vec<String> = env::args().collect();
-
-        if args_vec.is_empty() {
-            return Ok(());
+    /// What kind of value the arg takes, for shell completion.
+    pub fn value_hint(&self) -> Option<ValueHint> {
+        match self {
+            Arg::Bool { settings, .. } => settings.value_hint,
+            Arg::Int { settings, .. } => settings.value_hint,
+            Arg::String { settings, .. } => settings.value_hint,
         }
+    }
 
-        let f = File::open(&args_vec[0]);
-        let mut start = 0;
-        if let Ok(_) = f {
-            start = 1; // first arg is the program path, skip it
+    fn completer(&self) -> Option<&Completer> {
+        match self {
+            Arg::Bool { settings, .. } => settings.completer.as_ref(),
+            Arg::Int { settings, .. } => settings.completer.as_ref(),
+            Arg::String { settings, .. } => settings.completer.as_ref(),
         }
+    }
 
-        let mut prev_key = String::new();
-        for arg_str in args_vec.iter().skip(start) {
-            if Self::is_long_key(arg_str) {
-                let (key_l, val) = arg_str.split_once("=").unwrap_or_else(|| (&arg_str, ""));
-                let arg = self.get_mut_arg(&key_l).expect("key not found");
-                match arg {
-                    Arg::Bool { vals, .. } => {
-                        assert!(val.is_empty());
-                        vals.push(true);
-                    },
-                    Arg::Int { vals, .. } => vals.push(val.parse().map_err(|e| ())?),
-                    Arg::String { vals, .. } => vals.push(val.to_string()),
-                }
-            }
-            else if Self::is_short_key(arg_str) {
-                let arg = self.get_mut_arg(&arg_str).expect("key not found");
-                if let Arg::Bool { vals, .. } = arg {
-                    vals.push(true);
-                }
-                else {
-                    prev_key.push_str(arg_str);
-                }
-            }
-            else { // is val
-                let arg = self.get_mut_arg(&prev_key).expect("key not found");
-                match arg {
-                    Arg::Int { vals, .. } => vals.push(arg_str.parse().map_err(|e| ())?),
-                    Arg::String { vals, .. } => vals.push(arg_str.to_string()),
-                    _ => panic!("How did I end up here?"),
-                }
-                prev_key.clear();
-            }
+    /// The values a string arg is restricted to, empty if any value goes.
+    pub fn choices(&self) -> &[String] {
+        match self {
+            Arg::String { settings, .. } => &settings.choices,
+            _ => &[],
         }
+    }
 
-        dbg!(&self.keys);
+    fn is_file_value(&self) -> bool {
+        match self {
+            Arg::String { settings, .. } => settings.file_value,
+            _ => false,
+        }
+    }
 
-        for arg in self.args.iter_mut() {
-            arg.apply_settings()?;
+    fn is_optional(&self) -> bool {
+        match self {
+            Arg::Bool { settings, .. } => settings.optional,
+            Arg::Int { settings, .. } => settings.optional,
+            Arg::String { settings, .. } => settings.optional,
         }
+    }
 
-        Ok(())
+    fn is_bool(&self) -> bool {
+        matches!(self, Arg::Bool { .. })
     }
 
-    const KV_REGEX: &'static str = r#"(((?P<key_l>\s+--\w+)=)|(?P<key_s>\s+-\w+\s+))(?P<val>(\S+)|("[^"]*"))?"#;
+    /// Whether a bool arg was turned off, by `--no-flag` or `--flag=false`
+    /// given last.
+    fn is_off(&self) -> bool {
+        matches!(self, Arg::Bool { vals, .. } if vals.last() == Some(&false))
+    }
 
-    // TODO
-    pub fn parse(&mut self, args_line: &str) -> Result<(), ()> {
-        todo!("Probably not todo");
-        lazy_static! {
-            static ref RE: Regex = Regex::new(CliArgs::KV_REGEX).unwrap();
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            Arg::Bool { settings, .. } => settings.description.as_deref(),
+            Arg::Int { settings, .. } => settings.description.as_deref(),
+            Arg::String { settings, .. } => settings.description.as_deref(),
         }
-        let captures = RE.captures_iter(&args_line);
-
-        for cap in captures {
-            let key = cap.name("key_l").unwrap_or_else(|| cap.name("key_s").unwrap());
-            let val = cap.name("val");
+    }
 
-            let arg = self.get_mut_arg(key.as_str()).map(|a| Ok(a)).unwrap_or(Err(()))?;
-            match arg {
-                Arg::Bool { vals, .. } => vals.push(true),
-                Arg::Int { vals, .. } => vals.push(val.unwrap().as_str().parse().map_err(|_| ())?),
-                Arg::String { vals, .. } => vals.push(val.unwrap().as_str().to_string()),
-            }
+    fn set_description(&mut self, description: &str) {
+        let description = Some(description.to_string());
+        match self {
+            Arg::Bool { settings, .. } => settings.description = description,
+            Arg::Int { settings, .. } => settings.description = description,
+            Arg::String { settings, .. } => settings.description = description,
         }
+    }
 
-        for arg in self.args.iter_mut() {
-            arg.apply_settings()?;
+    /// The environment variable read when the arg is not given.
+    pub fn env(&self) -> Option<&str> {
+        match self {
+            Arg::Bool { settings, .. } => settings.env.as_deref(),
+            Arg::Int { settings, .. } => settings.env.as_deref(),
+            Arg::String { settings, .. } => settings.env.as_deref(),
         }
-
-        Ok(())
     }
 
-    pub fn get_bool(&self, key: &str) -> Result<Option<bool>, ArgError> {
-        self.get_bool_multi(key).map(|vs| vs.get(0).cloned())
+    /// Whether the arg takes values starting with `-`, see [`CliArgs::set_allow_hyphen_values`].
+    fn allows_hyphen_values(&self) -> bool {
+        match self {
+            Arg::Bool { .. } => false,
+            Arg::Int { settings, .. } => settings.hyphen_values,
+            Arg::String { settings, .. } => settings.hyphen_values,
+        }
     }
 
-    pub fn get_int(&self, key: &str) -> Result<Option<i32>, ArgError> {
-        self.get_int_multi(key).map(|vs| vs.get(0).cloned())
+    /// Whether the arg takes every token after its key, see [`CliArgs::set_raw`].
+    fn is_raw(&self) -> bool {
+        match self {
+            Arg::String { settings, .. } => settings.raw,
+            _ => false,
+        }
     }
 
-    pub fn get_string(&self, key: &str) -> Result<Option<String>, ArgError> {
-        self.get_string_multi(key).map(|vs| vs.get(0).cloned())
-    }
-    
-    pub fn get_str(&self, key: &str) -> Result<Option<&str>, ArgError> {
-        self.get_string_multi(key).map(|vs| vs.get(0).map(|s| &**s))
+    /// Whether the arg joins the tokens after its key into one value, see
+    /// [`CliArgs::set_join_rest`].
+    fn joins_rest(&self) -> bool {
+        match self {
+            Arg::String { settings, .. } => settings.join_rest,
+            _ => false,
+        }
     }
-    
-    pub fn unwrap_bool(&self, key: &str) -> bool {
-        self.get_bool(key).unwrap().unwrap()
+
+    /// Whether the arg takes the tokens after its key, see [`CliArgs::set_raw`]
+    /// and [`CliArgs::set_join_rest`].
+    fn takes_rest(&self) -> bool {
+        self.is_raw() || self.joins_rest()
     }
 
-    pub fn unwrap_int(&self, key: &str) -> i32 {
-        self.get_int(key).unwrap().unwrap()
+    /// The value taken when the arg is given without one, see
+    /// [`CliArgs::with_default_missing_value`].
+    pub fn default_missing_value(&self) -> Option<&str> {
+        match self {
+            Arg::Bool { .. } => None,
+            Arg::Int { settings, .. } => settings.default_missing.as_deref(),
+            Arg::String { settings, .. } => settings.default_missing.as_deref(),
+        }
     }
 
-    pub fn unwrap_string(&self, key: &str) -> String {
-        self.get_string(key).unwrap().unwrap()
+    fn has_vals(&self) -> bool {
+        match self {
+            Arg::Bool { vals, .. } => !vals.is_empty(),
+            Arg::Int { vals, .. } => !vals.is_empty(),
+            Arg::String { vals, .. } => !vals.is_empty(),
+        }
     }
 
-    pub fn unwrap_str(&self, key: &str) -> &str {
-        self.get_str(key).unwrap().unwrap()
+    /// Drops all collected values, including applied defaults.
+    pub fn clear_vals(&mut self) {
+        match self {
+            Arg::Bool { vals, .. } => vals.clear(),
+            Arg::Int { vals, .. } => vals.clear(),
+            Arg::String { vals, .. } => vals.clear(),
+        }
     }
 
-    pub fn get_bool_multi(&self, key: &str) -> Result<&[bool], ArgError> {
-        let arg = self.get_arg(key).ok_or(ArgError::WrongKey)?;
-        match arg {
-            Arg::Bool { vals, .. } => Ok(vals),
-            _ => Err(ArgError::WrongType),
+    fn push_val(&mut self, key: &str, val: &str) -> Result<(), ParseError> {
+        let val = self.transformed(val);
+        let val = match self {
+            Arg::String { settings, .. } => settings.canonical(val),
+            _ => val,
+        };
+        let val = &*val;
+        let secret = self.is_secret();
+        let invalid = || match secret {
+            true => ParseError::InvalidSecret { key: key.to_string(), reason: None },
+            false => ParseError::InvalidValue { key: key.to_string(), value: val.to_string() },
+        };
+        self.validate(key, val)?;
+        match self {
+            Arg::Bool { vals, .. } => vals.push(match val {
+                "" => true,
+                _ => bool::from_arg(val).ok_or_else(invalid)?,
+            }),
+            Arg::Int { vals, .. } => vals.push(i32::from_arg(val).ok_or_else(invalid)?),
+            Arg::String { vals, settings } => {
+                let val = settings.resolve_choice(val.to_string()).ok_or_else(invalid)?;
+                if let Some(tuple) = &settings.tuple {
+                    tuple.check(key, &val, secret)?;
+                }
+                settings.check_map_key(key, &val)?;
+                vals.push(val);
+            },
         }
+        Ok(())
     }
 
-    pub fn get_int_multi(&self, key: &str) -> Result<&[i32], ArgError> {
-        let arg = self.get_arg(key).ok_or(ArgError::WrongKey)?;
-        match arg {
-            Arg::Int { vals, .. } => Ok(vals),
-            _ => Err(ArgError::WrongType),
+    /// How given values of the arg are parsed, see [`ValueParser`].
+    fn parser(&self) -> ValueParser {
+        let plain = match self {
+            Arg::Bool { settings, .. } => settings.is_plain(),
+            Arg::Int { settings, .. } => settings.is_plain(),
+            Arg::String { settings, .. } => settings.is_plain(),
+        };
+        match plain {
+            true => ValueParser::Plain,
+            false => ValueParser::Checked,
         }
     }
 
-    pub fn get_string_multi(&self, key: &str) -> Result<&[String], ArgError> {
-        let arg = self.get_arg(key).ok_or(ArgError::WrongKey)?;
-        match arg {
-            Arg::String { vals, .. } => Ok(vals),
-            _ => Err(ArgError::WrongType),
+    /// Converts `val` to the arg's type and pushes it, see [`ValueParser::Plain`].
+    fn push_plain(&mut self, key: &str, val: &str) -> Result<(), ParseError> {
+        let invalid = || ParseError::InvalidValue { key: key.to_string(), value: val.to_string() };
+        match self {
+            Arg::Bool { vals, .. } => vals.push(match val {
+                "" => true,
+                _ => bool::from_arg(val).ok_or_else(invalid)?,
+            }),
+            Arg::Int { vals, .. } => vals.push(i32::from_arg(val).ok_or_else(invalid)?),
+            Arg::String { vals, .. } => vals.push(val.to_string()),
         }
+        Ok(())
     }
+}
+
+/// How [`CliArgs::parse_from`] turns a token into a value of an arg,
+/// decided for every arg at the start of each parse.
+#[derive(Debug, Clone, Copy)]
+enum ValueParser {
+    /// Converted to the arg's type right away, as the arg has nothing to
+    /// transform, resolve or check first.
+    Plain,
+    /// Through [`Arg::push_val`].
+    Checked,
+}
 
-    pub fn unwrap_bool_multi(&self, key: &str) -> &[bool] {
-        self.get_bool_multi(key).unwrap()//.iter().map(|e| e.clone()).collect()
+/// Conversion of a value or default to an arg's type.
+trait FromArg: Sized {
+    fn from_arg(val: &str) -> Option<Self>;
+}
+
+/// `true`, `1` and `yes`, or `false`, `0` and `no`.
+impl FromArg for bool {
+    fn from_arg(val: &str) -> Option<Self> {
+        match val {
+            "true" | "1" | "yes" => Some(true),
+            "false" | "0" | "no" => Some(false),
+            _ => None,
+        }
     }
+}
 
-    pub fn unwrap_int_multi(&self, key: &str) -> &[i32] {
-        self.get_int_multi(key).unwrap()//.iter().map(|e| e.clone()).collect()
+/// Decimal, or hexadecimal, octal and binary with a `0x`, `0o` or `0b`
+/// prefix, each with an optional leading `-`: `-0x1F` is -31. Digits may be
+/// grouped by single underscores between them, as in `1_000_000`.
+impl FromArg for i32 {
+    fn from_arg(val: &str) -> Option<Self> {
+        let (sign, unsigned) = match val.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", val),
+        };
+        let (radix, digits) = match unsigned.get(..2) {
+            Some("0x" | "0X") => (16, &unsigned[2..]),
+            Some("0o" | "0O") => (8, &unsigned[2..]),
+            Some("0b" | "0B") => (2, &unsigned[2..]),
+            _ if sign.is_empty() => (10, unsigned.strip_prefix('+').unwrap_or(unsigned)),
+            _ => (10, unsigned),
+        };
+        if digits.starts_with(['+', '-', '_']) || digits.ends_with('_') {
+            return None;
+        }
+        let digits = match digits.contains('_') {
+            true if digits.contains("__") => return None,
+            true => Cow::Owned(digits.replace('_', "")),
+            false => Cow::Borrowed(digits),
+        };
+        // parsed wider, as the magnitude of `i32::MIN` is no i32
+        let magnitude = i64::from_str_radix(&digits, radix).ok()?;
+        i32::try_from(if sign.is_empty() { magnitude } else { -magnitude }).ok()
     }
+}
 
-    pub fn unwrap_string_multi(&self, key: &str) -> &[String] {
-        self.get_string_multi(key).unwrap()//.iter().map(|e| e.clone()).collect()
+impl FromArg for String {
+    fn from_arg(val: &str) -> Option<Self> {
+        Some(val.to_string())
     }
+}
 
+/// The declared type of an arg, see [`CliArgs::type_of`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArgKind {
+    /// `b` in the schema.
+    Bool,
+    /// `i` in the schema.
+    Int,
+    /// `s` in the schema.
+    String,
+}
 
-    fn is_long_key(s: &str) -> bool {
-        s.starts_with("--")
-    }
+/// How many args a [`CliArgs`] has, see [`CliArgs::summary`]. Displayed as
+/// `5 args: 2 required, 3 optional (1 bool, 2 int, 2 string)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SchemaSummary {
+    pub total: usize,
+    pub required: usize,
+    pub optional: usize,
+    pub bools: usize,
+    pub ints: usize,
+    pub strings: usize,
+}
 
-    fn is_short_key(s: &str) -> bool {
-        s.starts_with("-") && (!s.starts_with("--"))
+impl Display for SchemaSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "{} args: {} required, {} optional ({} bool, {} int, {} string)",
+            self.total, self.required, self.optional, self.bools, self.ints, self.strings,
+        )
     }
+}
 
-    fn get_arg(&self, key: &str) -> Option<&Arg> {
-        self.args.get(*self.keys.get(key)?)
-    }
+#[derive(Debug)]
+pub enum ArgError {
+    WrongKey,
+    WrongType,
+    Missing,
+}
 
-    fn get_mut_arg(&mut self, key: &str) -> Option<&mut Arg> {
-        self.args.get_mut(*self.keys.get(key)?)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    UnknownKey(String),
+    MissingValue(String),
+    TooFewValues { key: String, expected: usize, found: usize },
+    /// `--key=value` for an arg taking a fixed count of more than one value.
+    AttachedValue { key: String, expected: usize },
+    /// A bool `key` last in combined flags with a value, like `-b` in `-ab=c`.
+    FlagValue { key: String, token: String },
+    InvalidValue { key: String, value: String },
+    UnexpectedValue(String),
+    MissingRequired(String),
+    Schema(SchemaError),
+    /// The file named by an `@path` value could not be read.
+    ValueFile { key: String, path: String, reason: String },
+    /// Not a failure: `--version` was given, holds the version text to print.
+    Version(String),
+    /// Not a failure: `--help`, `-h` or the `help` subcommand was given,
+    /// holds the help text to print, see [`CliSubcommands::help`].
+    Help(String),
+    /// Not a failure: the `__complete` protocol was invoked, holds the
+    /// candidates to print one per line, see [`CliArgs::complete`].
+    Completions(Vec<String>),
+    /// Not a failure: `--generate-completions <shell>` was given, holds the
+    /// script to print, see [`CliArgs::with_generate_completions`].
+    CompletionsRequested(String),
+    /// Some but not all members of an [`CliArgs::all_or_none`] group were given.
+    PartialGroup { given: Vec<String>, missing: Vec<String> },
+    /// A quote opened in a [`CliArgs::parse_shell`] line is never closed,
+    /// see [`TokenizeError`].
+    UnclosedQuote(char),
+    /// A value starts with `~` but the home directory is unknown, see [`CliArgs::set_expand_tilde`].
+    NoHome(String),
+    /// A value refers to an environment variable that is not set, see [`UnknownVar::Error`].
+    UnknownVar { key: String, var: String },
+    /// A `key=value` entry with a key not allowed by [`CliArgs::set_map_keys`].
+    UnknownMapKey { key: String, entry: String, allowed: Vec<String> },
+    /// The variable of [`CliSubcommands::default_from_env`] names no subcommand.
+    UnknownCommand { var: String, name: String },
+    /// A value at a level with subcommands that names none of them, with
+    /// the closest name or alias if one is close and all the names.
+    NoSuchCommand { name: String, suggestion: Option<String>, commands: Vec<String> },
+    /// A prefix of several subcommands, see [`CliSubcommands::allow_abbreviations`].
+    AmbiguousCommand { name: String, candidates: Vec<String> },
+    /// A value rejected by a [`CliArgs::with_range`] or [`CliArgs::with_validator`] check.
+    Rejected { key: String, value: String, reason: String },
+    /// A [`CliArgs::with_tuple`] value with `component` not an int, or with
+    /// too few or too many components if `None`.
+    InvalidTuple { key: String, value: String, expected: String, component: Option<String> },
+    /// A value that is empty or only whitespace, see [`CliArgs::set_non_empty`].
+    Empty(String),
+    /// A value shorter than [`CliArgs::set_min_len`], `len` in characters.
+    TooShort { key: String, min: usize, len: usize },
+    /// A value longer than [`CliArgs::set_max_len`], `len` in characters.
+    TooLong { key: String, max: usize, len: usize },
+    /// An invalid value of a [`CliArgs::set_secret`] arg, which is not
+    /// repeated, with the reason if a validator rejected it.
+    InvalidSecret { key: String, reason: Option<String> },
+    /// A token that is not valid UTF-8, shown lossily, see [`NonUtf8::Strict`].
+    NonUtf8(String),
+    /// The value of the environment variable `var` read for `key` is not
+    /// valid UTF-8, see [`NonUtf8::Strict`].
+    NonUtf8Var { key: String, var: String },
+    /// Several invalid values and validation errors found in one parse.
+    Multiple(Vec<ParseError>),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownKey(key) => write!(f, "unknown key `{}`", key),
+            ParseError::NonUtf8(token) => write!(f, "`{}` is not valid UTF-8", token),
+            ParseError::NonUtf8Var { key, var } => write!(f, "`${}` for `{}` is not valid UTF-8", var, key),
+            ParseError::MissingValue(key) => write!(f, "`{}` needs a value", key),
+            ParseError::TooFewValues { key, expected, found } => {
+                write!(f, "`{}` needs {} values but got {}", key, expected, found)
+            },
+            ParseError::FlagValue { key, token } => write!(f, "`{}` in `{}` takes no value", key, token),
+            ParseError::AttachedValue { key, expected } => {
+                write!(f, "`{}` takes {} values as separate tokens, not `{}=..`", key, expected, key)
+            },
+            ParseError::InvalidValue { key, value } => write!(f, "invalid value `{}` for `{}`", value, key),
+            ParseError::UnexpectedValue(value) => write!(f, "unexpected value `{}`", value),
+            ParseError::MissingRequired(key) => write!(f, "missing required `{}`", key),
+            ParseError::Schema(e) => write!(f, "{}", e),
+            ParseError::Version(version) => write!(f, "{}", version),
+            ParseError::Help(help) => write!(f, "{}", help),
+            ParseError::Completions(candidates) => write!(f, "{}", candidates.join("\n")),
+            ParseError::CompletionsRequested(script) => write!(f, "{}", script),
+            ParseError::ValueFile { key, path, reason } => {
+                write!(f, "cannot read `{}` for `{}`: {}", path, key, reason)
+            },
+            ParseError::PartialGroup { given, missing } => {
+                write!(f, "`{}` given without `{}`, they must be given together", given.join("`, `"), missing.join("`, `"))
+            },
+            ParseError::UnclosedQuote(quote) => write!(f, "unclosed quote `{}`", quote),
+            ParseError::NoHome(key) => write!(f, "cannot expand `~` for `{}`, the home directory is unknown", key),
+            ParseError::UnknownVar { key, var } => write!(f, "`{}` refers to `${}`, which is not set", key, var),
+            ParseError::Rejected { key, value, reason } => write!(f, "invalid value `{}` for `{}`: {}", value, key, reason),
+            ParseError::InvalidTuple { key, value, expected, component: Some(component) } => {
+                write!(f, "invalid `{}` in `{}` for `{}`, expected {}", component, value, key, expected)
+            },
+            ParseError::InvalidTuple { key, value, expected, component: None } => {
+                write!(f, "invalid value `{}` for `{}`, expected {}", value, key, expected)
+            },
+            ParseError::UnknownMapKey { key, entry, allowed } => {
+                let allowed: Vec<String> = allowed.iter().map(|key| format!("`{key}`")).collect();
+                write!(f, "unknown key `{}` in `{}`, expected one of {}", entry, key, allowed.join(", "))
+            },
+            ParseError::UnknownCommand { var, name } => write!(f, "`${}` names `{}`, which is no subcommand", var, name),
+            ParseError::NoSuchCommand { name, suggestion: None, commands } => {
+                let commands: Vec<String> = commands.iter().map(|name| format!("`{name}`")).collect();
+                write!(f, "unknown subcommand `{}`, expected one of {}", name, commands.join(", "))
+            },
+            ParseError::NoSuchCommand { name, suggestion: Some(suggestion), .. } => {
+                write!(f, "unknown subcommand `{}`, did you mean `{}`?", name, suggestion)
+            },
+            ParseError::AmbiguousCommand { name, candidates } => {
+                let candidates: Vec<String> = candidates.iter().map(|name| format!("`{name}`")).collect();
+                write!(f, "`{}` could be any of {}", name, candidates.join(", "))
+            },
+            ParseError::Empty(key) => write!(f, "`{}` must not be empty", key),
+            ParseError::TooShort { key, min, len } => {
+                write!(f, "`{}` needs at least {} characters, got {}", key, min, len)
+            },
+            ParseError::TooLong { key, max, len } => {
+                write!(f, "`{}` takes at most {} characters, got {}", key, max, len)
+            },
+            ParseError::InvalidSecret { key, reason: None } => write!(f, "invalid value for secret `{}`", key),
+            ParseError::InvalidSecret { key, reason: Some(reason) } => {
+                write!(f, "invalid value for secret `{}`: {}", key, reason)
+            },
+            ParseError::Multiple(errors) => {
+                write!(f, "{} errors:", errors.len())?;
+                for e in errors {
+                    write!(f, "\n  - {}", e)?;
+                }
+                Ok(())
+            },
+        }
     }
+}
 
-    // const SCHEMA_REGEX: &'static str = r#"((?P<kl>--[\w_-]+)|(?P<ks>-[\w_-]+)|(?P<kls>--[\w_-]+/-[\w_-]+))=(?P<type>[bis])\??(:(?P<default_val>.+))?"#;
-    const SCHEMA_REGEX: &'static str = r#"((?P<kl>--[\w_-]+)|(?P<ks>-[\w_-]+)|(?P<kls>--[\w_-]+/-[\w_-]+))=(?P<type>[bis])\??"#;
+impl Error for ParseError {}
 
-    fn parse_schema(schema: &str) -> (Option<String>, Option<String>, Arg) {
-        let split = schema.split_once("::>");
-        let mut default_val: Option<String> = None;
-        if let Some((_, default_val_0)) = split {
-            default_val = Some(default_val_0.to_string());
-        }
-        let schema: String = schema.split_whitespace().collect();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaError {
+    Malformed(String),
+    InvalidDefault { schema: String, default: String },
+    UnknownType(char),
+    MissingKeyPrefix(String),
+    UnknownKey(String),
+    MultiCharShortKey(String),
+    /// A key is registered twice, as the long or short key of two args, or
+    /// a key registered by the crate itself, like `-v` by [`CliArgs::with_verbosity`], is already taken.
+    KeyTaken(String),
+    /// A setting was attached to an arg of a type it does not apply to, like a range to a string.
+    WrongType(String),
+    /// A [`Tuple`] without a separator or with other than 2 or 3 components.
+    InvalidTuple(String),
+}
 
-        lazy_static! {
-            static ref RE: Regex = Regex::new(CliArgs::SCHEMA_REGEX).unwrap();
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Malformed(schema) => write!(f, "malformed schema `{}`", schema),
+            SchemaError::InvalidDefault { schema, default } => {
+                write!(f, "default `{}` does not match the type of schema `{}`", default, schema)
+            },
+            SchemaError::UnknownType(code) => write!(f, "unknown type code `{}`, expected one of `b`, `i`, `s`", code),
+            SchemaError::MissingKeyPrefix(schema) => {
+                write!(f, "schema `{}` has no key, keys start with `--` (long) or `-` (short)", schema)
+            },
+            SchemaError::UnknownKey(key) => write!(f, "no arg is registered for key `{}`", key),
+            SchemaError::MultiCharShortKey(key) => {
+                write!(f, "short key `{}` has more than one character, use a long `--` key or allow multi-char short keys", key)
+            },
+            SchemaError::KeyTaken(key) => write!(f, "key `{}` is already registered", key),
+            SchemaError::WrongType(key) => write!(f, "arg `{}` has the wrong type for this setting", key),
+            SchemaError::InvalidTuple(key) => {
+                write!(f, "tuple of `{}` needs a separator and 2 or 3 components", key)
+            },
         }
-        let captures = RE.captures(&schema).unwrap();
-        let kls = captures.name("kls");
-        let kl = captures.name("kl");
-        let ks = captures.name("ks");
-        let arg_type = captures.name("type").unwrap();
-        let optional = captures.name("optional");
-        //let default_val = captures.name("default_val");
+    }
+}
 
-        let to_string_op_t = |(s1, s2): (&str, &str)| {
-            (Some(s1.to_string()), Some(s2.to_string()))
-        };
+impl Error for SchemaError {}
 
-        let (key_l, key_s) = match kls {
-            Some(kls) => to_string_op_t(kls.as_str().split_once("/").unwrap()),
-            None => (kl.map(|s| s.as_str().to_string()),
-                    ks.map(|s| s.as_str().to_string())),
-        };
+/// The typed getters shared by [`CliArgs`] and its [`ArgValues`] snapshot,
+/// both of which provide `vals(&self, key) -> Result<Vals, ArgError>`.
+macro_rules! typed_getters {
+    ($ty:ty) => {
+        impl $ty {
+            /// `None` only if the flag was not given and has no default,
+            /// `Some(false)` for `--no-flag` and `--flag=false`. The last
+            /// value if given more than once, so `--no-flag` overrides a
+            /// `--flag` given before it, as by a shell alias.
+            pub fn get_bool(&self, key: &str) -> Result<Option<bool>, ArgError> {
+                self.get_bool_last(key)
+            }
 
-        let optional = optional.map_or(false, |_| true);
-        let mut arg = match arg_type.as_str() {
-            "b" => {
-                Arg::Bool {
-                    vals: Vec::new(),
-                    settings: ArgSettings {
-                        optional,
-                        default_val: default_val.map(|d| d.as_str().parse().unwrap())
-                    },
+            /// The first value if given more than once, see [`Self::get_int_last`].
+            pub fn get_int(&self, key: &str) -> Result<Option<i32>, ArgError> {
+                self.get_int_first(key)
+            }
+
+            /// The first value if given more than once, see [`Self::get_string_last`].
+            pub fn get_string(&self, key: &str) -> Result<Option<String>, ArgError> {
+                self.get_string_first(key)
+            }
+
+            /// The first value if given more than once, see [`Self::get_str_last`].
+            pub fn get_str(&self, key: &str) -> Result<Option<&str>, ArgError> {
+                self.get_str_first(key)
+            }
+
+            /// The value given first, so a `--flag` before a `--no-flag` wins,
+            /// see [`Self::get_bool_last`].
+            pub fn get_bool_first(&self, key: &str) -> Result<Option<bool>, ArgError> {
+                self.get_bool_multi(key).map(|vs| vs.first().cloned())
+            }
+
+            /// The value given last, for "the last one wins" when e.g. a
+            /// wrapper script passes defaults ahead of the user's args.
+            pub fn get_bool_last(&self, key: &str) -> Result<Option<bool>, ArgError> {
+                self.get_bool_multi(key).map(|vs| vs.last().cloned())
+            }
+
+            /// The value given first, see [`Self::get_bool_first`].
+            pub fn get_int_first(&self, key: &str) -> Result<Option<i32>, ArgError> {
+                self.get_int_multi(key).map(|vs| vs.first().cloned())
+            }
+
+            /// The value given last, see [`Self::get_bool_last`].
+            pub fn get_int_last(&self, key: &str) -> Result<Option<i32>, ArgError> {
+                self.get_int_multi(key).map(|vs| vs.last().cloned())
+            }
+
+            /// The value given first, see [`Self::get_bool_first`].
+            pub fn get_string_first(&self, key: &str) -> Result<Option<String>, ArgError> {
+                self.get_string_multi(key).map(|vs| vs.first().cloned())
+            }
+
+            /// The value given last, see [`Self::get_bool_last`].
+            pub fn get_string_last(&self, key: &str) -> Result<Option<String>, ArgError> {
+                self.get_string_multi(key).map(|vs| vs.last().cloned())
+            }
+
+            /// The value given first, see [`Self::get_bool_first`].
+            pub fn get_str_first(&self, key: &str) -> Result<Option<&str>, ArgError> {
+                self.get_string_multi(key).map(|vs| vs.first().map(|s| &**s))
+            }
+
+            /// The value given last, see [`Self::get_bool_last`].
+            pub fn get_str_last(&self, key: &str) -> Result<Option<&str>, ArgError> {
+                self.get_string_multi(key).map(|vs| vs.last().map(|s| &**s))
+            }
+
+            /// The value at `n` in command line order, of any arg type:
+            ///
+            /// ```
+            /// # use clitrs::args::CliArgs;
+            /// let mut args = CliArgs::new();
+            /// args.with("--tag=s*").parse("--tag a b c").unwrap();
+            /// assert_eq!(args.get_nth::<String>("--tag", 1).unwrap().map(String::as_str), Some("b"));
+            /// assert_eq!(args.get_nth::<String>("--tag", 3).unwrap(), None);
+            /// ```
+            pub fn get_nth<T: ArgValue>(&self, key: &str, n: usize) -> Result<Option<&T>, ArgError> {
+                let vals = T::of(self.vals(key)?).ok_or(ArgError::WrongType)?;
+                Ok(vals.get(n))
+            }
+
+            /// Panics if the key is unknown, of another type or has no value.
+            pub fn unwrap_bool(&self, key: &str) -> bool {
+                self.get_bool(key).unwrap().unwrap()
+            }
+
+            /// Panics if the key is unknown, of another type or has no value.
+            pub fn unwrap_int(&self, key: &str) -> i32 {
+                self.get_int(key).unwrap().unwrap()
+            }
+
+            /// Panics if the key is unknown, of another type or has no value.
+            pub fn unwrap_string(&self, key: &str) -> String {
+                self.get_string(key).unwrap().unwrap()
+            }
+
+            /// Panics if the key is unknown, of another type or has no value.
+            pub fn unwrap_str(&self, key: &str) -> &str {
+                self.get_str(key).unwrap().unwrap()
+            }
+
+            /// Like [`Self::get_bool`], with an absent value as [`ArgError::Missing`].
+            pub fn require_bool(&self, key: &str) -> Result<bool, ArgError> {
+                self.get_bool(key)?.ok_or(ArgError::Missing)
+            }
+
+            /// Like [`Self::get_int`], with an absent value as [`ArgError::Missing`].
+            pub fn require_int(&self, key: &str) -> Result<i32, ArgError> {
+                self.get_int(key)?.ok_or(ArgError::Missing)
+            }
+
+            /// Like [`Self::get_string`], with an absent value as [`ArgError::Missing`].
+            pub fn require_string(&self, key: &str) -> Result<String, ArgError> {
+                self.get_string(key)?.ok_or(ArgError::Missing)
+            }
+
+            /// Like [`Self::get_str`], with an absent value as [`ArgError::Missing`].
+            pub fn require_str(&self, key: &str) -> Result<&str, ArgError> {
+                self.get_str(key)?.ok_or(ArgError::Missing)
+            }
+
+            /// How many values the arg has, for a flag how often it was
+            /// given, e.g. 3 for `-vvv`.
+            pub fn count(&self, key: &str) -> Result<usize, ArgError> {
+                Ok(match self.vals(key)? {
+                    Vals::Bool(vals) => vals.len(),
+                    Vals::Int(vals) => vals.len(),
+                    Vals::String(vals) => vals.len(),
+                })
+            }
+
+            pub fn get_bool_multi(&self, key: &str) -> Result<&[bool], ArgError> {
+                match self.vals(key)? {
+                    Vals::Bool(vals) => Ok(vals),
+                    _ => Err(ArgError::WrongType),
                 }
-            },
-            "i" => {
-                Arg::Int {
-                    vals: Vec::new(),
-                    settings: ArgSettings {
-                        optional,
-                        default_val: default_val.map(|d| d.as_str().parse().unwrap())
-                    },
+            }
+
+            /// All values of an int arg, e.g. both components of a `{2}` arg:
+            ///
+            /// ```
+            /// # use clitrs::args::CliArgs;
+            /// let mut args = CliArgs::new();
+            /// args.with("--point=i{2}").parse("--point 3 7").unwrap();
+            /// assert_eq!(args.get_int_multi("--point").unwrap(), [3, 7]);
+            /// ```
+            pub fn get_int_multi(&self, key: &str) -> Result<&[i32], ArgError> {
+                match self.vals(key)? {
+                    Vals::Int(vals) => Ok(vals),
+                    _ => Err(ArgError::WrongType),
                 }
-            },
-            "s" => {
-                Arg::String {
-                    vals: Vec::new(),
-                    settings: ArgSettings {
-                        optional,
-                        default_val: default_val.map(|d| d.as_str().parse().unwrap())
-                    },
+            }
+
+            pub fn get_string_multi(&self, key: &str) -> Result<&[String], ArgError> {
+                match self.vals(key)? {
+                    Vals::String(vals) => Ok(vals),
+                    _ => Err(ArgError::WrongType),
                 }
-            },
-            _ => panic!("Parse error"),
-        };
+            }
 
-        (key_l, key_s, arg)
-    }
+            /// Panics if the key is unknown or of another type.
+            pub fn unwrap_bool_multi(&self, key: &str) -> &[bool] {
+                self.get_bool_multi(key).unwrap()//.iter().map(|e| e.clone()).collect()
+            }
+
+            /// Panics if the key is unknown or of another type.
+            pub fn unwrap_int_multi(&self, key: &str) -> &[i32] {
+                self.get_int_multi(key).unwrap()//.iter().map(|e| e.clone()).collect()
+            }
+
+            /// Panics if the key is unknown or of another type.
+            pub fn unwrap_string_multi(&self, key: &str) -> &[String] {
+                self.get_string_multi(key).unwrap()//.iter().map(|e| e.clone()).collect()
+            }
+        }
+    };
 }
 
-#[cfg(test)]
-mod tests {
-    use super::{CliArgs, ArgError};
+/// A type args hold values of, `bool`, `i32` or `String`, see [`CliArgs::get_nth`].
+pub trait ArgValue: sealed::Sealed {}
 
+impl ArgValue for bool {}
+impl ArgValue for i32 {}
+impl ArgValue for String {}
 
-    #[test]
-    fn cli_args_use() {
-        let cmd_line = "";
-        let mut args = CliArgs::new();
-        args
-            .with("--name/-n=s")
-            .with("--age/-a = i? ::>18")    
-            .with("--adult=b?")    
-            .parse(cmd_line)
-            .unwrap();
+mod sealed {
+    /// Borrowed values of a single arg.
+    pub enum Vals<'a> {
+        Bool(&'a [bool]),
+        Int(&'a [i32]),
+        String(&'a [String]),
+    }
+
+    pub trait Sealed: Sized {
+        /// The values if they are of this type.
+        fn of(vals: Vals<'_>) -> Option<&[Self]>;
+    }
+
+    impl Sealed for bool {
+        fn of(vals: Vals<'_>) -> Option<&[Self]> {
+            match vals {
+                Vals::Bool(vals) => Some(vals),
+                _ => None,
+            }
+        }
+    }
+
+    impl Sealed for i32 {
+        fn of(vals: Vals<'_>) -> Option<&[Self]> {
+            match vals {
+                Vals::Int(vals) => Some(vals),
+                _ => None,
+            }
+        }
+    }
 
-        let name = args.get_str("--name");
-        let age = args.get_int("-a");
-        let is_adult = args.get_bool("--adult");
-        dbg!(name);
-        dbg!(age);
-        dbg!(is_adult);
+    impl Sealed for String {
+        fn of(vals: Vals<'_>) -> Option<&[Self]> {
+            match vals {
+                Vals::String(vals) => Some(vals),
+                _ => None,
+            }
+        }
     }
+}
+
+/// One entry of [`CliArgs::ordered`]. Flags have no value, every value of a
+/// multi-value arg is its own entry, and tokens after `--` have no key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderedArg {
+    pub key: Option<String>,
+    pub value: Option<String>,
+}
 
-}
\ No newline at end of file
+/// Which args a parse took from the command line or environment and which
+/// fell back to their defaults, see [`CliArgs::parse_with_summary`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseSummary {
+    pub defaulted: Vec<String>,
+    pub provided: Vec<String>,
+}
+
+#[derive(Default, Clone)]
+pub struct CliArgs {
+    keys: Keys,
+    display_keys: Vec<String>, // per arg, its longest key, see `CliArgs::display_key`
+    parsers: Vec<ValueParser>, // per arg, for the parse at hand
+    args: Vec<Arg>,
+    trailing: Vec<String>,
+    ordered: Vec<OrderedArg>,
+    unknown: Vec<String>,
+    all_or_none: Vec<Vec<usize>>,
+    implications: Vec<(usize, usize, String)>, // given arg, implied arg and its value
+    given: Vec<bool>, // per arg, whether the last parse had a value before defaults
+    ignore_unknown: bool,
+    extra_positionals: bool,
+    positionals: Vec<usize>, // arg indices, in declaration order
+    remaining: Vec<String>,
+    record_order: bool,
+    multi_char_short: bool,
+    hide_env: bool,
+    expand_env: Option<UnknownVar>,
+    non_utf8: NonUtf8,
+    warnings: Vec<String>,
+    color_option: bool,
+    exit_codes: Option<ExitCodes>,
+    generate_completions: bool,
+    stdin: Option<String>,
+    schema_error: Option<SchemaError>,
+    examples: Vec<Example>,
+    meta: Meta,
+}
+
+impl CliArgs {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers an arg from a schema string such as `--name/-n=s`.
+    ///
+    /// The type code is one of `b`, `i` or `s`, ints also taking `0x`, `0o`
+    /// and `0b` prefixed values like `0xFF` and `_` separated digits like
+    /// `1_000`, optionally followed by `*`
+    /// to make the arg greedy (`--files=s*` collects `--files a b c`) or by
+    /// `{N}` to take exactly N values (`--point=i{2}` takes `--point 3 7`),
+    /// and `?` to make it optional. A default value may follow `::>`, and a
+    /// description may follow ` # `: `--age/-a=i? ::>18 # age in years`.
+    ///
+    /// An invalid schema is not registered; the first such error is kept
+    /// and returned by every subsequent parse.
+    pub fn with(&mut self, schema: &str) -> &mut Self {
+        let (key_l, key_s, arg_base) = match Self::parse_schema(schema) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.schema_error.get_or_insert(e);
+                return self;
+            },
+        };
+        self.register(key_l, key_s, arg_base)
+    }
+
+    /// Registers an arg like [`CliArgs::with`], with `help` as its description
+    /// instead of a `# description` in the schema.
+    pub fn with_help(&mut self, schema: &str, help: &str) -> &mut Self {
+        let registered = self.args.len();
+        self.with(schema);
+        if let Some(arg) = self.args.get_mut(registered) {
+            arg.set_description(help);
+        }
+        self
+    }
+
+    /// The description of the arg registered for `key`.
+    pub fn description(&self, key: &str) -> Result<Option<&str>, ArgError> {
+        self.get_arg(key).map(Arg::description).ok_or(ArgError::WrongKey)
+    }
+
+    /// The values of an int arg per occurrence, e.g. `[[1, 2], [3, 4]]` for
+    /// `--point 1 2 --point 3 4` of a `{2}` arg. Without a fixed count
+    /// every value is a group of its own.
+    pub fn get_int_groups(&self, key: &str) -> Result<Vec<&[i32]>, ArgError> {
+        let n = self.get_arg(key).and_then(Arg::nargs).unwrap_or(1);
+        self.get_int_multi(key).map(|vals| vals.chunks(n).collect())
+    }
+
+    /// The values of a string arg per occurrence, see [`CliArgs::get_int_groups`].
+    pub fn get_string_groups(&self, key: &str) -> Result<Vec<&[String]>, ArgError> {
+        let n = self.get_arg(key).and_then(Arg::nargs).unwrap_or(1);
+        self.get_string_multi(key).map(|vals| vals.chunks(n).collect())
+    }
+
+    /// Lets an int or string arg take its values verbatim when they start
+    /// with `-`, as in `--pattern -foo` or `--exec -v`, even if the token is
+    /// a registered key. Only the values the arg is waiting for are taken
+    /// this way, any other key is parsed as usual. The trade-off is that a
+    /// forgotten value takes the next key instead of being reported: with
+    /// `--pattern --verbose`, `--verbose` is the pattern. A greedy arg
+    /// takes every following token up to a `--`, which always terminates.
+    pub fn set_allow_hyphen_values(&mut self, key: &str, allow: bool) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        match &mut self.args[ind] {
+            Arg::Bool { .. } => return Err(ArgError::WrongType),
+            Arg::Int { settings, .. } => settings.hyphen_values = allow,
+            Arg::String { settings, .. } => settings.hyphen_values = allow,
+        }
+        Ok(())
+    }
+
+    /// Makes a string arg take every token after its key verbatim and in
+    /// order, keys and `--` included, as for `--cmd ssh -p 2222 host`. The
+    /// first value may be attached, `--cmd=ssh -p 2222 host` is the same.
+    /// Nothing after the key is parsed, so the arg must come last on the
+    /// command line. The arg becomes greedy, shown as `--cmd <ARGS>...` in help.
+    pub fn set_raw(&mut self, key: &str, raw: bool) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        let Arg::String { settings, .. } = &mut self.args[ind] else {
+            return Err(ArgError::WrongType);
+        };
+        settings.raw = raw;
+        if raw {
+            (settings.greedy, settings.nargs) = (true, None);
+        }
+        Ok(())
+    }
+
+    /// Makes a string arg take every token after its key up to a `--` or the
+    /// end of the command line, joined by spaces into a single value, as for
+    /// `--message the rest of the line`. Keys among them are taken as words,
+    /// and the tokens after the `--` are [`CliArgs::trailing`]. Unlike a
+    /// greedy arg, which keeps each token a value of its own, the choices,
+    /// validators and other checks see the joined value.
+    pub fn set_join_rest(&mut self, key: &str, join_rest: bool) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        let Arg::String { settings, .. } = &mut self.args[ind] else {
+            return Err(ArgError::WrongType);
+        };
+        settings.join_rest = join_rest;
+        if join_rest {
+            (settings.greedy, settings.nargs) = (true, None);
+        }
+        Ok(())
+    }
+
+    /// The declared type of the arg registered under `key`, `None` for an
+    /// unknown key.
+    pub fn type_of(&self, key: &str) -> Option<ArgKind> {
+        Some(match self.get_arg(key)? {
+            Arg::Bool { .. } => ArgKind::Bool,
+            Arg::Int { .. } => ArgKind::Int,
+            Arg::String { .. } => ArgKind::String,
+        })
+    }
+
+    /// Counts the registered args, positionals included, by whether they are
+    /// optional and by type.
+    pub fn summary(&self) -> SchemaSummary {
+        let mut summary = SchemaSummary { total: self.args.len(), ..SchemaSummary::default() };
+        for arg in &self.args {
+            match arg.is_optional() {
+                true => summary.optional += 1,
+                false => summary.required += 1,
+            }
+            match arg {
+                Arg::Bool { .. } => summary.bools += 1,
+                Arg::Int { .. } => summary.ints += 1,
+                Arg::String { .. } => summary.strings += 1,
+            }
+        }
+        summary
+    }
+
+    /// The long and short keys of the arg registered under `key`, either of
+    /// them, `None` for a form it does not have or an unknown key:
+    /// `--port/-p` gives `(Some("--port"), Some("-p"))` for `-p` as well.
+    pub fn keys_of(&self, key: &str) -> (Option<String>, Option<String>) {
+        let Some(&ind) = self.keys.get(key) else {
+            return (None, None);
+        };
+        let (long, short) = self.arg_keys(ind);
+        (long.map(str::to_string), short.filter(|key| Self::is_short_key(key)).map(str::to_string))
+    }
+
+    /// Whether the last parse took a value for `key` from the command line or
+    /// the environment, which a default does not count as. True for an arg
+    /// given bare and taking its [`CliArgs::with_default_missing_value`].
+    pub fn is_present(&self, key: &str) -> Result<bool, ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        Ok(self.given.get(ind).copied().unwrap_or(false))
+    }
+
+    /// Lets an int or string arg be given bare, taking `value` then: with
+    /// `--backup=s?` and `simple`, `--backup` is `simple` and
+    /// `--backup=numbered` is `numbered`. A value must then be attached, the
+    /// next token is never taken. A value not of the arg's type, an unknown
+    /// key and a bool are schema errors, see [`CliArgs::schema_error`].
+    ///
+    /// ```
+    /// # use clitrs::args::CliArgs;
+    /// let mut args = CliArgs::new();
+    /// args.with("--backup=s?").with_default_missing_value("--backup", "simple");
+    /// args.parse("--backup").unwrap();
+    /// assert_eq!(args.get_str("--backup").unwrap(), Some("simple"));
+    /// ```
+    pub fn with_default_missing_value(&mut self, key: &str, value: &str) -> &mut Self {
+        let Some(&ind) = self.keys.get(key) else {
+            self.schema_error.get_or_insert(SchemaError::UnknownKey(key.to_string()));
+            return self;
+        };
+        let default_missing = Some(value.to_string());
+        match &mut self.args[ind] {
+            Arg::Bool { .. } => {
+                self.schema_error.get_or_insert(SchemaError::WrongType(key.to_string()));
+            },
+            Arg::Int { .. } if i32::from_arg(value).is_none() => {
+                self.schema_error.get_or_insert(SchemaError::InvalidDefault { schema: key.to_string(), default: value.to_string() });
+            },
+            Arg::Int { settings, .. } => settings.default_missing = default_missing,
+            Arg::String { settings, .. } => settings.default_missing = default_missing,
+        }
+        self
+    }
+
+    /// Makes a string arg read `@path` values from the file at `path`,
+    /// replacing the value with the trimmed file contents. `@-` reads stdin
+    /// to the end, which fails if stdin is a terminal.
+    pub fn set_file_value(&mut self, key: &str, file_value: bool) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        match &mut self.args[ind] {
+            Arg::String { settings, .. } => settings.file_value = file_value,
+            _ => return Err(ArgError::WrongType),
+        }
+        Ok(())
+    }
+
+    /// Reads the arg from the environment variable `var` when it is not
+    /// given on the command line. A set variable takes precedence over the
+    /// default. Flags are set by any value but the empty string.
+    pub fn set_env(&mut self, key: &str, var: &str) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        let var = Some(var.to_string());
+        match &mut self.args[ind] {
+            Arg::Bool { settings, .. } => settings.env = var,
+            Arg::Int { settings, .. } => settings.env = var,
+            Arg::String { settings, .. } => settings.env = var,
+        }
+        Ok(())
+    }
+
+    /// Restricts a string arg to the given choices. With `ignore_case` a
+    /// value matches a choice regardless of case and is stored in the
+    /// declared casing, e.g. `HIGH` is stored as `high`.
+    pub fn set_choices(&mut self, key: &str, choices: &[&str], ignore_case: bool) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        match &mut self.args[ind] {
+            Arg::String { settings, .. } => {
+                settings.choices = choices.iter().map(|c| c.to_string()).collect();
+                settings.ignore_case = ignore_case;
+            },
+            _ => return Err(ArgError::WrongType),
+        }
+        Ok(())
+    }
+
+    /// Stores `canonical` when a string arg is given `alias`, e.g. `grey`
+    /// as `gray`. The alias is rewritten after transforms and before
+    /// validators and choices, so only the canonical value needs to be a
+    /// choice. An unknown key or an arg of another type is a schema error.
+    pub fn value_alias(&mut self, key: &str, alias: &str, canonical: &str) -> &mut Self {
+        let Some(&ind) = self.keys.get(key) else {
+            self.schema_error.get_or_insert(SchemaError::UnknownKey(key.to_string()));
+            return self;
+        };
+        match &mut self.args[ind] {
+            Arg::String { settings, .. } => settings.value_aliases.push((alias.to_string(), canonical.to_string())),
+            _ => {
+                self.schema_error.get_or_insert(SchemaError::WrongType(key.to_string()));
+            },
+        }
+        self
+    }
+
+    /// Tells shell completion what kind of value the arg takes. Args without
+    /// a hint complete like the shell does by default, usually as files.
+    pub fn set_value_hint(&mut self, key: &str, hint: ValueHint) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        match &mut self.args[ind] {
+            Arg::Bool { settings, .. } => settings.value_hint = Some(hint),
+            Arg::Int { settings, .. } => settings.value_hint = Some(hint),
+            Arg::String { settings, .. } => settings.value_hint = Some(hint),
+        }
+        Ok(())
+    }
+
+    /// Makes every occurrence of an int or string arg take exactly `n`
+    /// values, like the `{N}` schema suffix: `--range 10 20` for 2. A value
+    /// may start with `-` as long as it is no registered key, so `--range
+    /// -10 20` works. A count of 0 lifts the limit again.
+    pub fn set_num_values(&mut self, key: &str, n: usize) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        let nargs = (n > 0).then_some(n);
+        match &mut self.args[ind] {
+            Arg::Bool { .. } => return Err(ArgError::WrongType),
+            Arg::Int { settings, .. } => (settings.nargs, settings.greedy) = (nargs, false),
+            Arg::String { settings, .. } => (settings.nargs, settings.greedy) = (nargs, false),
+        }
+        Ok(())
+    }
+
+    /// Leaves the default of an arg out of the help output, e.g. for secrets.
+    pub fn set_hide_default(&mut self, key: &str, hide: bool) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        match &mut self.args[ind] {
+            Arg::Bool { settings, .. } => settings.hide_default = hide,
+            Arg::Int { settings, .. } => settings.hide_default = hide,
+            Arg::String { settings, .. } => settings.hide_default = hide,
+        }
+        Ok(())
+    }
+
+    /// Marks an arg as carrying a token or password. Its values and default
+    /// are `***` in `Debug` output, its default is left out of help and
+    /// [`CliArgs::schema_json`], errors about it do not repeat the value,
+    /// and [`CliArgs::set_expand_env`] does not touch it. A leading `~` is
+    /// still expanded under [`CliArgs::set_expand_tilde`], which reads no
+    /// variable the value could leak through.
+    pub fn set_secret(&mut self, key: &str, secret: bool) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        match &mut self.args[ind] {
+            Arg::Bool { settings, .. } => settings.secret = secret,
+            Arg::Int { settings, .. } => settings.secret = secret,
+            Arg::String { settings, .. } => settings.secret = secret,
+        }
+        Ok(())
+    }
+
+    /// Leaves the `[env: ..]` annotations out of the help output.
+    pub fn hide_env(&mut self, hide: bool) -> &mut Self {
+        self.hide_env = hide;
+        self
+    }
+
+    /// Reads `reader` to the end and uses it in place of stdin for the next
+    /// `@-` value, see [`CliArgs::set_file_value`].
+    pub fn set_stdin(&mut self, mut reader: impl Read) -> io::Result<()> {
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+        self.stdin = Some(input);
+        Ok(())
+    }
+
+    /// Registers an optional bool flag without going through a schema string,
+    /// e.g. `flag("--verbose", Some('v'))` is `with("--verbose/-v=b?")`.
+    pub fn flag(&mut self, long: &str, short: Option<char>) -> &mut Self {
+        self.option(long, short, 'b')
+    }
+
+    /// Registers an optional arg of the given type code (`b`, `i` or `s`)
+    /// without going through a schema string. A `long` without a name after
+    /// its `--` or a `short` of `-` is a [`SchemaError::MissingKeyPrefix`].
+    pub fn option(&mut self, long: &str, short: Option<char>, type_code: char) -> &mut Self {
+        if !long.starts_with("--") || Self::is_bare_key(long) {
+            self.schema_error.get_or_insert(SchemaError::MissingKeyPrefix(long.to_string()));
+            return self;
+        }
+        if short == Some('-') {
+            self.schema_error.get_or_insert(SchemaError::MissingKeyPrefix("--".to_string()));
+            return self;
+        }
+        let arg = match type_code {
+            'b' => Arg::Bool { vals: Vec::with_capacity(1), settings: ArgSettings { optional: true, ..Default::default() } },
+            'i' => Arg::Int { vals: Vec::with_capacity(1), settings: ArgSettings { optional: true, ..Default::default() } },
+            's' => Arg::String { vals: Vec::with_capacity(1), settings: ArgSettings { optional: true, ..Default::default() } },
+            _ => {
+                self.schema_error.get_or_insert(SchemaError::UnknownType(type_code));
+                return self;
+            },
+        };
+        self.register(Some(long.to_string()), short.map(|c| format!("-{}", c)), arg)
+    }
+
+    /// Requires the args keyed by `members` to be given either all together
+    /// or not at all, e.g. `--tls-cert` and `--tls-key`. Checked after each
+    /// parse, a value from the environment counts as given, a default does not.
+    pub fn all_or_none(&mut self, members: &[&str]) -> &mut Self {
+        let mut group = Vec::with_capacity(members.len());
+        for key in members {
+            match self.keys.get(key) {
+                Some(ind) => group.push(*ind),
+                None => {
+                    self.schema_error.get_or_insert(SchemaError::UnknownKey(key.to_string()));
+                    return self;
+                },
+            }
+        }
+        self.all_or_none.push(group);
+        self
+    }
+
+    /// Gives `implied_key` the `value` when `key` is given and it is not, in
+    /// place of its default: `--debug` implying `--log-level=debug`. A bool
+    /// `key` turned off, as by `--no-debug`, implies nothing. A value
+    /// from the environment counts as given for both, and the implied value
+    /// does not make [`CliArgs::is_present`] true. An unknown key or a value
+    /// the implied arg does not take is a schema error.
+    pub fn implies(&mut self, key: &str, implied_key: &str, value: &str) -> &mut Self {
+        let (Some(&ind), Some(&implied)) = (self.keys.get(key), self.keys.get(implied_key)) else {
+            let unknown = if self.keys.contains_key(key) { implied_key } else { key };
+            self.schema_error.get_or_insert(SchemaError::UnknownKey(unknown.to_string()));
+            return self;
+        };
+        let mut probe = self.args[implied].clone();
+        probe.clear_vals();
+        if probe.push_val(implied_key, value).is_err() {
+            self.schema_error.get_or_insert(SchemaError::InvalidDefault { schema: implied_key.to_string(), default: value.to_string() });
+            return self;
+        }
+        self.implications.push((ind, implied, value.to_string()));
+        self
+    }
+
+    /// Collects unknown keys and values that belong to no key into
+    /// [`CliArgs::unknown`] instead of failing the parse. The value of an
+    /// unknown key cannot be told apart and is collected after it.
+    pub fn ignore_unknown(&mut self, ignore: bool) -> &mut Self {
+        self.ignore_unknown = ignore;
+        self
+    }
+
+    /// Collects values that belong to no key into [`CliArgs::trailing`],
+    /// as if given after `--`, while unknown keys stay errors. A middle
+    /// ground for tools passing extra operands through, stricter than
+    /// [`CliArgs::ignore_unknown`]. With declared positionals, those left
+    /// over go to [`CliArgs::remaining_positionals`] instead.
+    pub fn allow_extra_positionals(&mut self, allow: bool) -> &mut Self {
+        self.extra_positionals = allow;
+        self
+    }
+
+    /// Allows short keys longer than one character, like `-foo`, for args
+    /// registered after this call. They are rejected by default since `-foo`
+    /// reads like the combined flags `-f -o -o`.
+    pub fn allow_multi_char_short(&mut self, allow: bool) -> &mut Self {
+        self.multi_char_short = allow;
+        self
+    }
+
+    fn register(&mut self, key_l: Option<String>, key_s: Option<String>, arg: Arg) -> &mut Self {
+        if let Some(key_s) = &key_s {
+            if !self.multi_char_short && key_s.chars().count() > 2 {
+                self.schema_error.get_or_insert(SchemaError::MultiCharShortKey(key_s.clone()));
+                return self;
+            }
+        }
+        // a short key may look long with multi-char short keys, `-a/--name`
+        // registers `--name` as short, so check every key against all others
+        if let Some(key) = [&key_l, &key_s].into_iter().flatten().find(|key| self.keys.contains_key(key.as_str())) {
+            self.schema_error.get_or_insert(SchemaError::KeyTaken(key.clone()));
+            return self;
+        }
+        let ind = self.args.len();
+
+        let display_key = [&key_l, &key_s].into_iter().flatten().max_by_key(|k| (k.len(), *k)).cloned();
+        self.display_keys.push(display_key.unwrap_or_default());
+        if let Some(key_s) = key_s {
+            self.keys.insert(key_s, ind);
+        }
+        if let Some(key_l) = key_l {
+            self.keys.insert(key_l, ind);
+        }
+        self.args.push(arg);
+
+        self
+    }
+
+    pub fn parse_cmd(&mut self) -> Result<(), ParseError> {
+        let args_vec: Vec<String> = env::args().collect();
+
+        if args_vec.is_empty() {
+            return Ok(());
+        }
+
+        let f = File::open(&args_vec[0]);
+        let mut start = 0;
+        if f.is_ok() {
+            start = 1; // first arg is the program path, skip it
+        }
+
+        self.parse_from(&args_vec[start..])
+    }
+
+    /// Parses already split tokens, without a leading program path.
+    ///
+    /// Long keys take their value either attached (`--age=18`) or as the
+    /// next token (`--age 18`), short keys also attached (`-a18`, `-a=18`).
+    /// Single character flags can be combined, `-vq` is `-v -q`. A token
+    /// that is no registered key is read by its first character: a short
+    /// key taking a value takes the rest as its value, a bool expands the
+    /// token as combined flags, so with a bool `-v` the token `-v5` is an
+    /// unknown key rather than `-v` with a value. With `=`, as in `-vn=5`,
+    /// every character before it is a flag and the last one takes the
+    /// value, so all but the last must be bools and the last must not be
+    /// one, [`ParseError::FlagValue`]. Bools
+    /// never take the next token: `--verbose true` is the flag followed by a
+    /// stray `true`, which is [`ParseError::UnexpectedValue`] (or collected
+    /// by [`CliArgs::ignore_unknown`]); write `--verbose=true` or just `--verbose`.
+    /// A long bool is set false by `--verbose=false` or `--no-verbose`. Greedy
+    /// args collect every following bare token until the next key or `--`.
+    /// Everything after the first `--` is collected into [`CliArgs::trailing`].
+    ///
+    /// Malformed input never panics, it is reported as a [`ParseError`].
+    /// Errors that leave the remaining tokens meaningless, like an unknown
+    /// key, stop parsing. Invalid values and missing required args do not,
+    /// all of them are returned together as [`ParseError::Multiple`].
+    ///
+    /// `__complete <shell> <words>..` is answered with the completion
+    /// candidates for the last word as [`ParseError::Completions`], and
+    /// `--generate-completions <shell>` with the script as
+    /// [`ParseError::CompletionsRequested`] once registered.
+    pub fn parse_from(&mut self, args: &[String]) -> Result<(), ParseError> {
+        if let Some(e) = &self.schema_error {
+            return Err(ParseError::Schema(e.clone()));
+        }
+        if let [first, _shell, words @ ..] = args {
+            if first == "__complete" {
+                return Err(ParseError::Completions(self.complete(words)));
+            }
+        }
+        if let Some(script) = self.requested_completions(args) {
+            return Err(ParseError::CompletionsRequested(script));
+        }
+
+        self.parsers.clear();
+        self.parsers.extend(self.args.iter().map(Arg::parser));
+        let mut errors: Vec<(usize, ParseError)> = Vec::new(); // reported together after all tokens
+        let mut tokenizer = Tokenizer::new(args);
+        let mut filled = 0; // declared positionals that took a value
+        let mut joined: Option<(Cow<str>, usize, String)> = None; // words of a `CliArgs::set_join_rest` arg so far
+        while let Some(event) = tokenizer.next(self) {
+            if let Indexed::Value { key, value, ind } = event {
+                if self.args[ind].joins_rest() {
+                    match &mut joined {
+                        Some((_, _, words)) => {
+                            words.push(' ');
+                            words.push_str(value);
+                        },
+                        None => joined = Some((self.owned_event_key(key, ind), ind, value.to_string())),
+                    }
+                    continue;
+                }
+            }
+            if let Some((key, ind, words)) = joined.take() {
+                self.push_val(&key, ind, &words, &mut errors);
+            }
+            match event {
+                Indexed::Flag { key, ind } => {
+                    let key = self.owned_event_key(key, ind);
+                    let missing = self.args[ind].default_missing_value().map(str::to_owned);
+                    self.push_val(&key, ind, missing.as_deref().unwrap_or(""), &mut errors)
+                },
+                Indexed::Value { key, value, ind } => {
+                    let key = self.owned_event_key(key, ind);
+                    self.push_val(&key, ind, value, &mut errors)
+                },
+                Indexed::Positional { value } | Indexed::Trailing { value } if filled < self.positionals.len() => {
+                    let ind = self.positionals[filled];
+                    filled += 1;
+                    let key = self.display_key(ind).to_string();
+                    self.push_val(&key, ind, value, &mut errors)
+                },
+                Indexed::Positional { value } if !self.extra_positionals => {
+                    self.tolerate(value, ParseError::UnexpectedValue(value.to_string()))?
+                },
+                Indexed::Positional { value } if !self.positionals.is_empty() => self.remaining.push(value.to_string()),
+                Indexed::Positional { value } | Indexed::Trailing { value } => {
+                    self.record(None, Some(value));
+                    self.trailing.push(value.to_string());
+                },
+                Indexed::Error { token, error: error @ ParseError::UnknownKey(_) } => self.tolerate(token, error)?,
+                Indexed::Error { error, .. } => return Err(error),
+            }
+        }
+        if let Some((key, ind, words)) = joined {
+            self.push_val(&key, ind, &words, &mut errors);
+        }
+
+        let mut given = Vec::with_capacity(self.args.len()); // before defaults are applied
+        for ind in 0..self.args.len() {
+            match self.env_fallback(ind) {
+                Ok(Some(val)) => {
+                    if let Err(e) = self.args[ind].push_val(&self.display_keys[ind], &val) {
+                        errors.push((ind, e));
+                    }
+                },
+                Ok(None) => {},
+                Err(e) => errors.push((ind, e)),
+            }
+            let had_invalid = errors.iter().any(|(i, _)| *i == ind);
+            given.push(had_invalid || self.args[ind].has_vals());
+            if self.args[ind].apply_settings().is_err() && !had_invalid {
+                errors.push((ind, ParseError::MissingRequired(self.display_key(ind).to_string())));
+            }
+            if !given[ind] {
+                if let Err(e) = self.args[ind].check_defaults(&self.display_keys[ind]) {
+                    errors.push((ind, e));
+                }
+            }
+            if let Err(e) = self.expand_vals(ind) {
+                errors.push((ind, e));
+            }
+            self.args[ind].post_transform();
+        }
+        for (ind, implied, value) in &self.implications {
+            if !given[*ind] || self.args[*ind].is_off() || given[*implied] {
+                continue;
+            }
+            errors.retain(|(i, e)| !(i == implied && matches!(e, ParseError::MissingRequired(_))));
+            let arg = &mut self.args[*implied];
+            arg.clear_vals();
+            match arg.push_val(&self.display_keys[*implied], value) {
+                Ok(()) => arg.post_transform(),
+                Err(e) => errors.push((*implied, e)),
+            }
+        }
+        for group in &self.all_or_none {
+            let (present, absent): (Vec<usize>, Vec<usize>) = group.iter().partition(|ind| given[**ind]);
+            if !present.is_empty() && !absent.is_empty() {
+                errors.push((absent[0], ParseError::PartialGroup {
+                    given: present.iter().map(|ind| self.display_key(*ind).to_string()).collect(),
+                    missing: absent.iter().map(|ind| self.display_key(*ind).to_string()).collect(),
+                }));
+            }
+        }
+        self.given = given;
+
+        let mut errors: Vec<ParseError> = errors.into_iter().map(|(_, e)| e).collect();
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(ParseError::Multiple(errors)),
+        }
+    }
+
+    /// Parses the process arguments like [`CliArgs::parse_cmd`], and on error
+    /// prints it (every error if there are several) and exits, see [`CliArgs::exit_with`].
+    pub fn parse_or_exit(&mut self) {
+        if let Err(e) = self.parse_cmd() {
+            self.exit_with(e);
+        }
+    }
+
+    /// Parses the known keys and their values and hands everything else
+    /// back verbatim and in order, for a subparser to continue with: unknown
+    /// keys, values no key takes and, from `--` on, the trailing tokens.
+    ///
+    /// ```
+    /// # use clitrs::args::CliArgs;
+    /// let mut global = CliArgs::new();
+    /// global.with("--verbose/-v=b?");
+    /// let tokens: Vec<String> = ["-v", "commit", "-m", "msg"].map(String::from).into();
+    /// let rest = global.parse_partial(&tokens).unwrap();
+    /// assert_eq!(rest, ["commit", "-m", "msg"]);
+    /// ```
+    pub fn parse_partial(&mut self, args: &[String]) -> Result<Vec<String>, ParseError> {
+        let ignore_unknown = mem::replace(&mut self.ignore_unknown, true);
+        let unknown = mem::take(&mut self.unknown);
+        let trailing = mem::take(&mut self.trailing);
+        let res = self.parse_from(args);
+        self.ignore_unknown = ignore_unknown;
+        let mut rest = mem::replace(&mut self.unknown, unknown);
+        let rest_trailing = mem::replace(&mut self.trailing, trailing);
+        res?;
+
+        if args.iter().any(|t| t == "--") {
+            rest.push("--".to_string());
+            rest.extend(rest_trailing);
+        }
+        Ok(rest)
+    }
+
+    /// Same as [`CliArgs::parse_from`], additionally reporting which args
+    /// were provided and which got their default, by their longest key.
+    ///
+    /// ```
+    /// # use clitrs::args::CliArgs;
+    /// let mut args = CliArgs::new();
+    /// args.with("--port=i ::>8080").with("--host=s");
+    /// let tokens: Vec<String> = ["--host", "localhost"].map(String::from).into();
+    /// let summary = args.parse_with_summary(&tokens).unwrap();
+    /// for key in &summary.defaulted {
+    ///     println!("using default for {key}");
+    /// }
+    /// # assert_eq!(summary.defaulted, ["--port"]);
+    /// ```
+    pub fn parse_with_summary(&mut self, args: &[String]) -> Result<ParseSummary, ParseError> {
+        self.parse_from(args)?;
+        let mut summary = ParseSummary::default();
+        for ind in 0..self.args.len() {
+            match (self.given[ind], self.args[ind].has_vals()) {
+                (true, _) => summary.provided.push(self.display_key(ind).to_string()),
+                (false, true) => summary.defaulted.push(self.display_key(ind).to_string()),
+                (false, false) => {},
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Same as [`CliArgs::parse_from`], additionally recording every key and
+    /// value in the order they appear, see [`CliArgs::ordered`].
+    pub fn parse_interleaved(&mut self, args: &[String]) -> Result<(), ParseError> {
+        self.record_order = true;
+        let res = self.parse_from(args);
+        self.record_order = false;
+        res
+    }
+
+    /// Parses a whitespace separated command line.
+    pub fn parse(&mut self, args_line: &str) -> Result<(), ParseError> {
+        self.parse_from(&Self::split_line(args_line))
+    }
+
+    /// Splits a command line into tokens for [`CliArgs::parse`].
+    #[doc(hidden)]
+    pub fn split_line(args_line: &str) -> Vec<String> {
+        args_line.split_whitespace().map(String::from).collect()
+    }
+
+    /// Parses a command line split into words the way a POSIX shell would,
+    /// unlike [`CliArgs::parse`] which splits at every whitespace:
+    ///
+    /// ```
+    /// # use clitrs::args::CliArgs;
+    /// let mut args = CliArgs::new();
+    /// args.with("--name=s").with("--path=s");
+    /// args.parse_shell(r#"--name "Alp Yilmaz" --path my\ files/'a b'"#).unwrap();
+    /// assert_eq!(args.get_str("--name").unwrap(), Some("Alp Yilmaz"));
+    /// assert_eq!(args.get_str("--path").unwrap(), Some("my files/a b"));
+    /// ```
+    ///
+    /// The line is split by [`tokenize`], see there for the quoting rules.
+    pub fn parse_shell(&mut self, line: &str) -> Result<(), ParseError> {
+        self.parse_from(&tokenize(line)?)
+    }
+
+    /// Replaces the default values of the given args, keyed by any of their
+    /// keys, with the values transformed (see [`CliArgs::add_transform`]) and
+    /// parsed to each arg's type. Meant to be called
+    /// before parsing, so environment specific defaults stay out of schemas.
+    /// Either every default is applied or, if one is invalid, none is and
+    /// the error is about the first invalid one by key.
+    pub fn apply_defaults(&mut self, defaults: &HashMap<String, String>) -> Result<(), SchemaError> {
+        self.args = self.with_defaults(self.args.clone(), defaults)?;
+        Ok(())
+    }
+
+    /// `args` with `defaults` applied, see [`CliArgs::apply_defaults`].
+    fn with_defaults(&self, mut args: Vec<Arg>, defaults: &HashMap<String, String>) -> Result<Vec<Arg>, SchemaError> {
+        let mut defaults: Vec<_> = defaults.iter().collect();
+        defaults.sort();
+        for (key, default) in defaults {
+            let ind = *self.keys.get(key.as_str()).ok_or_else(|| SchemaError::UnknownKey(key.clone()))?;
+            let schema = self.display_key(ind);
+            let default = Some(args[ind].transformed(default).into_owned());
+            match &mut args[ind] {
+                Arg::Bool { settings, .. } => settings.default_val = Self::parse_default(schema, default)?,
+                Arg::Int { settings, .. } => settings.default_val = Self::parse_default(schema, default)?,
+                Arg::String { settings, .. } => settings.default_val = Self::parse_default(schema, default)?,
+            }
+        }
+        Ok(args)
+    }
+
+    /// An owned copy of the parsed values, without the settings, that can be
+    /// sent to other threads and queried with the same typed getters.
+    pub fn values_snapshot(&self) -> ArgValues {
+        ArgValues {
+            keys: self.keys.clone(),
+            vals: self.args.iter().map(|arg| match arg {
+                Arg::Bool { vals, .. } => OwnedVals::Bool(vals.clone()),
+                Arg::Int { vals, .. } => OwnedVals::Int(vals.clone()),
+                Arg::String { vals, .. } => OwnedVals::String(vals.clone()),
+            }).collect(),
+            secret: self.args.iter().map(Arg::is_secret).collect(),
+            trailing: self.trailing.clone(),
+        }
+    }
+
+    /// Clears everything collected by a previous parse while keeping the
+    /// registered args, so the same `CliArgs` can parse another command line.
+    ///
+    /// Parsing appends to the collected values, without a reset the values
+    /// and defaults of the previous parse would still be there.
+    pub fn reset(&mut self) {
+        for arg in self.args.iter_mut() {
+            arg.clear_vals();
+        }
+        self.trailing.clear();
+        self.remaining.clear();
+        self.unknown.clear();
+        self.ordered.clear();
+        self.given.clear();
+        self.warnings.clear();
+    }
+
+    /// Clears the values of the arg under `key` only, its defaults included,
+    /// e.g. in a REPL where the other args carry over to the next command.
+    /// The next parse appends to the values the other args kept.
+    pub fn clear(&mut self, key: &str) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        self.args[ind].clear_vals();
+        if let Some(given) = self.given.get_mut(ind) {
+            *given = false;
+        }
+        let keys = &self.keys;
+        self.ordered.retain(|entry| entry.key.as_deref().and_then(|key| keys.get(key)) != Some(&ind));
+        Ok(())
+    }
+
+    /// The first error met while registering schemas, if any.
+    pub fn schema_error(&self) -> Option<&SchemaError> {
+        self.schema_error.as_ref()
+    }
+
+    /// Checks a batch of schemas as [`CliArgs::with`] would register them,
+    /// keys taken twice included, returning every error in order instead
+    /// of only the first. Meant for the tests of crates keeping schemas as
+    /// strings:
+    ///
+    /// ```
+    /// # use clitrs::args::{CliArgs, SchemaError};
+    /// assert!(CliArgs::assert_schema(&["--name/-n=s", "--age=i? ::>18"]).is_ok());
+    /// assert_eq!(CliArgs::assert_schema(&["--age=x"]), Err(vec![SchemaError::Malformed("--age=x".to_string())]));
+    /// ```
+    pub fn assert_schema(schemas: &[&str]) -> Result<(), Vec<SchemaError>> {
+        let mut args = CliArgs::new();
+        let errors: Vec<SchemaError> = schemas.iter()
+            .filter_map(|schema| args.with(schema).schema_error.take())
+            .collect();
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// Tokens given after the `--` terminator, in order. Only the first `--`
+    /// terminates, a later one is kept as a literal value. Also holds the
+    /// values without a key under [`CliArgs::allow_extra_positionals`].
+    pub fn trailing(&self) -> &[String] {
+        &self.trailing
+    }
+
+    /// Takes the trailing tokens out, leaving the buffer empty.
+    pub fn take_trailing(&mut self) -> Vec<String> {
+        mem::take(&mut self.trailing)
+    }
+
+    /// Unknown keys and values without a key, in order, collected instead
+    /// of failing when [`CliArgs::ignore_unknown`] is set.
+    pub fn unknown(&self) -> &[String] {
+        &self.unknown
+    }
+
+    /// Takes the unknown tokens out, leaving the buffer empty.
+    pub fn take_unknown(&mut self) -> Vec<String> {
+        mem::take(&mut self.unknown)
+    }
+
+    /// Keys and values in command line order, recorded by [`CliArgs::parse_interleaved`].
+    pub fn ordered(&self) -> &[OrderedArg] {
+        &self.ordered
+    }
+
+    fn is_long_key(s: &str) -> bool {
+        s.starts_with("--") && s != "--"
+    }
+
+    fn is_short_key(s: &str) -> bool {
+        s.starts_with('-') && (!s.starts_with("--"))
+    }
+
+    /// Whether a key is only its prefix, like `--`, which can never be
+    /// matched as `--` ends the options.
+    fn is_bare_key(s: &str) -> bool {
+        s.chars().all(|c| c == '-')
+    }
+
+    fn lookup(&self, key: &str) -> Result<usize, ParseError> {
+        self.keys.get(key).copied().ok_or_else(|| ParseError::UnknownKey(key.to_string()))
+    }
+
+    /// Collects a token that does not fit the schema when unknown tokens are ignored.
+    fn tolerate(&mut self, token: &str, e: ParseError) -> Result<(), ParseError> {
+        if !self.ignore_unknown {
+            return Err(e);
+        }
+        self.unknown.push(token.to_string());
+        Ok(())
+    }
+
+    /// Pushes a value (`""` for a flag), keeping an invalid one in `errors`
+    /// so the remaining tokens are still parsed.
+    fn push_val(&mut self, key: &str, ind: usize, val: &str, errors: &mut Vec<(usize, ParseError)>) {
+        let res = match val.strip_prefix('@') {
+            Some(path) if self.args[ind].is_file_value() => self.read_value_file(key, path)
+                .and_then(|contents| self.args[ind].push_val(key, &contents)),
+            _ => match self.parsers[ind] {
+                ValueParser::Plain => self.args[ind].push_plain(key, val),
+                ValueParser::Checked => self.args[ind].push_val(key, val),
+            },
+        };
+        match res {
+            Ok(()) => self.record(Some(key), if self.args[ind].is_bool() { None } else { Some(val) }),
+            Err(e) => errors.push((ind, e)),
+        }
+    }
+
+    /// The trimmed contents of the file at `path`, or of stdin for `-`.
+    fn read_value_file(&mut self, key: &str, path: &str) -> Result<String, ParseError> {
+        let contents = match path {
+            "-" => self.read_stdin(),
+            _ => fs::read_to_string(path),
+        };
+        contents
+            .map(|contents| contents.trim().to_string())
+            .map_err(|e| ParseError::ValueFile { key: key.to_string(), path: path.to_string(), reason: e.to_string() })
+    }
+
+    /// Reads stdin to the end, or takes the input given to [`CliArgs::set_stdin`].
+    /// Fails if stdin is a terminal, as that would wait for the user silently.
+    fn read_stdin(&mut self) -> io::Result<String> {
+        if let Some(input) = self.stdin.take() {
+            return Ok(input);
+        }
+        if term::is_tty(Stream::Stdin) {
+            return Err(io::Error::other("stdin is a terminal"));
+        }
+        let mut input = String::new();
+        io::stdin().lock().read_to_string(&mut input)?;
+        Ok(input)
+    }
+
+    /// The value of the arg's environment variable, if the arg was not given.
+    /// Flags read as given (`""`) unless the variable is empty.
+    fn env_fallback(&mut self, ind: usize) -> Result<Option<String>, ParseError> {
+        let arg = &self.args[ind];
+        let Some(var) = arg.env().filter(|_| !arg.has_vals()).map(str::to_string) else {
+            return Ok(None);
+        };
+        let is_bool = arg.is_bool();
+        let key = self.display_key(ind).to_string();
+        let Some(val) = self.env_var(&var, &key)? else {
+            return Ok(None);
+        };
+        Ok(match is_bool {
+            true if val.is_empty() => None,
+            true => Some(String::new()),
+            false => Some(val),
+        })
+    }
+
+    fn record(&mut self, key: Option<&str>, value: Option<&str>) {
+        if self.record_order {
+            self.ordered.push(OrderedArg { key: key.map(str::to_string), value: value.map(str::to_string) });
+        }
+    }
+
+    fn vals(&self, key: &str) -> Result<Vals<'_>, ArgError> {
+        let arg = self.get_arg(key).ok_or(ArgError::WrongKey)?;
+        Ok(match arg {
+            Arg::Bool { vals, .. } => Vals::Bool(vals),
+            Arg::Int { vals, .. } => Vals::Int(vals),
+            Arg::String { vals, .. } => Vals::String(vals),
+        })
+    }
+
+    fn get_arg(&self, key: &str) -> Option<&Arg> {
+        self.args.get(*self.keys.get(key)?)
+    }
+
+    /// The long and short keys registered for the arg at `ind`.
+    fn arg_keys(&self, ind: usize) -> (Option<&str>, Option<&str>) {
+        let mut keys = (None, None);
+        for (key, _) in self.keys.iter().filter(|(_, i)| **i == ind) {
+            if Self::is_long_key(key) {
+                keys.0 = Some(key);
+            }
+            else {
+                keys.1 = Some(key);
+            }
+        }
+        keys
+    }
+
+    /// The key of a parse event, or for a flag of a combined token like
+    /// `-vq` the short key of the arg at `ind`, see [`Indexed`].
+    fn event_key<'k>(&'k self, key: Option<&'k str>, ind: usize) -> &'k str {
+        key.or(self.arg_keys(ind).1).unwrap_or_default()
+    }
+
+    /// [`CliArgs::event_key`], copied only for a flag of a combined token
+    /// so that the args can be changed while the key is held.
+    fn owned_event_key<'k>(&self, key: Option<&'k str>, ind: usize) -> Cow<'k, str> {
+        key.map_or_else(|| Cow::Owned(self.event_key(None, ind).to_string()), Cow::Borrowed)
+    }
+
+    /// The longest key registered for the arg at `ind`, used in error messages.
+    fn display_key(&self, ind: usize) -> &str {
+        &self.display_keys[ind]
+    }
+
+    fn parse_schema(schema: &str) -> Result<(Option<String>, Option<String>, Arg), SchemaError> {
+        let schema_str = schema;
+        let (schema, description) = Self::split_description(schema);
+        let (head, default_val) = match schema.split_once("::>") {
+            Some((head, default_val)) => (head, Some(default_val.to_string())),
+            None => (schema, None),
+        };
+        let head: Cow<str> = if head.contains(char::is_whitespace) {
+            Cow::Owned(head.split_whitespace().collect())
+        } else {
+            Cow::Borrowed(head)
+        };
+
+        let keys = head.split_once('=').map_or(&*head, |(keys, _)| keys);
+        if keys.split('/').any(|key| !key.starts_with('-') || Self::is_bare_key(key)) {
+            return Err(SchemaError::MissingKeyPrefix(schema_str.to_string()));
+        }
+
+        let malformed = || SchemaError::Malformed(schema_str.to_string());
+        let SchemaParts { key_l, key_s, type_code, greedy, nargs, optional } = split_schema(&head).ok_or_else(malformed)?;
+        let nargs = match nargs {
+            Some(n) => match n.parse() {
+                Ok(0) | Err(_) => return Err(malformed()),
+                Ok(n) => Some(n),
+            },
+            None => None,
+        };
+        let key_l = key_l.map(str::to_string);
+        let key_s = key_s.map(str::to_string);
+
+        // vals are sized at registration so parsing a single value does not allocate
+        let capacity = if greedy { 4 } else { nargs.unwrap_or(1) };
+        // the settings are the same for every type apart from the type of the default
+        fn settings<T: Debug>(optional: bool, default_val: Option<T>, greedy: bool, nargs: Option<usize>, description: Option<String>) -> ArgSettings<T> {
+            ArgSettings { optional, default_val, greedy, nargs, description, ..Default::default() }
+        }
+        let arg = match type_code {
+            'b' => Arg::Bool {
+                vals: Vec::with_capacity(capacity),
+                settings: settings(optional, Self::parse_default(schema_str, default_val)?, false, None, description),
+            },
+            'i' => Arg::Int {
+                vals: Vec::with_capacity(capacity),
+                settings: settings(optional, Self::parse_default(schema_str, default_val)?, greedy, nargs, description),
+            },
+            's' => Arg::String {
+                vals: Vec::with_capacity(capacity),
+                settings: settings(optional, Self::parse_default(schema_str, default_val)?, greedy, nargs, description),
+            },
+            _ => return Err(malformed()),
+        };
+
+        Ok((key_l, key_s, arg))
+    }
+
+    /// Splits off a trailing ` # description`. It starts at the first `#`
+    /// preceded by whitespace, so it always comes after a default, and a
+    /// default may contain `#` as long as no whitespace precedes it.
+    fn split_description(schema: &str) -> (&str, Option<String>) {
+        let mut prev_ws = false;
+        for (i, c) in schema.char_indices() {
+            if c == '#' && prev_ws {
+                return (schema[..i].trim_end(), Some(schema[i + 1..].trim().to_string()));
+            }
+            prev_ws = c.is_whitespace();
+        }
+        (schema, None)
+    }
+
+    fn parse_default<T: FromArg>(schema: &str, default_val: Option<String>) -> Result<Option<T>, SchemaError> {
+        default_val
+            .map(|d| T::from_arg(&d).ok_or_else(|| SchemaError::InvalidDefault { schema: schema.to_string(), default: d }))
+            .transpose()
+    }
+}
+
+/// Types that know how to register their args, usually through
+/// `#[derive(CliSchema)]` with the `derive` feature.
+pub trait CliSchema {
+    fn register(args: &mut CliArgs);
+
+    /// A new `CliArgs` with only this type's args registered.
+    fn cli() -> CliArgs {
+        let mut args = CliArgs::new();
+        Self::register(&mut args);
+        args
+    }
+}
+
+#[cfg(feature = "derive")]
+pub use clitrs_derive::CliSchema;
+
+/// Declares a type whose associated constants are the lookup keys of the
+/// given schemas, so that registration and lookup share one constant and a
+/// misspelled key fails to compile. The type implements [`CliSchema`]:
+///
+/// ```
+/// use clitrs::args::CliSchema;
+///
+/// clitrs::declare_flags! {
+///     pub struct Flags {
+///         NAME => "--name/-n=s",
+///         VERBOSE => "-v=b?",
+///     }
+/// }
+///
+/// let mut args = Flags::cli();
+/// args.parse("-n alp -v").unwrap();
+/// assert_eq!(Flags::NAME, "--name");
+/// assert_eq!(args.get_str(Flags::NAME).unwrap(), Some("alp"));
+/// assert_eq!(args.get_bool(Flags::VERBOSE).unwrap(), Some(true));
+/// ```
+///
+/// The constant is the long key, or the short key without one. Keys must
+/// be written without spaces inside them.
+#[macro_export]
+macro_rules! declare_flags {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$flag_meta:meta])* $flag:ident => $schema:literal),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name;
+
+        impl $name {
+            $($(#[$flag_meta])* $vis const $flag: &'static str = $crate::args::schema_key($schema);)*
+        }
+
+        impl $crate::args::CliSchema for $name {
+            fn register(args: &mut $crate::args::CliArgs) {
+                $(args.with($schema);)*
+            }
+        }
+    };
+}
+
+/// The key a schema is looked up by: its first key, evaluated at compile
+/// time by [`declare_flags!`].
+#[doc(hidden)]
+pub const fn schema_key(schema: &str) -> &str {
+    let bytes = schema.as_bytes();
+    let mut start = 0;
+    while start < bytes.len() && bytes[start].is_ascii_whitespace() {
+        start += 1;
+    }
+    let mut end = start;
+    while end < bytes.len() && !matches!(bytes[end], b'/' | b'=' | b' ' | b'\t' | b'\n') {
+        end += 1;
+    }
+    let (head, _) = bytes.split_at(end);
+    let (_, key) = head.split_at(start);
+    match std::str::from_utf8(key) {
+        Ok(key) => key,
+        Err(_) => panic!("schema key is not valid UTF-8"),
+    }
+}
+
+typed_getters!(CliArgs);
+
+/// Parsed values taken out of a [`CliArgs`] by [`CliArgs::values_snapshot`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct ArgValues {
+    keys: Keys,
+    vals: Vec<OwnedVals>,
+    secret: Vec<bool>,
+    trailing: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OwnedVals {
+    Bool(Vec<bool>),
+    Int(Vec<i32>),
+    String(Vec<String>),
+}
+
+impl ArgValues {
+    /// Tokens given after the `--` terminator, in order.
+    pub fn trailing(&self) -> &[String] {
+        &self.trailing
+    }
+
+    fn vals(&self, key: &str) -> Result<Vals<'_>, ArgError> {
+        let vals = self.keys.get(key).and_then(|ind| self.vals.get(*ind)).ok_or(ArgError::WrongKey)?;
+        Ok(match vals {
+            OwnedVals::Bool(vals) => Vals::Bool(vals),
+            OwnedVals::Int(vals) => Vals::Int(vals),
+            OwnedVals::String(vals) => Vals::String(vals),
+        })
+    }
+}
+
+typed_getters!(ArgValues);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use proptest::prelude::*;
+    use super::{ArgError, ArgKind, CliArgs, CliSchema, OrderedArg, ParseError, ParseSummary, SchemaError, SchemaSummary};
+    use crate::{assert_parse_err, assert_parses};
+    use crate::testing::NoValue;
+
+
+    #[test]
+    fn cli_args_use() {
+        let mut args = CliArgs::new();
+        args
+            .with("--name/-n=s")
+            .with("--age/-a = i? ::>18")
+            .with("--adult=b?");
+        assert_parses!(args, "-n alp", {
+            "--name" => "alp",
+            "-a" => 18,
+            "--adult" => NoValue,
+        });
+    }
+
+    #[test]
+    fn greedy_stops_at_next_key() {
+        let mut args = CliArgs::new();
+        args
+            .with("--files/-f=s*")
+            .with("--nums=i*?")
+            .with("--verbose/-v=b?")
+            .with("--out=s?");
+        assert_parses!(args, ["--files", "a", "b", "c", "-v", "--nums", "1", "2", "--out", "x", "--", "y", "z"], {
+            "--files" => ["a", "b", "c"],
+            "--nums" => [1, 2],
+            "--out" => "x",
+            "-v" => true,
+        });
+        assert_eq!(args.trailing(), ["y", "z"]);
+    }
+
+    #[test]
+    fn non_greedy_takes_single_value() {
+        let mut args = CliArgs::new();
+        args.with("--out/-o=s");
+        assert_parse_err!(args, ["-o", "x", "y"], ParseError::UnexpectedValue(v) if v == "y");
+    }
+
+
+    #[test]
+    fn invalid_schema_is_reported() {
+        let mut args = CliArgs::new();
+        args
+            .with("--name/-n=s")
+            .with("--age=x")
+            .with("--count=i ::>many");
+        assert_eq!(args.schema_error(), Some(&SchemaError::Malformed("--age=x".to_string())));
+        assert_eq!(
+            args.parse("-n alp"),
+            Err(ParseError::Schema(SchemaError::Malformed("--age=x".to_string())))
+        );
+
+        let mut args = CliArgs::new();
+        args.with("--count=i ::>many");
+        assert_eq!(
+            args.schema_error(),
+            Some(&SchemaError::InvalidDefault { schema: "--count=i ::>many".to_string(), default: "many".to_string() })
+        );
+    }
+
+    #[test]
+    fn bool_default_literals() {
+        let mut args = CliArgs::new();
+        args.with("--color=b? ::>yes").with("--quiet=b? ::>0");
+        assert_eq!(args.schema_error(), None);
+        args.parse("").unwrap();
+        assert_eq!((args.get_bool("--color").unwrap(), args.get_bool("--quiet").unwrap()), (Some(true), Some(false)));
+
+        let mut args = CliArgs::new();
+        args.with("--flag=b ::>maybe");
+        assert_eq!(
+            args.schema_error(),
+            Some(&SchemaError::InvalidDefault { schema: "--flag=b ::>maybe".to_string(), default: "maybe".to_string() })
+        );
+    }
+
+    #[test]
+    fn flag_and_option_helpers() {
+        let mut args = CliArgs::new();
+        args
+            .flag("--verbose", Some('v'))
+            .flag("--dry-run", None)
+            .option("--name", Some('n'), 's')
+            .option("--count", None, 'i');
+        assert_parses!(args, ["-v", "--name", "alp", "--count=3"], {
+            "--verbose" => true,
+            "--dry-run" => NoValue,
+            "-n" => "alp",
+            "--count" => 3,
+        });
+
+        let mut args = CliArgs::new();
+        args.option("--ratio", None, 'f');
+        assert_eq!(args.schema_error(), Some(&SchemaError::UnknownType('f')));
+    }
+
+    #[test]
+    fn unprefixed_schema_key() {
+        let mut args = CliArgs::new();
+        args.with("name=s");
+        let err = args.schema_error().unwrap();
+        assert_eq!(err, &SchemaError::MissingKeyPrefix("name=s".to_string()));
+        assert_eq!(err.to_string(), "schema `name=s` has no key, keys start with `--` (long) or `-` (short)");
+
+        let mut args = CliArgs::new();
+        args.with("--name/n=s");
+        assert_eq!(args.schema_error(), Some(&SchemaError::MissingKeyPrefix("--name/n=s".to_string())));
+
+        let mut args = CliArgs::new();
+        args.flag("verbose", Some('v'));
+        assert_eq!(args.schema_error(), Some(&SchemaError::MissingKeyPrefix("verbose".to_string())));
+
+        // a bare prefix is no key, `--` ends the options
+        for schema in ["--=s?", "-=b?", "--x/--=i", "---=s"] {
+            let mut args = CliArgs::new();
+            args.with(schema);
+            assert_eq!(args.schema_error(), Some(&SchemaError::MissingKeyPrefix(schema.to_string())), "{schema}");
+        }
+        let mut args = CliArgs::new();
+        args.option("--", None, 'b');
+        assert_eq!(args.schema_error(), Some(&SchemaError::MissingKeyPrefix("--".to_string())));
+        let mut args = CliArgs::new();
+        args.flag("--x", Some('-'));
+        assert_eq!(args.schema_error(), Some(&SchemaError::MissingKeyPrefix("--".to_string())));
+        assert!(args.keys.iter().next().is_none());
+    }
+
+    #[test]
+    fn reset_between_parses() {
+        let mut args = CliArgs::new();
+        args
+            .with("--name/-n=s*")
+            .with("--age=i? ::>18");
+
+        args.parse("-n alp -- x").unwrap();
+        args.parse("-n bob").unwrap();
+        assert_eq!(args.get_string_multi("--name").unwrap(), ["alp", "bob"]);
+        assert_eq!(args.get_int_multi("--age").unwrap(), [18]); // the first default counts as a value
+
+        args.reset();
+        assert_eq!(args.get_string_multi("--name").unwrap(), [] as [String; 0]);
+        assert_eq!(args.trailing(), [] as [String; 0]);
+
+        assert_parses!(args, "-n bob --age 30", {
+            "--name" => ["bob"],
+            "--age" => [30],
+        });
+
+        args.reset();
+        assert_parses!(args, "-n bob", {
+            "--name" => ["bob"],
+            "--age" => [18],
+        });
+    }
+
+    #[test]
+    fn defaults_from_map() {
+        let mut args = CliArgs::new();
+        args
+            .with("--port/-p=i ::>80")
+            .with("--host=s?");
+        let defaults = HashMap::from([
+            ("--port".to_string(), "8080".to_string()),
+            ("--host".to_string(), "localhost".to_string()),
+        ]);
+        args.apply_defaults(&defaults).unwrap();
+        assert_parses!(args, "", {
+            "-p" => 8080,
+            "--host" => "localhost",
+        });
+
+        args.reset();
+        assert_parses!(args, "--port 1", { "-p" => 1 });
+
+        let defaults = HashMap::from([("--port".to_string(), "http".to_string())]);
+        assert_eq!(
+            args.apply_defaults(&defaults),
+            Err(SchemaError::InvalidDefault { schema: "--port".to_string(), default: "http".to_string() })
+        );
+        let defaults = HashMap::from([("--nope".to_string(), "1".to_string())]);
+        assert_eq!(args.apply_defaults(&defaults), Err(SchemaError::UnknownKey("--nope".to_string())));
+
+        // nothing is applied if one is invalid, and the error is the first by key
+        let defaults = HashMap::from([
+            ("--host".to_string(), "example.org".to_string()),
+            ("-p".to_string(), "http".to_string()),
+            ("--zone".to_string(), "1".to_string()),
+        ]);
+        assert_eq!(args.apply_defaults(&defaults), Err(SchemaError::UnknownKey("--zone".to_string())));
+        args.reset();
+        assert_parses!(args, "", { "--host" => "localhost" });
+    }
+
+    #[test]
+    fn snapshot_is_owned_and_sendable() {
+        fn assert_send_sync<T: Send + Sync + 'static>(_: &T) {}
+
+        let mut args = CliArgs::new();
+        args
+            .with("--name/-n=s")
+            .with("--nums=i*?")
+            .with("--verbose/-v=b?");
+        args.parse("-n alp --nums 1 2 -v -- rest").unwrap();
+
+        let snapshot = args.values_snapshot();
+        assert_send_sync(&snapshot);
+        let pristine = args.clone();
+        args.reset();
+
+        let handle = std::thread::spawn(move || {
+            assert_eq!(snapshot.get_str("-n").unwrap(), Some("alp"));
+            assert_eq!(snapshot.get_int_multi("--nums").unwrap(), [1, 2]);
+            assert!(snapshot.unwrap_bool("--verbose"));
+            assert_eq!(snapshot.trailing(), ["rest"]);
+            assert!(matches!(snapshot.get_int("--name"), Err(ArgError::WrongType)));
+        });
+        handle.join().unwrap();
+
+        assert_eq!(pristine.get_str("--name").unwrap(), Some("alp"));
+        assert_eq!(args.get_str("--name").unwrap(), None);
+    }
+
+    #[test]
+    fn interleaved_order() {
+        let entry = |key: Option<&str>, value: Option<&str>| OrderedArg {
+            key: key.map(str::to_string),
+            value: value.map(str::to_string),
+        };
+        let mut args = CliArgs::new();
+        args
+            .with("--name/-n=s*")
+            .with("--type=s?")
+            .with("--verbose/-v=b?")
+            .with("-q=b?");
+        let tokens: Vec<String> = "-n a b -vq --type=f -n c -- x".split(' ').map(String::from).collect();
+        args.parse_interleaved(&tokens).unwrap();
+
+        assert_eq!(args.ordered(), [
+            entry(Some("-n"), Some("a")),
+            entry(Some("-n"), Some("b")),
+            entry(Some("-v"), None),
+            entry(Some("-q"), None),
+            entry(Some("--type"), Some("f")),
+            entry(Some("-n"), Some("c")),
+            entry(None, Some("x")),
+        ]);
+        assert_eq!(args.get_string_multi("--name").unwrap(), ["a", "b", "c"]);
+        args.clear("--verbose").unwrap();
+        assert_eq!(args.ordered()[2], entry(Some("-q"), None));
+
+        args.reset();
+        args.parse_from(&tokens).unwrap();
+        assert_eq!(args.ordered(), []);
+    }
+
+    #[test]
+    fn short_keys_are_single_char() {
+        let mut args = CliArgs::new();
+        args.with("--name/-n=s").with("-v=b?");
+        assert_eq!(args.schema_error(), None);
+
+        let mut args = CliArgs::new();
+        args.with("--name/-nm=s");
+        assert_eq!(args.schema_error(), Some(&SchemaError::MultiCharShortKey("-nm".to_string())));
+
+        let mut args = CliArgs::new();
+        args
+            .allow_multi_char_short(true)
+            .with("--name/-nm=s")
+            .with("-foo=b?");
+        assert_eq!(args.schema_error(), None);
+        assert_parses!(args, "-nm alp -foo", {
+            "--name" => "alp",
+            "-foo" => true,
+        });
+    }
+
+    #[test]
+    fn schema_description() {
+        let mut args = CliArgs::new();
+        args
+            .with("--name/-n=s # The user's name")
+            .with("--color=s? ::>#fff # Color, # included")
+            .with("--age=i? ::>18 #   Age in years  ")
+            .with("--tag=s? ::>a #b")
+            .with("--plain=s?")
+            .with_help("--level=i? ::>1", "Log level");
+        assert_eq!(args.schema_error(), None);
+        assert_eq!(args.description("-n").unwrap(), Some("The user's name"));
+        assert_eq!(args.description("--color").unwrap(), Some("Color, # included"));
+        assert_eq!(args.description("--age").unwrap(), Some("Age in years"));
+        assert_eq!(args.description("--tag").unwrap(), Some("b"));
+        assert_eq!(args.description("--plain").unwrap(), None);
+        assert_eq!(args.description("--level").unwrap(), Some("Log level"));
+        assert!(matches!(args.description("--nope"), Err(ArgError::WrongKey)));
+
+        assert_parses!(args, "-n alp", {
+            "--color" => "#fff",
+            "--age" => 18,
+            "--tag" => "a",
+            "--level" => 1,
+        });
+    }
+
+    #[test]
+    fn collects_all_errors() {
+        let mut args = CliArgs::new();
+        args
+            .with("--name/-n=s")
+            .with("--age=i")
+            .with("--count=i")
+            .with("--port=i")
+            .with("--verbose=b?");
+        let err = args.parse("--age x --verbose --port 80").unwrap_err();
+        assert_eq!(err, ParseError::Multiple(vec![
+            ParseError::InvalidValue { key: "--age".to_string(), value: "x".to_string() },
+            ParseError::MissingRequired("--name".to_string()),
+            ParseError::MissingRequired("--count".to_string()),
+        ]));
+        assert_eq!(
+            err.to_string(),
+            "3 errors:\n  - invalid value `x` for `--age`\n  - missing required `--name`\n  - missing required `--count`"
+        );
+
+        let mut args = CliArgs::new();
+        args.with("--name/-n=s").with("--age=i");
+        assert_eq!(args.parse("--bogus --age x"), Err(ParseError::UnknownKey("--bogus".to_string())));
+    }
+
+    #[test]
+    fn take_unknown_and_trailing() {
+        let mut args = CliArgs::new();
+        args
+            .ignore_unknown(true)
+            .with("--name/-n=s");
+        assert_parses!(args, "--color red -n alp stray -x -- a b", { "--name" => "alp" });
+
+        assert_eq!(args.take_unknown(), ["--color", "red", "stray", "-x"]);
+        assert_eq!(args.unknown(), [] as [String; 0]);
+        assert_eq!(args.take_trailing(), ["a", "b"]);
+        assert_eq!(args.trailing(), [] as [String; 0]);
+
+        let mut args = CliArgs::new();
+        args.with("--name/-n=s");
+        assert_eq!(args.parse("--color red -n alp"), Err(ParseError::UnknownKey("--color".to_string())));
+    }
+
+    #[test]
+    fn extra_positionals_but_no_unknown_keys() {
+        let mut args = CliArgs::new();
+        args
+            .allow_extra_positionals(true)
+            .with("--name/-n=s")
+            .with("--verbose/-v=b?");
+        assert_parses!(args, "a.txt -n alp b.txt -v -- --raw", { "--name" => "alp" });
+        assert_eq!(args.trailing(), ["a.txt", "b.txt", "--raw"]);
+
+        args.reset();
+        assert_eq!(args.parse("a.txt --color red -n alp"), Err(ParseError::UnknownKey("--color".to_string())));
+        assert!(args.unknown().is_empty());
+    }
+
+    #[test]
+    fn double_dash_terminates_once() {
+        let mut args = CliArgs::new();
+        args.with("--name/-n=s?");
+        args.parse("-- -- value").unwrap();
+        assert_eq!(args.trailing(), ["--", "value"]);
+
+        let mut args = CliArgs::new();
+        args.with("--name/-n=s?");
+        assert_parses!(args, "-n alp -- -n -- --", { "--name" => "alp" });
+        assert_eq!(args.trailing(), ["-n", "--", "--"]);
+
+        let mut args = CliArgs::new();
+        let tokens: Vec<String> = ["--", "--", "x"].map(String::from).into();
+        assert_eq!(args.parse_partial(&tokens).unwrap(), ["--", "--", "x"]);
+    }
+
+    #[test]
+    fn require_values() {
+        let mut args = CliArgs::new();
+        args
+            .with("--count/-c=i?")
+            .with("--name=s?")
+            .with("--verbose/-v=b?");
+        args.parse("-c 3 -v").unwrap();
+
+        assert!(matches!(args.require_int("--count"), Ok(3)));
+        assert!(matches!(args.require_bool("-v"), Ok(true)));
+        assert!(matches!(args.require_str("--name"), Err(ArgError::Missing)));
+        assert!(matches!(args.require_string("--name"), Err(ArgError::Missing)));
+        assert!(matches!(args.require_int("--name"), Err(ArgError::WrongType)));
+        assert!(matches!(args.require_int("--nope"), Err(ArgError::WrongKey)));
+
+        let snapshot = args.values_snapshot();
+        assert!(matches!(snapshot.require_int("-c"), Ok(3)));
+        assert!(matches!(snapshot.require_str("--name"), Err(ArgError::Missing)));
+    }
+
+    #[test]
+    fn file_value_reads_trimmed_contents() {
+        let path = std::env::temp_dir().join(format!("clitrs-file-value-{}.txt", std::process::id()));
+        std::fs::write(&path, "hunter2\n").unwrap();
+
+        let mut args = CliArgs::new();
+        args.with("--token=s").with("--name=s?");
+        args.set_file_value("--token", true).unwrap();
+        assert!(matches!(args.set_file_value("--nope", true), Err(ArgError::WrongKey)));
+
+        args.parse(&format!("--token @{} --name @literal", path.display())).unwrap();
+        assert_eq!(args.get_str("--token").unwrap(), Some("hunter2"));
+        assert_eq!(args.get_str("--name").unwrap(), Some("@literal"));
+        std::fs::remove_file(&path).unwrap();
+
+        args.reset();
+        let missing = format!("{}", path.display());
+        match args.parse(&format!("--token @{}", missing)) {
+            Err(ParseError::ValueFile { key, path, .. }) => assert_eq!((key.as_str(), path.as_str()), ("--token", missing.as_str())),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn env_fallback() {
+        let var = format!("CLITRS_TEST_PORT_{}", std::process::id());
+        let flag_var = format!("{}_VERBOSE", var);
+        let mut args = CliArgs::new();
+        args.with("--port=i ::>80").with("--verbose=b?");
+        args.set_env("--port", &var).unwrap();
+        args.set_env("--verbose", &flag_var).unwrap();
+
+        assert_parses!(args, "", {
+            "--port" => 80,
+            "--verbose" => NoValue,
+        });
+
+        std::env::set_var(&var, "8080");
+        std::env::set_var(&flag_var, "1");
+        args.reset();
+        assert_parses!(args, "", {
+            "--port" => 8080,
+            "--verbose" => true,
+        });
+
+        args.reset();
+        assert_parses!(args, "--port 1", { "--port" => 1 });
+
+        std::env::set_var(&var, "x");
+        args.reset();
+        assert_eq!(args.parse(""), Err(ParseError::InvalidValue { key: "--port".to_string(), value: "x".to_string() }));
+        std::env::remove_var(&var);
+        std::env::remove_var(&flag_var);
+    }
+
+    #[test]
+    fn choices_ignore_case() {
+        let mut args = CliArgs::new();
+        args.with("--level=s").with("--mode=s?");
+        args.set_choices("--level", &["low", "High"], true).unwrap();
+        args.set_choices("--mode", &["fast"], false).unwrap();
+        assert!(matches!(args.set_choices("--nope", &[], false), Err(ArgError::WrongKey)));
+
+        assert_parses!(args, "--level HIGH", { "--level" => "High" });
+
+        args.reset();
+        assert_parses!(args, "--level LoW --mode fast", { "--level" => "low" });
+
+        args.reset();
+        assert_eq!(args.parse("--level low --mode FAST"), Err(ParseError::InvalidValue { key: "--mode".to_string(), value: "FAST".to_string() }));
+        args.reset();
+        assert_eq!(args.parse("--level medium"), Err(ParseError::InvalidValue { key: "--level".to_string(), value: "medium".to_string() }));
+    }
+
+    #[test]
+    fn value_aliases() {
+        let mut args = CliArgs::new();
+        args.with("--color=s?").with("--name=s?");
+        args.set_choices("--color", &["gray", "red"], false).unwrap();
+        args.value_alias("--color", "grey", "gray").value_alias("--color", "silver", "gray");
+
+        assert_parses!(args, "--color=grey --name grey", {
+            "--color" => "gray",
+            "--name" => "grey",
+        });
+
+        args.reset();
+        assert_parses!(args, "--color silver", { "--color" => "gray" });
+
+        let mut args = CliArgs::new();
+        args.with("--port=i?").value_alias("--port", "http", "80");
+        assert_eq!(args.parse(""), Err(ParseError::Schema(SchemaError::WrongType("--port".to_string()))));
+    }
+
+    #[test]
+    fn file_value_from_stdin() {
+        let mut args = CliArgs::new();
+        args.with("--body=s").with("--name=s?");
+        args.set_file_value("--body", true).unwrap();
+        args.set_stdin("{\"a\": 1}\n".as_bytes()).unwrap();
+
+        assert_parses!(args, "--body @- --name @-", {
+            "--body" => "{\"a\": 1}",
+            "--name" => "@-",
+        });
+    }
+
+    #[test]
+    fn declared_flag_constants() {
+        crate::declare_flags! {
+            struct Flags {
+                NAME => "--name/-n=s # The user's name",
+                AGE => "  --age=i? ::>18",
+                /// Only a short key.
+                VERBOSE => "-v=b?",
+            }
+        }
+
+        assert_eq!((Flags::NAME, Flags::AGE, Flags::VERBOSE), ("--name", "--age", "-v"));
+        let mut args = Flags::cli();
+        args.parse("-n alp").unwrap();
+        assert_eq!(args.get_str(Flags::NAME).unwrap(), Some("alp"));
+        assert_eq!(args.get_int(Flags::AGE).unwrap(), Some(18));
+        assert_eq!(args.get_bool(Flags::VERBOSE).unwrap(), None);
+    }
+
+    #[test]
+    fn bool_does_not_take_next_token() {
+        let mut args = CliArgs::new();
+        args.with("--verbose/-v=b?").with("--name=s?");
+        assert_eq!(args.parse("--verbose true"), Err(ParseError::UnexpectedValue("true".to_string())));
+        args.reset();
+        assert_eq!(args.parse("-v true"), Err(ParseError::UnexpectedValue("true".to_string())));
+
+        args.reset();
+        args.ignore_unknown(true);
+        assert_parses!(args, "--verbose true --name x", { "--verbose" => true });
+        assert_eq!(args.unknown(), ["true"]);
+    }
+
+    #[test]
+    fn first_last_and_nth() {
+        let mut args = CliArgs::new();
+        args
+            .with("--jobs/-j=i")
+            .with("--profile=s?")
+            .with("--color=b?")
+            .with("--tag=s?");
+        args.parse("-j 2 --profile release -j 8 --color=false --color --profile dev").unwrap();
+        assert_eq!(args.get_int("-j").unwrap(), Some(2));
+        assert_eq!(args.get_int_first("-j").unwrap(), Some(2));
+        assert_eq!(args.get_int_last("-j").unwrap(), Some(8));
+        assert_eq!(args.get_str_last("--profile").unwrap(), Some("dev"));
+        assert_eq!(args.get_string_first("--profile").unwrap(), Some("release".to_string()));
+        assert_eq!((args.get_bool_first("--color").unwrap(), args.get_bool_last("--color").unwrap()), (Some(false), Some(true)));
+        assert_eq!(args.get_str_last("--tag").unwrap(), None);
+
+        assert_eq!(args.get_nth::<i32>("-j", 1).unwrap(), Some(&8));
+        assert_eq!(args.get_nth::<bool>("--color", 2).unwrap(), None);
+        assert!(matches!(args.get_nth::<String>("-j", 0), Err(ArgError::WrongType)));
+        assert_eq!(args.values_snapshot().get_nth::<String>("--profile", 1).unwrap().map(String::as_str), Some("dev"));
+    }
+
+    #[test]
+    fn colliding_keys() {
+        let mut args = CliArgs::new();
+        args.with("--name/-n=s").with("--number/-n=i?");
+        assert_eq!(args.schema_error(), Some(&SchemaError::KeyTaken("-n".to_string())));
+        assert!(args.get_int("--number").is_err());
+        assert!(args.get_str("-n").is_ok());
+
+        // a multi-char short key spelled like the long key of another arg
+        let mut args = CliArgs::new();
+        args.allow_multi_char_short(true).with("--verbose=b?").with("--all/--verbose=b?");
+        assert_eq!(args.schema_error(), Some(&SchemaError::KeyTaken("--verbose".to_string())));
+        assert_eq!(args.parse(""), Err(ParseError::Schema(SchemaError::KeyTaken("--verbose".to_string()))));
+    }
+
+    #[test]
+    fn raw_takes_the_rest() {
+        let mut args = CliArgs::new();
+        args
+            .with("--cmd/-c=s?")
+            .with("--port/-p=i?")
+            .with("--verbose/-v=b?");
+        args.set_raw("--cmd", true).unwrap();
+        assert!(matches!(args.set_raw("-p", true), Err(ArgError::WrongType)));
+        assert!(args.help().contains("-c, --cmd <ARGS>..."), "{}", args.help());
+
+        assert_parses!(args, "-v --cmd ssh -p 2222 -- host -v", {
+            "--cmd" => ["ssh", "-p", "2222", "--", "host", "-v"],
+            "-v" => [true],
+            "-p" => NoValue,
+        });
+        assert!(args.trailing().is_empty());
+
+        args.reset();
+        assert_parses!(args, "--cmd=ssh -p 2222 host", { "--cmd" => ["ssh", "-p", "2222", "host"] });
+
+        args.reset();
+        assert_parses!(args, "-cssh -v", { "--cmd" => ["ssh", "-v"] });
+
+        args.reset();
+        assert_parses!(args, "-p 1 -c", { "--cmd" => NoValue });
+    }
+
+    #[test]
+    fn implied_values() {
+        let mut args = CliArgs::new();
+        args.with("--debug=b?").with("--verbose/-v=b? ::>false").with("--log-level=s? ::>info");
+        args.implies("--debug", "-v", "true").implies("--debug", "--log-level", "debug");
+        assert_eq!(args.schema_error(), None);
+
+        args.parse("").unwrap();
+        assert_eq!((args.get_bool("-v").unwrap(), args.get_str("--log-level").unwrap()), (Some(false), Some("info")));
+
+        args.reset();
+        args.parse("--debug").unwrap();
+        assert_eq!((args.get_bool("-v").unwrap(), args.get_str("--log-level").unwrap()), (Some(true), Some("debug")));
+        assert!(!args.is_present("--log-level").unwrap());
+
+        args.reset();
+        args.parse("--debug --log-level trace --no-verbose").unwrap();
+        assert_eq!((args.get_bool("-v").unwrap(), args.get_str("--log-level").unwrap()), (Some(false), Some("trace")));
+
+        // a flag turned off implies nothing
+        for off in ["--no-debug", "--debug=false", "--debug --no-debug"] {
+            args.reset();
+            args.parse(off).unwrap();
+            assert_eq!((args.get_bool("-v").unwrap(), args.get_str("--log-level").unwrap()), (Some(false), Some("info")), "{off}");
+        }
+
+        args.implies("--debug", "--verbose", "loud");
+        assert_eq!(args.schema_error(), Some(&SchemaError::InvalidDefault { schema: "--verbose".to_string(), default: "loud".to_string() }));
+    }
+
+    #[test]
+    fn clear_one_arg() {
+        let mut args = CliArgs::new();
+        args.with("--verbose/-v=b?").with("--name/-n=s?");
+        args.parse("-v -n alp").unwrap();
+        args.clear("-n").unwrap();
+        assert_eq!(args.get_str("--name").unwrap(), None);
+        assert_eq!(args.get_bool("--verbose").unwrap(), Some(true));
+        assert!(!args.is_present("--name").unwrap() && args.is_present("-v").unwrap());
+        assert!(matches!(args.clear("--nope"), Err(ArgError::WrongKey)));
+    }
+
+    #[test]
+    fn declared_types() {
+        let mut args = CliArgs::new();
+        args.with("--verbose/-v=b?").with("--port=i{2}?").with("--files=s*?").with_positional("target=s?");
+        assert_eq!(args.type_of("-v"), Some(ArgKind::Bool));
+        assert_eq!(args.type_of("--port"), Some(ArgKind::Int));
+        assert_eq!(args.type_of("--files"), Some(ArgKind::String));
+        assert_eq!(args.type_of("target"), Some(ArgKind::String));
+        assert_eq!(args.type_of("--nope"), None);
+    }
+
+    #[test]
+    fn schema_summary() {
+        let mut args = CliArgs::new();
+        args.with("--verbose/-v=b?").with("--port=i").with("--retries=i?").with("--files=s*?").with_positional("target=s");
+        let summary = args.summary();
+        assert_eq!(summary, SchemaSummary { total: 5, required: 2, optional: 3, bools: 1, ints: 2, strings: 2 });
+        assert_eq!(summary.to_string(), "5 args: 2 required, 3 optional (1 bool, 2 int, 2 string)");
+        assert_eq!(CliArgs::new().summary(), SchemaSummary::default());
+    }
+
+    #[test]
+    fn keys_of_either_form() {
+        let mut args = CliArgs::new();
+        args.with("--port/-p=i?").with("--host=s?").with("-v=b?").with_positional("target=s?");
+        let both = (Some("--port".to_string()), Some("-p".to_string()));
+        assert_eq!(args.keys_of("--port"), both);
+        assert_eq!(args.keys_of("-p"), both);
+        assert_eq!(args.keys_of("--host"), (Some("--host".to_string()), None));
+        assert_eq!(args.keys_of("-v"), (None, Some("-v".to_string())));
+        assert_eq!(args.keys_of("target"), (None, None));
+        assert_eq!(args.keys_of("--nope"), (None, None));
+    }
+
+    #[test]
+    fn join_rest_into_one_value() {
+        let mut args = CliArgs::new();
+        args.with("--message/-m=s?").with("--verbose/-v=b?").with("--tag=s*?");
+        args.set_join_rest("-m", true).unwrap();
+        assert!(matches!(args.set_join_rest("-v", true), Err(ArgError::WrongType)));
+
+        assert_parses!(args, "-v --message the rest -v of  the line", {
+            "--message" => ["the rest -v of the line"],
+            "-v" => [true],
+        });
+
+        args.reset();
+        assert_parses!(args, "--tag a b --message=fix: typo -- x y", {
+            "-m" => "fix: typo",
+            "--tag" => ["a", "b"],
+        });
+        assert_eq!(args.trailing(), ["x", "y"]);
+    }
+
+    #[test]
+    fn hyphen_values_of_one_arg() {
+        let mut args = CliArgs::new();
+        args
+            .with("--pattern/-p=s")
+            .with("--exec=s*?")
+            .with("--name=s?")
+            .with("--verbose/-v=b?");
+        args.set_allow_hyphen_values("--pattern", true).unwrap();
+        args.set_allow_hyphen_values("--exec", true).unwrap();
+        assert!(matches!(args.set_allow_hyphen_values("-v", true), Err(ArgError::WrongType)));
+
+        assert_parses!(args, "--pattern -v -v", {
+            "--pattern" => "-v",
+            "--verbose" => true,
+        });
+
+        args.reset();
+        assert_parses!(args, "-p --name --exec ls -la --name x -- y", {
+            "--pattern" => "--name",
+            "--exec" => ["ls", "-la", "--name", "x"],
+            "--name" => NoValue,
+        });
+        assert_eq!(args.trailing(), ["y"]);
+
+        // other args are not affected
+        args.reset();
+        assert_eq!(args.parse("--name -v --pattern x"), Err(ParseError::MissingValue("--name".to_string())));
+    }
+
+    #[test]
+    fn default_missing_value() {
+        let mut args = CliArgs::new();
+        args
+            .with("--backup/-b=s? ::>none")
+            .with("--level=i?")
+            .with("--name=s?")
+            .with_default_missing_value("--backup", "simple")
+            .with_default_missing_value("--level", "3");
+        assert_parses!(args, "--name x", { "--backup" => "none" });
+        assert!(!args.is_present("--backup").unwrap());
+        assert!(args.is_present("--name").unwrap());
+
+        args.reset();
+        assert_parses!(args, "--backup --level --name x", {
+            "--backup" => "simple",
+            "--level" => 3,
+        });
+        assert!(args.is_present("--backup").unwrap() && args.is_present("--level").unwrap());
+
+        args.reset();
+        assert_parses!(args, "-b=numbered --level=0x10", {
+            "--backup" => "numbered",
+            "--level" => 16,
+        });
+
+        // the value of a bare arg is never the next token
+        args.reset();
+        assert_eq!(args.parse("--backup numbered"), Err(ParseError::UnexpectedValue("numbered".to_string())));
+        assert!(args.help().contains("-b, --backup[=<BACKUP>]"), "{}", args.help());
+
+        let mut args = CliArgs::new();
+        args.with("--level=i?").with_default_missing_value("--level", "high");
+        assert_eq!(args.schema_error(), Some(&SchemaError::InvalidDefault { schema: "--level".to_string(), default: "high".to_string() }));
+    }
+
+    #[test]
+    fn bool_presence_and_explicit_false() {
+        let parsed = |line: &str| {
+            let mut args = CliArgs::new();
+            args.with("--verbose/-v=b?").with("--color=b? ::>true");
+            args.parse(line).map(|()| (args.get_bool("--verbose").unwrap(), args.get_bool("--color").unwrap()))
+        };
+        assert_eq!(parsed(""), Ok((None, Some(true))));
+        assert_eq!(parsed("--verbose"), Ok((Some(true), Some(true))));
+        assert_eq!(parsed("--verbose=true -v"), Ok((Some(true), Some(true))));
+        assert_eq!(parsed("--no-verbose"), Ok((Some(false), Some(true))));
+        assert_eq!(parsed("--verbose=false --no-color"), Ok((Some(false), Some(false))));
+        assert_eq!(parsed("--color=false"), Ok((None, Some(false))));
+        assert_eq!(parsed("--verbose=no --color=1"), Ok((Some(false), Some(true))));
+        assert_eq!(parsed("--verbose --no-verbose"), Ok((Some(false), Some(true))));
+        assert_eq!(parsed("--no-verbose -v"), Ok((Some(true), Some(true))));
+        assert_eq!(
+            parsed("--verbose=maybe"),
+            Err(ParseError::InvalidValue { key: "--verbose".to_string(), value: "maybe".to_string() }),
+        );
+        assert_eq!(parsed("--no-verbose=true"), Err(ParseError::UnknownKey("--no-verbose".to_string())));
+    }
+
+    #[test]
+    fn batch_schema_check() {
+        assert_eq!(CliArgs::assert_schema(&[]), Ok(()));
+        assert_eq!(CliArgs::assert_schema(&["--name/-n=s", "--files=s*?", "--point=i{2}", "-v=b? ::>true"]), Ok(()));
+        assert_eq!(
+            CliArgs::assert_schema(&["--name/-n=s", "--age=x", "--count=i ::>many", "--nick/-n=s", "name=s", "--ok=b?"]),
+            Err(vec![
+                SchemaError::Malformed("--age=x".to_string()),
+                SchemaError::InvalidDefault { schema: "--count=i ::>many".to_string(), default: "many".to_string() },
+                SchemaError::KeyTaken("-n".to_string()),
+                SchemaError::MissingKeyPrefix("name=s".to_string()),
+            ]),
+        );
+    }
+
+    #[test]
+    fn flag_on_by_default() {
+        let mut args = CliArgs::new();
+        args.with("--cache/-c=b? ::>true # cache downloads");
+        let mut parsed = |line: &str| {
+            args.reset();
+            args.parse(line).map(|()| args.get_bool("--cache").unwrap())
+        };
+        assert_eq!(parsed(""), Ok(Some(true)));
+        assert_eq!(parsed("--no-cache"), Ok(Some(false)));
+        assert_eq!(parsed("--cache=false"), Ok(Some(false)));
+        assert_eq!(parsed("--no-cache --cache"), Ok(Some(true)));
+        assert_eq!(parsed("--cache --no-cache"), Ok(Some(false)));
+        assert_eq!(parsed("--cache=true"), Ok(Some(true)));
+        assert_eq!(parsed("-c"), Ok(Some(true)));
+
+        assert!(args.help().contains("-c, --[no-]cache"), "{}", args.help());
+        assert!(args.help().contains("cache downloads [default: true]"));
+    }
+
+    #[test]
+    fn partial_hands_off_the_rest() {
+        let mut args = CliArgs::new();
+        args.with("--config/-c=s?").with("--verbose/-v=b?");
+        let tokens: Vec<String> = ["-v", "--color=red", "push", "-c", "x", "origin", "--force", "--", "-v", "a"]
+            .iter().map(|t| t.to_string()).collect();
+
+        let rest = args.parse_partial(&tokens).unwrap();
+        assert_eq!(rest, ["--color=red", "push", "origin", "--force", "--", "-v", "a"]);
+        assert_eq!(args.get_bool("-v").unwrap(), Some(true));
+        assert_eq!(args.get_str("--config").unwrap(), Some("x"));
+        assert_eq!(args.unknown(), [] as [String; 0]);
+        assert_eq!(args.trailing(), [] as [String; 0]);
+
+        args.reset();
+        assert_eq!(args.parse("push"), Err(ParseError::UnexpectedValue("push".to_string())));
+    }
+
+    #[test]
+    fn radix_prefixed_ints() {
+        let mut args = CliArgs::new();
+        args.with("--mask=i? ::>0x0F").with("--n=i*?");
+        assert_parses!(args, "--n 0xFF 0o17 0b1010 0XfF 42", {
+            "--n" => [255, 15, 10, 255, 42],
+            "--mask" => 15,
+        });
+        for (val, expected) in [("-0x1f", -31), ("-0b11", -3), ("-7", -7), ("-0x80000000", i32::MIN)] {
+            args.reset();
+            args.parse(&format!("--mask={val}")).unwrap();
+            assert_eq!(args.get_int("--mask").unwrap(), Some(expected));
+        }
+
+        for bad in ["0xG", "0b102", "0o8", "0x", "0x-5", "0x+5", "--0x5", "0x100000000"] {
+            args.reset();
+            assert_eq!(
+                args.parse(&format!("--n={bad}")),
+                Err(ParseError::InvalidValue { key: "--n".to_string(), value: bad.to_string() }),
+            );
+        }
+    }
+
+    #[test]
+    fn underscore_grouped_ints() {
+        let mut args = CliArgs::new();
+        args.with("--size=i? ::>64_000").with("--n=i*?");
+        assert_parses!(args, "--size 1_000_000 --n 0xFF_FF 0b1010_1010 1_2_3 7", {
+            "--size" => 1_000_000,
+            "--n" => [0xFFFF, 0b1010_1010, 123, 7],
+        });
+        args.reset();
+        assert_parses!(args, "--size=-1_000 --n +1_0", {
+            "--size" => -1000,
+            "--n" => 10,
+        });
+        args.reset();
+        assert_parses!(args, "", { "--size" => 64_000 });
+
+        for bad in ["_1000", "1000_", "1__000", "0x_FF", "0xFF_", "-_1", "_", "+_1", "-+1", "++1"] {
+            args.reset();
+            assert_eq!(
+                args.parse(&format!("--size={bad}")),
+                Err(ParseError::InvalidValue { key: "--size".to_string(), value: bad.to_string() }),
+            );
+        }
+    }
+
+    #[test]
+    fn summary_of_defaults() {
+        let var = format!("CLITRS_TEST_SUMMARY_{}", std::process::id());
+        std::env::set_var(&var, "x");
+        let mut args = CliArgs::new();
+        args
+            .with("--port/-p=i ::>8080")
+            .with("--host=s")
+            .with("--verbose/-v=b?")
+            .with("--name=s? ::>anon")
+            .with("--user=s? ::>root")
+            .with("--quiet=b?");
+        args.set_env("--user", &var).unwrap();
+        let tokens: Vec<String> = ["--host", "h", "-v"].map(String::from).into();
+        assert_eq!(args.parse_with_summary(&tokens), Ok(ParseSummary {
+            defaulted: vec!["--port".to_string(), "--name".to_string()],
+            provided: vec!["--host".to_string(), "--verbose".to_string(), "--user".to_string()],
+        }));
+
+        args.reset();
+        let tokens: Vec<String> = ["-p", "1", "--host", "h"].map(String::from).into();
+        let summary = args.parse_with_summary(&tokens).unwrap();
+        assert_eq!(summary.defaulted, ["--name"]);
+        assert_eq!(summary.provided, ["--port", "--host", "--user"]);
+    }
+
+    #[test]
+    fn all_or_none_group() {
+        let mut args = CliArgs::new();
+        args
+            .with("--tls-cert=s?")
+            .with("--tls-key=s?")
+            .with("--tls-ca=s? ::>ca.pem")
+            .with("--port=i?")
+            .all_or_none(&["--tls-cert", "--tls-key", "--tls-ca"]);
+
+        args.parse("--tls-cert c --tls-key k --tls-ca x").unwrap();
+        args.reset();
+        assert_parses!(args, "--port 80", { "--tls-ca" => "ca.pem" });
+
+        args.reset();
+        let err = args.parse("--tls-key k").unwrap_err();
+        assert_eq!(err, ParseError::PartialGroup {
+            given: vec!["--tls-key".to_string()],
+            missing: vec!["--tls-cert".to_string(), "--tls-ca".to_string()],
+        });
+        assert_eq!(err.to_string(), "`--tls-key` given without `--tls-cert`, `--tls-ca`, they must be given together");
+
+        let mut args = CliArgs::new();
+        args.with("--tls-cert=s?").all_or_none(&["--tls-cert", "--tls-key"]);
+        assert_eq!(args.schema_error(), Some(&SchemaError::UnknownKey("--tls-key".to_string())));
+    }
+
+    #[test]
+    fn parse_shell_words() {
+        let mut args = CliArgs::new();
+        args.with("--msg/-m=s").with("--files=s*?");
+        args.parse_shell(r#"-m "fix: don't panic" --files 'My Documents/a.txt' b\ c.txt"#).unwrap();
+        assert_eq!(args.get_str("-m").unwrap(), Some("fix: don't panic"));
+        assert_eq!(args.get_string_multi("--files").unwrap(), ["My Documents/a.txt", "b c.txt"]);
+        assert_eq!(args.parse_shell("-m 'oops"), Err(ParseError::UnclosedQuote('\'')));
+    }
+
+    fn all_types() -> CliArgs {
+        let mut args = CliArgs::new();
+        args
+            .with("--name/-n=s")
+            .with("--age/-a=i? ::>18")
+            .with("--verbose/-v=b?")
+            .with("--files/-f=s*?")
+            .with("--nums=i*?")
+            .with("--point=i{2}?");
+        args
+    }
+
+    fn token() -> impl Strategy<Value = String> {
+        prop_oneof![
+            "\\PC*",
+            "-{0,3}[a-z=]{0,6}",
+            Just("-".to_string()),
+            Just("--".to_string()),
+            Just("--=".to_string()),
+            Just("--age=99999999999999999999".to_string()),
+            Just("-99999999999999999999".to_string()),
+            Just("\"unterminated".to_string()),
+            Just("-n".to_string()),
+            Just("--files".to_string()),
+            Just("--nums".to_string()),
+            Just("--point".to_string()),
+            Just("--verbose=".to_string()),
+        ]
+    }
+
+    #[test]
+    fn nargs_takes_exact_count() {
+        let mut args = CliArgs::new();
+        args
+            .with("--point/-p=i{2}")
+            .with("--name=s{1}?");
+        assert_parses!(args, "--point 3 7 --name alp", {
+            "--point" => [3, 7],
+            "--name" => ["alp"],
+        });
+
+        let mut args = CliArgs::new();
+        args.with("--point/-p=i{2}");
+        assert_parses!(args, "-p 1 2 -p 3 4", { "-p" => [1, 2, 3, 4] });
+    }
+
+    #[test]
+    fn num_values_groups_occurrences() {
+        let mut args = CliArgs::new();
+        args
+            .with("--range=i?")
+            .with("--pair=s*?")
+            .with("--verbose/-v=b?");
+        args.set_num_values("--range", 2).unwrap();
+        args.set_num_values("--pair", 2).unwrap();
+        assert!(matches!(args.set_num_values("-v", 2), Err(ArgError::WrongType)));
+        assert!(args.help().contains("--range <RANGE> <RANGE>"));
+
+        args.parse("--range -10 20 --pair a b --range 3 -4 --pair c d").unwrap();
+        assert_eq!(args.get_int_groups("--range").unwrap(), [[-10, 20], [3, -4]]);
+        assert_eq!(args.get_string_groups("--pair").unwrap(), [["a", "b"], ["c", "d"]]);
+        assert!(matches!(args.get_int_groups("--pair"), Err(ArgError::WrongType)));
+
+        args.reset();
+        assert_eq!(
+            args.parse("--range 1 -v"),
+            Err(ParseError::TooFewValues { key: "--range".to_string(), expected: 2, found: 1 })
+        );
+    }
+
+    #[test]
+    fn nargs_too_few() {
+        let mut args = CliArgs::new();
+        args
+            .with("--point/-p=i{2}")
+            .with("--verbose=b?");
+        assert_eq!(
+            args.parse("--point 3 --verbose"),
+            Err(ParseError::TooFewValues { key: "--point".to_string(), expected: 2, found: 1 })
+        );
+
+        let mut args = CliArgs::new();
+        args.with("--point/-p=i{2}");
+        assert_eq!(
+            args.parse("-p 3"),
+            Err(ParseError::TooFewValues { key: "-p".to_string(), expected: 2, found: 1 })
+        );
+    }
+
+    #[test]
+    fn nargs_extra_token_is_next_token() {
+        let mut args = CliArgs::new();
+        args.with("--point=i{2}");
+        assert_eq!(args.parse("--point 3 7 9"), Err(ParseError::UnexpectedValue("9".to_string())));
+
+        let mut args = CliArgs::new();
+        args.with("--point=i{2}");
+        assert_parses!(args, "--point 3 7 -- 9", { "--point" => [3, 7] });
+        assert_eq!(args.trailing(), ["9"]);
+    }
+
+    proptest! {
+        #[test]
+        fn parse_from_never_panics(tokens in prop::collection::vec(token(), 0..12)) {
+            let _ = all_types().parse_from(&tokens);
+        }
+
+        #[test]
+        fn parse_never_panics(line in prop::collection::vec(token(), 0..12).prop_map(|t| t.join(" "))) {
+            let _ = all_types().parse(&line);
+        }
+
+        #[test]
+        fn with_never_panics(schema in prop_oneof!["\\PC*", "-{0,2}[a-z]{0,4}(/-[a-z])?=[bisx](\\*|\\{[0-9]{1,3}\\})?\\??( ?::>\\PC{0,5})?"]) {
+            let _ = CliArgs::new().with(&schema).parse("");
+        }
+    }
+
+}
@@ -0,0 +1,292 @@
+//! Conversions between [`CliArgs`] and `clap::Command`, for moving a CLI
+//! from one crate to the other a piece at a time.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use clap::builder::PossibleValuesParser;
+use clap::{ArgAction, Command};
+
+use super::{Arg, CliArgs};
+
+/// Id of the hidden positional that takes the tokens after `--`, which
+/// [`CliArgs`] collects into [`CliArgs::trailing`].
+const TRAILING_ID: &str = "clitrs-trailing";
+
+/// Maps every arg with its keys, type, default, required-ness, choices,
+/// environment variable and description. Every value arg appends on
+/// repetition like [`CliArgs`] does. Short keys longer than one character
+/// have no clap equivalent and are left out. A required arg with a default
+/// is optional for clap, which rejects required args that are not given
+/// even with a default. Clap keeps a choice matched regardless of case in
+/// the given casing, not the declared one.
+impl From<&CliArgs> for Command {
+    fn from(cli: &CliArgs) -> Command {
+        let mut command = Command::new(cli.resolved_name());
+        if let Some(bin_name) = &cli.meta.bin_name {
+            command = command.bin_name(bin_name.clone());
+        }
+        if let Some(author) = &cli.meta.author {
+            command = command.author(author.clone());
+        }
+        if let Some(about) = &cli.meta.about {
+            command = command.about(about.clone());
+        }
+        if let Some(long_about) = &cli.meta.long_about {
+            command = command.long_about(long_about.clone());
+        }
+
+        for (ind, arg) in cli.args.iter().enumerate() {
+            let (key_l, key_s) = cli.arg_keys(ind);
+            let id = key_l.or(key_s).map_or(String::new(), |key| key.trim_start_matches('-').to_string());
+            let mut clap_arg = clap::Arg::new(id);
+            if let Some(key_l) = key_l {
+                clap_arg = clap_arg.long(key_l[2..].to_string());
+            }
+            let mut short = key_s.map(|key| key[1..].chars());
+            if let Some((Some(c), None)) = short.as_mut().map(|chars| (chars.next(), chars.next())) {
+                clap_arg = clap_arg.short(c);
+            }
+            if let Some(description) = arg.description() {
+                clap_arg = clap_arg.help(description.to_string());
+            }
+            if let Some(env) = arg.env() {
+                clap_arg = clap_arg.env(env.to_string());
+            }
+
+            let (default, hide_default) = match arg {
+                Arg::Bool { settings, .. } => (settings.default_val.map(|d| d.to_string()), settings.hide_default),
+                Arg::Int { settings, .. } => (settings.default_val.map(|d| d.to_string()), settings.hide_default),
+                Arg::String { settings, .. } => (settings.default_val.clone(), settings.hide_default),
+            };
+            clap_arg = match arg {
+                Arg::Bool { .. } => clap_arg.action(ArgAction::SetTrue),
+                _ => {
+                    clap_arg = clap_arg
+                        .action(ArgAction::Append)
+                        .required(!arg.is_optional() && default.is_none())
+                        .hide_default_value(hide_default || arg.is_secret());
+                    if let Some(default) = default {
+                        clap_arg = clap_arg.default_value(default);
+                    }
+                    clap_arg = match (arg.nargs(), arg.is_greedy()) {
+                        (Some(n), _) => clap_arg.num_args(n),
+                        (None, true) => clap_arg.num_args(1..),
+                        (None, false) => clap_arg.num_args(1),
+                    };
+                    match arg {
+                        Arg::Int { .. } => clap_arg.value_parser(clap::value_parser!(i32)),
+                        Arg::String { settings, .. } if !settings.choices.is_empty() => clap_arg
+                            .value_parser(PossibleValuesParser::new(settings.choices.iter().cloned()))
+                            .ignore_case(settings.ignore_case),
+                        _ => clap_arg,
+                    }
+                },
+            };
+            command = command.arg(clap_arg);
+        }
+
+        command.arg(clap::Arg::new(TRAILING_ID).num_args(0..).last(true).hide(true))
+    }
+}
+
+impl CliArgs {
+    /// Registers the args of a clap command, as far as this crate supports
+    /// them, and returns them with one line for everything left out:
+    /// positionals, subcommands, aliases, actions other than setting a flag
+    /// or a value, and value counts that are neither fixed nor open ended.
+    /// Integer types other than `i32` are read as strings.
+    ///
+    /// ```
+    /// # use clitrs::args::CliArgs;
+    /// let command = clap::Command::new("mytool")
+    ///     .arg(clap::Arg::new("port").long("port").short('p').value_parser(clap::value_parser!(i32)))
+    ///     .arg(clap::Arg::new("file"));
+    /// let (mut args, dropped) = CliArgs::from_clap(&command);
+    /// assert_eq!(dropped, ["positional `file`"]);
+    /// args.parse("-p 80").unwrap();
+    /// assert_eq!(args.get_int("--port").unwrap(), Some(80));
+    /// ```
+    pub fn from_clap(command: &Command) -> (CliArgs, Vec<String>) {
+        let mut cli = CliArgs::new();
+        let mut dropped = Vec::new();
+        cli.name(command.get_name());
+        if let Some(bin_name) = command.get_bin_name() {
+            cli.bin_name(bin_name);
+        }
+        if let Some(author) = command.get_author() {
+            cli.author(author);
+        }
+        if let Some(about) = command.get_about() {
+            cli.about(&about.to_string());
+        }
+        if let Some(long_about) = command.get_long_about() {
+            cli.long_about(&long_about.to_string());
+        }
+
+        for arg in command.get_arguments() {
+            let id = arg.get_id().as_str();
+            if id == TRAILING_ID {
+                continue;
+            }
+            if arg.is_positional() {
+                dropped.push(format!("positional `{id}`"));
+                continue;
+            }
+            let key_l = arg.get_long().map(|long| format!("--{long}"));
+            let key_s = arg.get_short().map(|short| format!("-{short}"));
+            let key = key_l.clone().or(key_s.clone()).unwrap_or_default();
+            if arg.get_all_aliases().is_some() || arg.get_all_short_aliases().is_some() {
+                dropped.push(format!("aliases of `{key}`"));
+            }
+
+            let type_code = match arg.get_action() {
+                ArgAction::SetTrue => 'b',
+                ArgAction::Set | ArgAction::Append if arg.get_value_parser().type_id() == TypeId::of::<i32>() => 'i',
+                ArgAction::Set | ArgAction::Append => 's',
+                ArgAction::Help | ArgAction::HelpShort | ArgAction::HelpLong | ArgAction::Version => continue,
+                action => {
+                    dropped.push(format!("`{key}` with action {action:?}"));
+                    continue;
+                },
+            };
+            let count = match arg.get_num_args().filter(|_| type_code != 'b') {
+                None => String::new(),
+                Some(range) if range.max_values() == usize::MAX => "*".to_string(),
+                Some(range) if range.min_values() == range.max_values() && range.min_values() > 1 => {
+                    format!("{{{}}}", range.min_values())
+                },
+                Some(range) if range.min_values() == 1 && range.max_values() == 1 => String::new(),
+                Some(range) => {
+                    dropped.push(format!("value count {range} of `{key}`, it takes one value"));
+                    String::new()
+                },
+            };
+            let optional = if arg.is_required_set() { "" } else { "?" };
+            let keys = [key_l, key_s].into_iter().flatten().collect::<Vec<_>>().join("/");
+
+            let registered = cli.args.len();
+            cli.with(&format!("{keys}={type_code}{count}{optional}"));
+            if let Some(e) = cli.schema_error.take() {
+                dropped.push(format!("`{key}`: {e}"));
+                continue;
+            }
+            if let Some(help) = arg.get_help() {
+                cli.args[registered].set_description(&help.to_string());
+            }
+            if let Some(default) = arg.get_default_values().first().and_then(|d| d.to_str()).filter(|_| type_code != 'b') {
+                if cli.apply_defaults(&HashMap::from([(key.clone(), default.to_string())])).is_err() {
+                    dropped.push(format!("default `{default}` of `{key}`"));
+                }
+            }
+            let _ = cli.set_hide_default(&key, arg.is_hide_default_value_set());
+            if let Some(env) = arg.get_env().and_then(|env| env.to_str()) {
+                let _ = cli.set_env(&key, env);
+            }
+            let choices: Vec<String> = arg.get_possible_values().iter().map(|v| v.get_name().to_string()).collect();
+            if type_code == 's' && !choices.is_empty() {
+                let choices: Vec<&str> = choices.iter().map(String::as_str).collect();
+                let _ = cli.set_choices(&key, &choices, arg.is_ignore_case_set());
+            }
+        }
+
+        for sub in command.get_subcommands() {
+            dropped.push(format!("subcommand `{}`", sub.get_name()));
+        }
+        (cli, dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Command;
+
+    use crate::args::CliArgs;
+
+    fn tool() -> CliArgs {
+        let mut args = CliArgs::new();
+        args
+            .name("mytool")
+            .bin_name("mytool")
+            .about("Syncs files")
+            .with("--config/-c=s? ::>mytool.toml # Config file")
+            .with("--jobs/-j=i? # Parallel jobs")
+            .with("--verbose/-v=b?")
+            .with("--level=s? ::>low")
+            .with("--files/-f=s*?")
+            .with("--point=i{2}?")
+            .with("--remote=s");
+        args.set_choices("--level", &["low", "high"], true).unwrap();
+        args.set_env("--jobs", "MYTOOL_JOBS").unwrap();
+        args
+    }
+
+    fn tokens(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn clap_accepts_the_same_lines() {
+        let lines = [
+            "--remote origin",
+            "-c x.toml -j 4 -v --remote origin",
+            "--remote origin --level HIGH --files a b c -v",
+            "--point 3 7 --remote o --point 1 2",
+            "--remote o -- rest -v",
+        ];
+        for line in lines {
+            let mut args = tool();
+            args.parse(line).unwrap_or_else(|e| panic!("`{line}`: {e}"));
+            let matches = Command::from(&tool())
+                .no_binary_name(true)
+                .try_get_matches_from(tokens(line))
+                .unwrap_or_else(|e| panic!("`{line}`: {e}"));
+
+            let clap_str = |id: &str| matches.get_one::<String>(id).cloned();
+            assert_eq!(clap_str("config").as_deref(), args.get_str("--config").unwrap(), "{line}");
+            let level = clap_str("level").map(|level| level.to_lowercase()); // clap keeps the given casing
+            assert_eq!(level.as_deref(), args.get_str("--level").unwrap(), "{line}");
+            assert_eq!(matches.get_one::<i32>("jobs").copied(), args.get_int("--jobs").unwrap(), "{line}");
+            assert_eq!(matches.get_flag("verbose"), args.get_bool("-v").unwrap().unwrap_or(false), "{line}");
+            let files: Vec<String> = matches.get_many::<String>("files").into_iter().flatten().cloned().collect();
+            assert_eq!(files, args.get_string_multi("--files").unwrap(), "{line}");
+            let point: Vec<i32> = matches.get_many::<i32>("point").into_iter().flatten().copied().collect();
+            assert_eq!(point, args.get_int_multi("--point").unwrap(), "{line}");
+        }
+
+        for line in ["", "--jobs x --remote o", "--level medium --remote o", "--point 1 --remote o", "--bogus"] {
+            assert!(tool().parse(line).is_err(), "{line}");
+            assert!(Command::from(&tool()).no_binary_name(true).try_get_matches_from(tokens(line)).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn round_trips_through_clap() {
+        let (back, dropped) = CliArgs::from_clap(&Command::from(&tool()));
+        assert_eq!(dropped, [] as [String; 0]);
+        assert_eq!(back.schema_json(), tool().schema_json());
+        assert_eq!(back.help(), tool().help());
+        assert_eq!(back.arg_keys(0), tool().arg_keys(0));
+    }
+
+    #[test]
+    fn reports_what_is_dropped() {
+        let command = Command::new("git")
+            .arg(clap::Arg::new("quiet").long("quiet").short('q').action(clap::ArgAction::Count))
+            .arg(clap::Arg::new("message").long("message").visible_alias("msg"))
+            .arg(clap::Arg::new("depth").long("depth").num_args(1..=3).value_parser(clap::value_parser!(i32)))
+            .arg(clap::Arg::new("path"))
+            .subcommand(Command::new("fetch"));
+        let (mut args, dropped) = CliArgs::from_clap(&command);
+        assert_eq!(dropped, [
+            "`--quiet` with action Count",
+            "aliases of `--message`",
+            "value count 1..=3 of `--depth`, it takes one value",
+            "positional `path`",
+            "subcommand `fetch`",
+        ]);
+        args.parse("--message hi --depth 2").unwrap();
+        assert_eq!(args.get_str("--message").unwrap(), Some("hi"));
+        assert_eq!(args.get_int("--depth").unwrap(), Some(2));
+    }
+}
@@ -0,0 +1,125 @@
+//! The `--color` option and whether output should be colored.
+
+use std::env;
+
+use super::{CliArgs, SchemaError};
+use crate::term::{self, Stream};
+
+/// When to color output, the value of `--color`, see [`CliArgs::with_color_option`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Decide from the environment and the output stream.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether output to `stream` should be colored. `Always` and `Never`
+    /// decide on their own. `Auto` is off if `NO_COLOR` is set to anything
+    /// but the empty string, else on if `CLICOLOR_FORCE` is set to anything
+    /// but `0`, else on if `stream` is a terminal and `TERM` is not `dumb`.
+    pub fn enabled_for(self, stream: Stream) -> bool {
+        self.resolve(term::is_tty(stream), |var| env::var(var).ok())
+    }
+
+    fn resolve(self, is_terminal: bool, var: impl Fn(&str) -> Option<String>) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto if var("NO_COLOR").is_some_and(|val| !val.is_empty()) => false,
+            ColorChoice::Auto if var("CLICOLOR_FORCE").is_some_and(|val| val != "0") => true,
+            ColorChoice::Auto => is_terminal && var("TERM").is_none_or(|term| term != "dumb"),
+        }
+    }
+}
+
+impl CliArgs {
+    /// Registers `--color <auto|always|never>`, defaulting to `auto`. Once
+    /// registered, [`CliArgs::help`] and the errors of [`CliArgs::parse_or_exit`]
+    /// are colored as [`CliArgs::color_choice`] says. Records
+    /// [`SchemaError::KeyTaken`] if `--color` is already registered.
+    pub fn with_color_option(&mut self) -> &mut Self {
+        if self.keys.contains_key("--color") {
+            self.schema_error.get_or_insert(SchemaError::KeyTaken("--color".to_string()));
+            return self;
+        }
+        self.with("--color=s? ::>auto # When to color the output");
+        let _ = self.set_choices("--color", &["auto", "always", "never"], true);
+        self.color_option = true;
+        self
+    }
+
+    /// The value given to `--color`, [`ColorChoice::Auto`] when it is not
+    /// registered. Pass it to [`ColorChoice::enabled_for`] to decide for a stream.
+    pub fn color_choice(&self) -> ColorChoice {
+        match self.get_str("--color").ok().flatten().map(str::to_ascii_lowercase).as_deref() {
+            Some("always") => ColorChoice::Always,
+            Some("never") => ColorChoice::Never,
+            _ => ColorChoice::Auto,
+        }
+    }
+
+    /// Whether the crate's own output to `stream` is colored: only with
+    /// [`CliArgs::with_color_option`], and then as [`CliArgs::color_choice`] says.
+    pub(super) fn colors(&self, stream: Stream) -> bool {
+        self.color_option && self.color_choice().enabled_for(stream)
+    }
+}
+
+/// `text` in bold.
+pub(super) fn bold(text: &str) -> String {
+    format!("\x1b[1m{text}\x1b[0m")
+}
+
+/// `text` in bold red.
+pub(super) fn bold_red(text: &str) -> String {
+    format!("\x1b[1;31m{text}\x1b[0m")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::{CliArgs, ColorChoice, SchemaError};
+
+    #[test]
+    fn explicit_choice_beats_env() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |var: &str| vars.iter().find(|(name, _)| *name == var).map(|(_, val)| val.to_string())
+        };
+        assert!(ColorChoice::Always.resolve(false, env(&[("NO_COLOR", "1")])));
+        assert!(!ColorChoice::Never.resolve(true, env(&[("CLICOLOR_FORCE", "1")])));
+        assert!(!ColorChoice::Auto.resolve(true, env(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")])));
+        assert!(ColorChoice::Auto.resolve(true, env(&[("NO_COLOR", "")])));
+        assert!(ColorChoice::Auto.resolve(false, env(&[("CLICOLOR_FORCE", "1")])));
+        assert!(!ColorChoice::Auto.resolve(false, env(&[("CLICOLOR_FORCE", "0")])));
+        assert!(!ColorChoice::Auto.resolve(true, env(&[("TERM", "dumb")])));
+        assert!(ColorChoice::Auto.resolve(true, env(&[])));
+    }
+
+    #[test]
+    fn color_option() {
+        let mut args = CliArgs::new();
+        args.with_color_option();
+        args.parse("").unwrap();
+        assert_eq!(args.color_choice(), ColorChoice::Auto);
+        args.reset();
+        args.parse("--color never").unwrap();
+        assert_eq!(args.color_choice(), ColorChoice::Never);
+        assert!(!args.help().contains('\x1b'));
+
+        let mut args = CliArgs::new();
+        args.with_color_option();
+        args.parse("--color Always").unwrap();
+        assert_eq!(args.color_choice(), ColorChoice::Always);
+        assert!(args.help().contains("\x1b[1mUSAGE:\x1b[0m"));
+        assert!(args.parse("--color sometimes").is_err());
+
+        let mut args = CliArgs::new();
+        args.with("--color=s?");
+        args.parse("--color always").unwrap();
+        assert!(!args.help().contains('\x1b'));
+        args.with_color_option();
+        assert_eq!(args.schema_error(), Some(&SchemaError::KeyTaken("--color".to_string())));
+    }
+}
@@ -0,0 +1,585 @@
+//! Shell completion scripts for the registered args.
+
+use std::fmt::{self, Debug, Formatter, Write};
+use std::sync::Arc;
+
+use super::{Arg, ArgError, CliArgs, ParseEvent, SchemaError};
+#[cfg(doc)]
+use super::ParseError;
+
+/// What kind of value an arg takes, so completion scripts can offer the
+/// right candidates. See [`CliArgs::set_value_hint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueHint {
+    AnyPath,
+    File,
+    Dir,
+    ExecutablePath,
+    Hostname,
+    Url,
+    /// A value that cannot be completed, nothing is offered.
+    Nothing,
+}
+
+type CompleteFn = dyn Fn(&str) -> Vec<String> + Send + Sync;
+
+/// A callback offering values for an arg, see [`CliArgs::set_completer`].
+#[derive(Clone)]
+pub(super) struct Completer(Arc<CompleteFn>);
+
+impl Debug for Completer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("Completer")
+    }
+}
+
+const GENERATE_KEY: &str = "--generate-completions";
+const SHELLS: [&str; 4] = ["bash", "zsh", "fish", "powershell"];
+
+impl CliArgs {
+    /// Registers a callback offering values for an arg at completion time,
+    /// e.g. profile names read from a config file. It gets the partial value
+    /// being completed, candidates not starting with it are dropped.
+    pub fn set_completer(
+        &mut self,
+        key: &str,
+        completer: impl Fn(&str) -> Vec<String> + Send + Sync + 'static,
+    ) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        let completer = Some(Completer(Arc::new(completer)));
+        match &mut self.args[ind] {
+            Arg::Bool { settings, .. } => settings.completer = completer,
+            Arg::Int { settings, .. } => settings.completer = completer,
+            Arg::String { settings, .. } => settings.completer = completer,
+        }
+        Ok(())
+    }
+
+    /// The completion candidates for the last of `words`, the words typed
+    /// after the program name. Keys are offered for a word starting with `-`,
+    /// values after a key taking one, both for `--key=value`. Values are the
+    /// arg's choices and what its [`CliArgs::set_completer`] callback offers.
+    ///
+    /// Invoked through `prog __complete <shell> <words>..`, see
+    /// [`CliArgs::parse_from`], by the scripts of [`CliArgs::completions_bash`]
+    /// and [`CliArgs::completions_zsh`].
+    pub fn complete(&self, words: &[String]) -> Vec<String> {
+        let (cur, before) = match words.split_last() {
+            Some((cur, before)) => (cur.as_str(), before),
+            None => ("", words),
+        };
+        if let Some((key, partial)) = cur.split_once('=').filter(|(key, _)| Self::is_long_key(key)) {
+            return match self.keys.get(key) {
+                Some(&ind) => self.complete_values(ind, partial).into_iter().map(|v| format!("{key}={v}")).collect(),
+                None => Vec::new(),
+            };
+        }
+
+        let pending = before.last()
+            .and_then(|prev| self.keys.get(prev.as_str()))
+            .filter(|ind| !self.args[**ind].is_bool());
+        match pending {
+            Some(&ind) if !cur.starts_with('-') => self.complete_values(ind, cur),
+            _ if cur.is_empty() || cur.starts_with('-') => (0..self.args.len())
+                .filter(|ind| !self.is_positional(*ind))
+                .flat_map(|ind| {
+                    let (key_l, key_s) = self.arg_keys(ind);
+                    [key_l, key_s]
+                })
+                .flatten()
+                .filter(|key| key.starts_with(cur))
+                .map(String::from)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn complete_values(&self, ind: usize, partial: &str) -> Vec<String> {
+        let arg = &self.args[ind];
+        let mut values = arg.choices().to_vec();
+        if let Some(Completer(completer)) = arg.completer() {
+            values.extend(completer(partial));
+        }
+        values.retain(|v| v.starts_with(partial));
+        values
+    }
+
+    /// Registers `--generate-completions <bash|zsh|fish|powershell>`, which
+    /// makes a parse return the completion script for the shell as
+    /// [`ParseError::CompletionsRequested`], named after the binary, so that
+    /// `mytool --generate-completions bash > /etc/bash_completion.d/mytool`
+    /// works. [`CliArgs::parse_or_exit`] prints it and exits with code 0.
+    /// Fails with [`SchemaError::KeyTaken`] if the key is already registered.
+    pub fn with_generate_completions(&mut self) -> Result<&mut Self, SchemaError> {
+        if self.keys.contains_key(GENERATE_KEY) {
+            return Err(SchemaError::KeyTaken(GENERATE_KEY.to_string()));
+        }
+        self.with("--generate-completions=s? # Print the completion script for a shell");
+        let _ = self.set_choices(GENERATE_KEY, &SHELLS, true);
+        self.generate_completions = true;
+        Ok(self)
+    }
+
+    /// The script for the shell named by `--generate-completions` among
+    /// `tokens`, if registered and given with a known shell. A token taken
+    /// as the value of another key does not count.
+    pub(super) fn requested_completions(&self, tokens: &[String]) -> Option<String> {
+        if !self.generate_completions {
+            return None;
+        }
+        let shell = self.parse_events(tokens).find_map(|event| match event {
+            ParseEvent::Value { key, value } if key == GENERATE_KEY => Some(value),
+            _ => None,
+        })?;
+        let prog = self.resolved_bin_name();
+        match shell.to_ascii_lowercase().as_str() {
+            "bash" => Some(self.completions_bash(&prog)),
+            "zsh" => Some(self.completions_zsh(&prog)),
+            "fish" => Some(self.completions_fish(&prog)),
+            "powershell" => Some(self.completions_powershell(&prog)),
+            _ => None, // rejected as a choice by the parse
+        }
+    }
+
+    /// A bash completion script for `prog_name`, to be sourced or installed
+    /// under `bash-completion/completions`. Values with a [`ValueHint`] are
+    /// completed by bash, everything else by asking `prog_name __complete`.
+    pub fn completions_bash(&self, prog_name: &str) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# bash completion for {prog_name}");
+        if let Some(about) = self.meta_about() {
+            let _ = writeln!(out, "# {about}");
+        }
+        let func = format!("_{}", shell_ident(prog_name));
+        let _ = writeln!(out, "{func}() {{");
+        out.push_str("    local cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+        out.push_str("    local prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+        out.push_str("    local words=(\"${COMP_WORDS[@]:1:COMP_CWORD}\") prefix=\"\"\n");
+        // `--key=value` is split into `--key`, `=` and `value` while COMP_WORDBREAKS holds `=`,
+        // and is one word otherwise, whose replies then need the `--key=` back
+        out.push_str("    if [[ \"$cur\" == \"=\" && COMP_CWORD -gt 1 ]]; then\n");
+        out.push_str("        cur=\"\"\n");
+        out.push_str("        words=(\"${COMP_WORDS[@]:1:COMP_CWORD-2}\" \"$prev\" \"\")\n");
+        out.push_str("    elif [[ \"$prev\" == \"=\" && COMP_CWORD -gt 2 ]]; then\n");
+        out.push_str("        prev=\"${COMP_WORDS[COMP_CWORD-2]}\"\n");
+        out.push_str("        words=(\"${COMP_WORDS[@]:1:COMP_CWORD-3}\" \"$prev\" \"$cur\")\n");
+        out.push_str("    elif [[ \"$cur\" == --*=* ]]; then\n");
+        out.push_str("        prev=\"${cur%%=*}\" prefix=\"${cur%%=*}=\" cur=\"${cur#*=}\"\n");
+        out.push_str("        words=(\"${COMP_WORDS[@]:1:COMP_CWORD-1}\" \"$prev\" \"$cur\")\n");
+        out.push_str("    fi\n");
+
+        let mut cases = String::new();
+        for (ind, arg) in self.args.iter().enumerate().filter(|(ind, _)| !self.is_positional(*ind)) {
+            if arg.is_bool() || !arg.choices().is_empty() {
+                continue;
+            }
+            let reply = match arg.value_hint() {
+                None => continue, // asked for below, falling back to the default completion
+                Some(ValueHint::AnyPath | ValueHint::File) => "COMPREPLY=($(compgen -P \"$prefix\" -f -- \"$cur\"))",
+                Some(ValueHint::Dir) => "COMPREPLY=($(compgen -P \"$prefix\" -d -- \"$cur\"))",
+                Some(ValueHint::ExecutablePath) => "COMPREPLY=($(compgen -P \"$prefix\" -c -- \"$cur\"))",
+                Some(ValueHint::Hostname) => "COMPREPLY=($(compgen -P \"$prefix\" -A hostname -- \"$cur\"))",
+                Some(ValueHint::Url | ValueHint::Nothing) => "compopt +o default; COMPREPLY=()",
+            };
+            let (key_l, key_s) = self.arg_keys(ind);
+            let pattern = [key_l, key_s].into_iter().flatten().collect::<Vec<_>>().join("|");
+            let _ = write!(cases, "        {pattern})\n            {reply}\n            return 0\n            ;;\n");
+        }
+        if !cases.is_empty() {
+            out.push_str("    case \"$prev\" in\n");
+            out.push_str(&cases);
+            out.push_str("    esac\n");
+        }
+
+        out.push_str("    local IFS=$'\\n'\n");
+        let _ = writeln!(out, "    COMPREPLY=($({} __complete bash \"${{words[@]}}\"))", shell_quote(prog_name));
+        out.push_str("    COMPREPLY=(\"${COMPREPLY[@]/#/$prefix}\")\n");
+        out.push_str("}\n");
+        let _ = writeln!(out, "complete -o default -F {func} {prog_name}");
+        out
+    }
+
+    /// A zsh completion script for `prog_name`, to be installed as
+    /// `_prog_name` in a directory on `$fpath`. Values without a
+    /// [`ValueHint`] are completed by asking `prog_name __complete`.
+    pub fn completions_zsh(&self, prog_name: &str) -> String {
+        let func = format!("_{}_complete", shell_ident(prog_name));
+        let mut out = String::new();
+        let _ = writeln!(out, "#compdef {prog_name}");
+        let _ = writeln!(out, "# zsh completion for {prog_name}");
+        if let Some(about) = self.meta_about() {
+            let _ = writeln!(out, "# {about}");
+        }
+        out.push('\n');
+        let _ = writeln!(out, "{func}() {{");
+        out.push_str("    local -a candidates\n");
+        let _ = writeln!(out, "    candidates=(${{(f)\"$({} __complete zsh \"${{(@)words[2,CURRENT]}}\")\"}})", shell_quote(prog_name));
+        out.push_str("    if (( ${#candidates} )); then\n        compadd -a candidates\n    else\n        _default\n    fi\n");
+        out.push_str("}\n\n");
+
+        out.push_str("_arguments -s");
+        for (ind, arg) in self.args.iter().enumerate().filter(|(ind, _)| !self.is_positional(*ind)) {
+            let (key_l, key_s) = self.arg_keys(ind);
+            let description = arg.description().map(zsh_escape).unwrap_or_default();
+            let value = match arg {
+                Arg::Bool { .. } => String::new(),
+                _ => format!(":{}:{}", key_l.map_or("value", |k| &k[2..]), zsh_action(arg, &func)),
+            };
+            let repeat = if arg.is_greedy() { "*" } else { "" };
+            for key in [key_s, key_l].into_iter().flatten() {
+                let spec = format!("{repeat}{key}[{description}]{value}");
+                let _ = write!(out, " \\\n    {}", shell_quote(&spec));
+            }
+        }
+        out.push('\n');
+        out
+    }
+
+    /// A fish completion script for `prog_name`, to be installed as
+    /// `prog_name.fish` under `~/.config/fish/completions`. Every key gets a
+    /// `complete` line, `-r` when it takes a value. Values without a
+    /// [`ValueHint`] are completed by asking `prog_name __complete`.
+    pub fn completions_fish(&self, prog_name: &str) -> String {
+        let prog = fish_quote(prog_name);
+        let dynamic = format!("\"({} __complete fish (commandline -opc)[2..] \\\"\\$(commandline -ct)\\\")\"", prog);
+        let mut out = String::new();
+        let _ = writeln!(out, "# fish completion for {prog_name}");
+        if let Some(about) = self.meta_about() {
+            let _ = writeln!(out, "# {about}");
+        }
+        for (ind, arg) in self.args.iter().enumerate().filter(|(ind, _)| !self.is_positional(*ind)) {
+            let (key_l, key_s) = self.arg_keys(ind);
+            let mut line = format!("complete -c {prog}");
+            if let Some(key_l) = key_l {
+                let _ = write!(line, " -l {}", fish_quote(&key_l[2..]));
+            }
+            if let Some(key_s) = key_s {
+                let option = if key_s.chars().count() == 2 { "-s" } else { "-o" }; // old style `-name`
+                let _ = write!(line, " {option} {}", fish_quote(&key_s[1..]));
+            }
+            if !arg.is_bool() {
+                let _ = write!(line, " {}", fish_values(arg, &dynamic));
+            }
+            if let Some(description) = arg.description() {
+                let _ = write!(line, " -d {}", fish_quote(description));
+            }
+            let _ = writeln!(out, "{line}");
+        }
+        out
+    }
+
+    /// A PowerShell completion script for `prog_name`, to be dot-sourced
+    /// from `$PROFILE`. Keys are offered with their descriptions as tooltips.
+    /// Values with a [`ValueHint`] are completed by PowerShell's own
+    /// completers, everything else by asking `prog_name __complete`.
+    pub fn completions_powershell(&self, prog_name: &str) -> String {
+        let prog = powershell_quote(prog_name);
+        let mut out = String::new();
+        let _ = writeln!(out, "# powershell completion for {prog_name}");
+        if let Some(about) = self.meta_about() {
+            let _ = writeln!(out, "# {about}");
+        }
+        let _ = writeln!(out, "Register-ArgumentCompleter -Native -CommandName {prog} -ScriptBlock {{");
+        out.push_str("    param($wordToComplete, $commandAst, $cursorPosition)\n");
+        out.push_str("    $words = @($commandAst.CommandElements | Select-Object -Skip 1 |\n");
+        out.push_str("        Where-Object { $_.Extent.EndOffset -le $cursorPosition } | ForEach-Object { $_.ToString() })\n");
+        out.push_str("    if ($wordToComplete -eq '') { $words += '' }\n");
+        out.push_str("    $prev = if ($words.Count -ge 2) { $words[-2] } else { '' }\n");
+        out.push_str("    $key, $value, $prefix = $prev, $wordToComplete, ''\n");
+        out.push_str("    if ($wordToComplete -like '--*=*') {\n");
+        out.push_str("        $key, $value = $wordToComplete.Split('=', 2)\n");
+        out.push_str("        $prefix = \"$key=\"\n");
+        out.push_str("    }\n");
+
+        let mut cases = String::new();
+        for (ind, arg) in self.args.iter().enumerate().filter(|(ind, _)| !self.is_positional(*ind)) {
+            if arg.is_bool() || !arg.choices().is_empty() {
+                continue;
+            }
+            let Some(hint) = arg.value_hint() else {
+                continue;
+            };
+            let (key_l, key_s) = self.arg_keys(ind);
+            let keys = [key_l, key_s].into_iter().flatten().map(powershell_quote).collect::<Vec<_>>().join(", ");
+            let _ = writeln!(cases, "        {{ $_ -in {keys} }} {{");
+            if let Some(values) = powershell_values(hint) {
+                let _ = writeln!(cases, "            {values}");
+            }
+            cases.push_str("            return\n        }\n");
+        }
+        if !cases.is_empty() {
+            out.push_str("    switch ($key) {\n");
+            out.push_str(&cases);
+            out.push_str("    }\n");
+        }
+
+        let mut flags = Vec::new();
+        let mut value_keys = Vec::new();
+        for (ind, arg) in self.args.iter().enumerate().filter(|(ind, _)| !self.is_positional(*ind)) {
+            let (key_l, key_s) = self.arg_keys(ind);
+            for key in [key_l, key_s].into_iter().flatten() {
+                let tooltip = match (arg.is_bool(), arg.description()) {
+                    (true, Some(description)) => description.to_string(),
+                    (true, None) => key.to_string(),
+                    (false, Some(description)) => format!("{key} <value>  {description}"),
+                    (false, None) => format!("{key} <value>"),
+                };
+                let key = powershell_quote(key);
+                flags.push(format!(
+                    "        [System.Management.Automation.CompletionResult]::new({key}, {key}, 'ParameterName', {})",
+                    powershell_quote(&tooltip),
+                ));
+                if !arg.is_bool() {
+                    value_keys.push(key);
+                }
+            }
+        }
+        let _ = writeln!(out, "    $valueKeys = @({})", value_keys.join(", "));
+        out.push_str("    if ($valueKeys -contains $prev -or $wordToComplete -like '--*=*') {\n");
+        let _ = writeln!(out, "        & {prog} __complete powershell @words | ForEach-Object {{");
+        out.push_str("            [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n");
+        out.push_str("        }\n        return\n    }\n");
+        let _ = writeln!(out, "    @(\n{}\n    ) | Where-Object {{ $_.CompletionText -like \"$wordToComplete*\" }}", flags.join("\n"));
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// The PowerShell pipeline completing `$value` for `hint`, each result
+/// prefixed by `$prefix`, or none if nothing is offered.
+fn powershell_values(hint: ValueHint) -> Option<String> {
+    let result = "[System.Management.Automation.CompletionResult]::new";
+    let completer = "[System.Management.Automation.CompletionCompleters]";
+    let rewrap = format!("ForEach-Object {{ {result}($prefix + $_.CompletionText, $_.ListItemText, $_.ResultType, $_.ToolTip) }}");
+    Some(match hint {
+        ValueHint::AnyPath | ValueHint::File => format!("{completer}::CompleteFilename($value) | {rewrap}"),
+        ValueHint::Dir => format!("{completer}::CompleteFilename($value) | Where-Object {{ $_.ResultType -eq 'ProviderContainer' }} | {rewrap}"),
+        ValueHint::ExecutablePath => format!("{completer}::CompleteCommand($value) | {rewrap}"),
+        ValueHint::Hostname => format!(concat!(
+            "$hosts = if ($env:SystemRoot) {{ \"$env:SystemRoot\\System32\\drivers\\etc\\hosts\" }} else {{ '/etc/hosts' }}\n",
+            "            (Get-Content -ErrorAction SilentlyContinue $hosts) -replace '#.*' -split '\\s+' |\n",
+            "                Where-Object {{ $_ -and $_ -notmatch '^[\\d.]+$|:' -and $_ -like \"$value*\" }} | Sort-Object -Unique |\n",
+            "                ForEach-Object {{ {result}($prefix + $_, $_, 'ParameterValue', $_) }}",
+        ), result = result),
+        ValueHint::Url | ValueHint::Nothing => return None,
+    })
+}
+
+/// `s` as a single quoted PowerShell string, where `'` is doubled.
+fn powershell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// The fish `complete` options completing the value of `arg`, `dynamic`
+/// being the command substitution that asks the program.
+fn fish_values(arg: &Arg, dynamic: &str) -> String {
+    if !arg.choices().is_empty() {
+        return format!("-r -f -a {dynamic}");
+    }
+    match arg.value_hint() {
+        None => format!("-r -f -a {dynamic}"),
+        Some(ValueHint::AnyPath | ValueHint::File) => "-r -F".to_string(),
+        Some(ValueHint::Dir) => "-r -f -a '(__fish_complete_directories (commandline -ct))'".to_string(),
+        Some(ValueHint::ExecutablePath) => "-r -f -a '(__fish_complete_command)'".to_string(),
+        Some(ValueHint::Hostname) => "-r -f -a '(__fish_print_hostnames)'".to_string(),
+        Some(ValueHint::Url | ValueHint::Nothing) => "-r -f".to_string(),
+    }
+}
+
+/// The zsh `_arguments` action completing the value of `arg`, `dynamic`
+/// being the function that asks the program.
+fn zsh_action(arg: &Arg, dynamic: &str) -> String {
+    if !arg.choices().is_empty() {
+        return dynamic.to_string();
+    }
+    match arg.value_hint() {
+        None => dynamic,
+        Some(ValueHint::AnyPath | ValueHint::File) => "_files",
+        Some(ValueHint::Dir) => "_directories",
+        Some(ValueHint::ExecutablePath) => "_command_names -e",
+        Some(ValueHint::Hostname) => "_hosts",
+        Some(ValueHint::Url) => "_urls",
+        Some(ValueHint::Nothing) => " ",
+    }
+    .to_string()
+}
+
+/// `name` with every character that is not valid in a shell function name replaced by `_`.
+fn shell_ident(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect()
+}
+
+/// `s` as a single quoted shell word.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// `s` as a single quoted fish word, where only `\` and `'` are escaped.
+fn fish_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Escapes the characters `_arguments` gives a meaning to in descriptions and values.
+fn zsh_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '[' | ']' | ':' | '\\' | '(' | ')') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shell_quote, ValueHint};
+    use crate::args::{ArgError, CliArgs, ParseError, SchemaError};
+
+    fn hinted() -> CliArgs {
+        let mut args = CliArgs::new();
+        args
+            .with("--config/-c=s # Config file")
+            .with("--workdir=s?")
+            .with("--host=s?")
+            .with("--level=s?")
+            .with("--name=s?")
+            .with("--verbose/-v=b? # Say more");
+        args.set_value_hint("--config", ValueHint::File).unwrap();
+        args.set_value_hint("--workdir", ValueHint::Dir).unwrap();
+        args.set_value_hint("--host", ValueHint::Hostname).unwrap();
+        args.set_choices("--level", &["low", "high"], false).unwrap();
+        args
+    }
+
+    #[test]
+    fn generate_completions_flag() {
+        let mut args = hinted();
+        args.bin_name("my-tool").with_generate_completions().unwrap();
+        assert_eq!(args.with_generate_completions().err(), Some(SchemaError::KeyTaken("--generate-completions".to_string())));
+
+        // wins over the missing required `--config`
+        let tokens = |line: &str| CliArgs::split_line(line);
+        assert_eq!(
+            args.parse_from(&tokens("--generate-completions bash")),
+            Err(ParseError::CompletionsRequested(args.completions_bash("my-tool"))),
+        );
+        let Err(ParseError::CompletionsRequested(script)) = args.parse_from(&tokens("-v --generate-completions=Fish")) else {
+            panic!("expected the fish script");
+        };
+        assert!(script.starts_with("# fish completion for my-tool\n"));
+
+        assert!(matches!(args.parse("-c x --generate-completions tcsh"), Err(ParseError::InvalidValue { .. })));
+        args.reset();
+        assert!(matches!(args.parse("-c x -- --generate-completions zsh"), Ok(())));
+
+        args.reset();
+        args.set_allow_hyphen_values("--name", true).unwrap();
+        args.parse("-c x --name --generate-completions").unwrap();
+        assert_eq!(args.get_str("--name").unwrap(), Some("--generate-completions"));
+    }
+
+    #[test]
+    fn bash_uses_hints() {
+        let bash = hinted().completions_bash("my-tool");
+        assert!(bash.starts_with("# bash completion for my-tool\n_my_tool() {\n"));
+        assert!(bash.contains("        --config|-c)\n            COMPREPLY=($(compgen -P \"$prefix\" -f -- \"$cur\"))\n"));
+        assert!(bash.contains("        --workdir)\n            COMPREPLY=($(compgen -P \"$prefix\" -d -- \"$cur\"))\n"));
+        assert!(bash.contains("        --host)\n            COMPREPLY=($(compgen -P \"$prefix\" -A hostname -- \"$cur\"))\n"));
+        assert!(!bash.contains("--level)") && !bash.contains("--name)") && !bash.contains("--verbose|-v)"));
+        assert!(bash.contains("    COMPREPLY=($('my-tool' __complete bash \"${words[@]}\"))\n    COMPREPLY=(\"${COMPREPLY[@]/#/$prefix}\")\n"));
+        // `--config=a.t` as `--config`, `=`, `a.t` while COMP_WORDBREAKS holds `=`, or as one word
+        assert!(bash.contains("    elif [[ \"$prev\" == \"=\" && COMP_CWORD -gt 2 ]]; then\n        prev=\"${COMP_WORDS[COMP_CWORD-2]}\"\n"));
+        assert!(bash.contains("        prev=\"${cur%%=*}\" prefix=\"${cur%%=*}=\" cur=\"${cur#*=}\"\n"));
+        assert!(bash.ends_with("complete -o default -F _my_tool my-tool\n"));
+    }
+
+    #[test]
+    fn zsh_uses_hints() {
+        let zsh = hinted().completions_zsh("mytool");
+        assert!(zsh.starts_with("#compdef mytool\n"));
+        assert!(zsh.contains("    candidates=(${(f)\"$('mytool' __complete zsh \"${(@)words[2,CURRENT]}\")\"})\n"));
+        assert!(zsh.contains(" \\\n    '-c[Config file]:config:_files'"));
+        assert!(zsh.contains(" \\\n    '--config[Config file]:config:_files'"));
+        assert!(zsh.contains("'--workdir[]:workdir:_directories'"));
+        assert!(zsh.contains("'--host[]:host:_hosts'"));
+        assert!(zsh.contains("'--level[]:level:_mytool_complete'"));
+        assert!(zsh.contains("'--name[]:name:_mytool_complete'"));
+        assert!(zsh.contains("'-v[Say more]'"));
+    }
+
+    #[test]
+    fn fish_line_per_arg() {
+        let mut args = hinted();
+        args.with("--quote=s? # It's a \\ test");
+        let fish = args.completions_fish("my-tool");
+        assert!(fish.starts_with("# fish completion for my-tool\n"));
+        let dynamic = "\"('my-tool' __complete fish (commandline -opc)[2..] \\\"\\$(commandline -ct)\\\")\"";
+        let lines: Vec<&str> = fish.lines().skip(1).collect();
+        assert_eq!(lines, [
+            "complete -c 'my-tool' -l 'config' -s 'c' -r -F -d 'Config file'".to_string(),
+            "complete -c 'my-tool' -l 'workdir' -r -f -a '(__fish_complete_directories (commandline -ct))'".to_string(),
+            "complete -c 'my-tool' -l 'host' -r -f -a '(__fish_print_hostnames)'".to_string(),
+            format!("complete -c 'my-tool' -l 'level' -r -f -a {dynamic}"),
+            format!("complete -c 'my-tool' -l 'name' -r -f -a {dynamic}"),
+            "complete -c 'my-tool' -l 'verbose' -s 'v' -d 'Say more'".to_string(),
+            format!("complete -c 'my-tool' -l 'quote' -r -f -a {dynamic} -d 'It\\'s a \\\\ test'"),
+        ]);
+    }
+
+    #[test]
+    fn powershell_lists_every_key() {
+        let mut args = hinted();
+        args.with("--quote=s? # It's quoted");
+        let ps = args.completions_powershell("my-tool");
+        assert!(ps.starts_with("# powershell completion for my-tool\nRegister-ArgumentCompleter -Native -CommandName 'my-tool' -ScriptBlock {\n"));
+        assert!(ps.contains("        & 'my-tool' __complete powershell @words | ForEach-Object {\n"));
+        for key in ["--config", "-c", "--workdir", "--host", "--level", "--name", "--verbose", "-v", "--quote"] {
+            assert!(ps.contains(&format!("::new('{key}', '{key}', 'ParameterName', ")), "no completion for {key}");
+        }
+        assert!(ps.contains("::new('-v', '-v', 'ParameterName', 'Say more')"));
+        assert!(ps.contains("::new('--name', '--name', 'ParameterName', '--name <value>')"));
+        assert!(ps.contains("::new('--quote', '--quote', 'ParameterName', '--quote <value>  It''s quoted')"));
+        assert!(ps.contains("    $valueKeys = @('--config', '-c', '--workdir', '--host', '--level', '--name', '--quote')\n"));
+        assert!(ps.contains("        { $_ -in '--config', '-c' } {\n            [System.Management.Automation.CompletionCompleters]::CompleteFilename($value) | "));
+        assert!(ps.contains("        { $_ -in '--workdir' } {\n            [System.Management.Automation.CompletionCompleters]::CompleteFilename($value) | Where-Object { $_.ResultType -eq 'ProviderContainer' } | "));
+        assert!(ps.contains("        { $_ -in '--host' } {\n            $hosts = "));
+        assert!(!ps.contains("{ $_ -in '--level' }") && !ps.contains("{ $_ -in '--name' }"));
+    }
+
+    #[test]
+    fn complete_protocol() {
+        let mut args = hinted();
+        args.set_completer("--name", |partial| {
+            ["alice", "bob", "albert"].iter().map(|n| n.to_string()).filter(|n| n.starts_with(partial)).collect()
+        }).unwrap();
+        let mut complete = |line: &str| {
+            let mut tokens: Vec<String> = ["__complete", "bash"].iter().map(|s| s.to_string()).collect();
+            tokens.extend(line.split(' ').map(String::from));
+            match args.parse_from(&tokens) {
+                Err(ParseError::Completions(candidates)) => candidates,
+                other => panic!("expected completions for `{}`, got {:?}", line, other),
+            }
+        };
+
+        assert_eq!(complete("--w"), ["--workdir"]);
+        assert_eq!(complete("-c x --v"), ["--verbose"]);
+        assert_eq!(complete("--level h"), ["high"]);
+        assert_eq!(complete("--level="), ["--level=low", "--level=high"]);
+        assert_eq!(complete("--name al"), ["alice", "albert"]);
+        assert_eq!(complete("--name=b"), ["--name=bob"]);
+        assert_eq!(complete("--config "), [] as [String; 0]);
+        assert_eq!(complete("-v x"), [] as [String; 0]);
+        assert_eq!(complete("").len(), 8);
+    }
+
+    #[test]
+    fn hint_needs_known_key() {
+        assert!(matches!(CliArgs::new().set_value_hint("--nope", ValueHint::Url), Err(ArgError::WrongKey)));
+    }
+
+    #[test]
+    fn quotes_for_shell() {
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+}
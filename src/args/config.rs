@@ -0,0 +1,166 @@
+//! Defaults read from config files of `key = value` lines, see
+//! [`CliArgs::load_config`].
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use super::{Arg, CliArgs, SchemaError};
+
+/// Why a config file could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The file could not be read.
+    Io { path: PathBuf, reason: String },
+    /// A line that is neither `key = value`, a `#` comment nor blank, `line` counting from 1.
+    Syntax { path: PathBuf, line: usize },
+    /// A key that names no arg or a value of the wrong type.
+    Schema { path: PathBuf, error: SchemaError },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, reason } => write!(f, "cannot read `{}`: {}", path.display(), reason),
+            ConfigError::Syntax { path, line } => write!(f, "`{}` line {}: expected `key = value`", path.display(), line),
+            ConfigError::Schema { path, error } => write!(f, "`{}`: {}", path.display(), error),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl CliArgs {
+    /// Reads `key = value` lines from the file at `path` as defaults, see
+    /// [`CliArgs::apply_defaults`], so values given on the command line or
+    /// through the environment take precedence. A key is either registered
+    /// as written or a long key without its dashes, and a value may be
+    /// wrapped in double quotes to keep surrounding whitespace. Blank lines
+    /// and lines starting with `#` are skipped, a later line for the same
+    /// arg replaces an earlier one.
+    ///
+    /// ```text
+    /// # ~/.config/mytool/config
+    /// port = 8080
+    /// -v = true
+    /// name = " alp "
+    /// ```
+    ///
+    /// Nothing is applied if a line is invalid.
+    pub fn load_config(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io { path: path.to_path_buf(), reason: e.to_string() })?;
+        self.args = self.with_config(self.args.clone(), path, &contents)?;
+        Ok(())
+    }
+
+    /// Loads the files at `paths` in order like [`CliArgs::load_config`], a
+    /// later file replacing the values of an earlier one, e.g. the system
+    /// wide config before the user's. Files that do not exist are skipped
+    /// and returned, for the caller to warn about if it wants to. Nothing
+    /// is applied if a line of any file is invalid.
+    pub fn load_config_layers(&mut self, paths: &[&Path]) -> Result<Vec<PathBuf>, ConfigError> {
+        let mut skipped = Vec::new();
+        let mut args = self.args.clone();
+        for path in paths {
+            match fs::read_to_string(path) {
+                Ok(contents) => args = self.with_config(args, path, &contents)?,
+                Err(e) if e.kind() == ErrorKind::NotFound => skipped.push(path.to_path_buf()),
+                Err(e) => return Err(ConfigError::Io { path: path.to_path_buf(), reason: e.to_string() }),
+            }
+        }
+        self.args = args;
+        Ok(skipped)
+    }
+
+    /// `args` with the defaults of the file at `path` applied.
+    fn with_config(&self, args: Vec<Arg>, path: &Path, contents: &str) -> Result<Vec<Arg>, ConfigError> {
+        let mut defaults = HashMap::new();
+        for (ind, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=')
+                .filter(|(key, _)| !key.trim().is_empty())
+                .ok_or_else(|| ConfigError::Syntax { path: path.to_path_buf(), line: ind + 1 })?;
+            let key = key.trim();
+            let key = match self.keys.contains_key(key) || key.starts_with('-') {
+                true => key.to_string(),
+                false => format!("--{key}"),
+            };
+            // keyed by arg, so a later line replaces an earlier one with another of its keys
+            let key = self.keys.get(key.as_str()).map_or(key, |ind| self.display_key(*ind).to_string());
+            let value = value.trim();
+            let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+            defaults.insert(key, value.to_string());
+        }
+        self.with_defaults(args, &defaults).map_err(|error| ConfigError::Schema { path: path.to_path_buf(), error })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::ConfigError;
+    use crate::args::{CliArgs, SchemaError};
+
+    fn write(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn layers_override_in_order() {
+        let dir = std::env::temp_dir().join(format!("clitrs-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let system = write(&dir, "system", "# system wide\nport = 80\nhost = example.org\n\n--verbose = true\n");
+        let user = write(&dir, "user", "-p = 8080\nname = \" alp \"\n");
+        let missing = dir.join("missing");
+
+        let mut args = CliArgs::new();
+        args.with("--port/-p=i?").with("--host=s?").with("--name=s?").with("--verbose/-v=b?");
+        let skipped = args.load_config_layers(&[&system, &missing, &user]).unwrap();
+        assert_eq!(skipped, [missing]);
+
+        args.parse("--host localhost").unwrap();
+        assert_eq!(args.get_int("--port").unwrap(), Some(8080));
+        assert_eq!(args.get_str("--host").unwrap(), Some("localhost"));
+        assert_eq!(args.get_str("--name").unwrap(), Some(" alp "));
+        assert_eq!(args.get_bool("-v").unwrap(), Some(true));
+
+        args.reset();
+        args.parse("-p 1 --no-verbose").unwrap();
+        assert_eq!((args.get_int("-p").unwrap(), args.get_bool("-v").unwrap()), (Some(1), Some(false)));
+
+        let broken = write(&dir, "broken", "port = 1\njust words\n");
+        assert_eq!(args.load_config(&broken), Err(ConfigError::Syntax { path: broken.clone(), line: 2 }));
+        let unknown = write(&dir, "unknown", "colour = red\n");
+        assert_eq!(
+            args.load_config_layers(&[&unknown]),
+            Err(ConfigError::Schema { path: unknown.clone(), error: SchemaError::UnknownKey("--colour".to_string()) }),
+        );
+        assert!(matches!(args.load_config(&dir.join("missing")), Err(ConfigError::Io { .. })));
+
+        // a bad file applies nothing, neither does an earlier good layer
+        let invalid = write(&dir, "invalid", "host = other.org
+port = http
+");
+        assert!(matches!(args.load_config(&invalid), Err(ConfigError::Schema { .. })));
+        assert!(matches!(args.load_config_layers(&[&system, &invalid]), Err(ConfigError::Schema { .. })));
+        let reordered = write(&dir, "reordered", "--port = 1
+-p = 2
+");
+        args.load_config(&reordered).unwrap();
+        args.reset();
+        args.parse("").unwrap();
+        assert_eq!((args.get_int("-p").unwrap(), args.get_str("--host").unwrap()), (Some(2), Some("example.org")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
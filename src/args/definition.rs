@@ -0,0 +1,480 @@
+//! CLI definitions read from a TOML or YAML document, for CLIs that are
+//! reviewed as data rather than as Rust code.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use super::{ArgError, CliArgs, CliSubcommands, SchemaError};
+
+/// A definition document that could not be turned into a parser, with the
+/// path of the offending value, e.g. `commands.fetch.args[2].type`. The
+/// path is empty for a document that is not valid TOML or YAML.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefinitionError {
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for DefinitionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "{}", self.message),
+            false => write!(f, "`{}`: {}", self.path, self.message),
+        }
+    }
+}
+
+impl Error for DefinitionError {}
+
+const META_FIELDS: &[&str] = &["name", "bin_name", "author", "about", "long_about", "args"];
+const COMMAND_FIELDS: &[&str] = &["version", "version_long", "commands"];
+const ARG_FIELDS: &[&str] = &[
+    "key", "short", "type", "required", "default", "greedy", "nargs", "choices", "ignore_case", "env", "help",
+];
+
+impl CliArgs {
+    /// Builds the args described by a TOML definition, see the format at
+    /// [`CliSubcommands::from_definition_toml`], which is also needed for
+    /// versions and subcommands.
+    #[cfg(feature = "definition-toml")]
+    pub fn from_definition_toml(document: &str) -> Result<CliArgs, DefinitionError> {
+        Self::from_definition(&Node::from_toml_str(document)?)
+    }
+
+    /// Builds the args described by a YAML definition, see
+    /// [`CliArgs::from_definition_toml`].
+    #[cfg(feature = "definition-yaml")]
+    pub fn from_definition_yaml(document: &str) -> Result<CliArgs, DefinitionError> {
+        Self::from_definition(&Node::from_yaml_str(document)?)
+    }
+
+    fn from_definition(root: &Node) -> Result<CliArgs, DefinitionError> {
+        let fields = root.map("")?;
+        if let Some((field, _)) = fields.iter().find(|(field, _)| COMMAND_FIELDS.contains(&field.as_str())) {
+            return Err(error(field, "needs a `CliSubcommands`, use `CliSubcommands::from_definition_*`"));
+        }
+        let mut args = CliArgs::new();
+        build_args(&mut args, fields, "")?;
+        Ok(args)
+    }
+}
+
+impl CliSubcommands {
+    /// Builds the command tree described by a TOML definition.
+    ///
+    /// A document describes one command: its metadata, its args and, for
+    /// [`CliSubcommands`], its subcommands by name, each a document of the same
+    /// shape. In TOML:
+    ///
+    /// ```toml
+    /// name = "mytool"          # optional: name, bin_name, author, about, long_about
+    /// version = "1.2.0"        # optional, CliSubcommands only: version, version_long
+    ///
+    /// [[args]]
+    /// key = "--config"         # long key, or short key when there is no long one
+    /// short = "-c"             # optional
+    /// type = "string"          # bool, int or string
+    /// required = false         # optional, defaults to false
+    /// default = "mytool.toml"  # optional, a value of the arg's type
+    /// greedy = false           # optional, collect values until the next key
+    /// nargs = 2                # optional, take exactly this many values
+    /// choices = ["a", "b"]     # optional, string args only
+    /// ignore_case = false      # optional, for choices
+    /// env = "MYTOOL_CONFIG"    # optional, environment variable fallback
+    /// help = "Config file"     # optional
+    ///
+    /// [commands.fetch]         # CliSubcommands only
+    /// about = "Downloads changes"
+    /// ```
+    ///
+    /// YAML documents have the same fields. Unknown fields are errors, so
+    /// that a typo does not silently drop a setting.
+    #[cfg(feature = "definition-toml")]
+    pub fn from_definition_toml(document: &str) -> Result<CliSubcommands, DefinitionError> {
+        build_command(&Node::from_toml_str(document)?, "")
+    }
+
+    /// Builds the command tree described by a YAML definition, see
+    /// [`CliSubcommands::from_definition_toml`].
+    #[cfg(feature = "definition-yaml")]
+    pub fn from_definition_yaml(document: &str) -> Result<CliSubcommands, DefinitionError> {
+        build_command(&Node::from_yaml_str(document)?, "")
+    }
+}
+
+/// A value of either document format.
+enum Node {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    List(Vec<Node>),
+    Map(Vec<(String, Node)>),
+    /// A value no field takes, named for the error message.
+    Other(&'static str),
+}
+
+impl Node {
+    #[cfg(feature = "definition-toml")]
+    fn from_toml_str(document: &str) -> Result<Node, DefinitionError> {
+        let table: toml::Table = document.parse().map_err(|e: toml::de::Error| error("", e.message()))?;
+        Ok(Node::from_toml(toml::Value::Table(table)))
+    }
+
+    #[cfg(feature = "definition-toml")]
+    fn from_toml(value: toml::Value) -> Node {
+        match value {
+            toml::Value::String(s) => Node::Str(s),
+            toml::Value::Integer(i) => Node::Int(i),
+            toml::Value::Boolean(b) => Node::Bool(b),
+            toml::Value::Array(items) => Node::List(items.into_iter().map(Node::from_toml).collect()),
+            toml::Value::Table(table) => Node::Map(table.into_iter().map(|(k, v)| (k, Node::from_toml(v))).collect()),
+            toml::Value::Float(_) => Node::Other("a float"),
+            toml::Value::Datetime(_) => Node::Other("a datetime"),
+        }
+    }
+
+    #[cfg(feature = "definition-yaml")]
+    fn from_yaml_str(document: &str) -> Result<Node, DefinitionError> {
+        let value: serde_yaml::Value = serde_yaml::from_str(document).map_err(|e| error("", &e.to_string()))?;
+        Ok(Node::from_yaml(value))
+    }
+
+    #[cfg(feature = "definition-yaml")]
+    fn from_yaml(value: serde_yaml::Value) -> Node {
+        use serde_yaml::Value;
+        match value {
+            Value::Null => Node::Null,
+            Value::Bool(b) => Node::Bool(b),
+            Value::Number(n) => n.as_i64().map_or(Node::Other("a float"), Node::Int),
+            Value::String(s) => Node::Str(s),
+            Value::Sequence(items) => Node::List(items.into_iter().map(Node::from_yaml).collect()),
+            Value::Mapping(mapping) => Node::Map(mapping.into_iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        Value::String(s) => s,
+                        Value::Bool(b) => b.to_string(),
+                        Value::Number(n) => n.to_string(),
+                        _ => "?".to_string(),
+                    };
+                    (key, Node::from_yaml(v))
+                })
+                .collect()),
+            Value::Tagged(_) => Node::Other("a tagged value"),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Node::Null => "null",
+            Node::Bool(_) => "a bool",
+            Node::Int(_) => "an integer",
+            Node::Str(_) => "a string",
+            Node::List(_) => "a list",
+            Node::Map(_) => "a table",
+            Node::Other(kind) => kind,
+        }
+    }
+
+    fn expected(&self, path: &str, expected: &str) -> DefinitionError {
+        error(path, &format!("expected {}, found {}", expected, self.kind()))
+    }
+
+    fn map(&self, path: &str) -> Result<&[(String, Node)], DefinitionError> {
+        match self {
+            Node::Map(fields) => Ok(fields),
+            _ => Err(self.expected(path, "a table")),
+        }
+    }
+
+    fn list(&self, path: &str) -> Result<&[Node], DefinitionError> {
+        match self {
+            Node::List(items) => Ok(items),
+            _ => Err(self.expected(path, "a list")),
+        }
+    }
+
+    fn str(&self, path: &str) -> Result<&str, DefinitionError> {
+        match self {
+            Node::Str(s) => Ok(s),
+            _ => Err(self.expected(path, "a string")),
+        }
+    }
+
+    fn bool(&self, path: &str) -> Result<bool, DefinitionError> {
+        match self {
+            Node::Bool(b) => Ok(*b),
+            _ => Err(self.expected(path, "a bool")),
+        }
+    }
+
+    fn int(&self, path: &str) -> Result<i64, DefinitionError> {
+        match self {
+            Node::Int(i) => Ok(*i),
+            _ => Err(self.expected(path, "an integer")),
+        }
+    }
+
+    /// A scalar as the text a command line would give for it.
+    fn scalar(&self, path: &str) -> Result<String, DefinitionError> {
+        match self {
+            Node::Bool(b) => Ok(b.to_string()),
+            Node::Int(i) => Ok(i.to_string()),
+            Node::Str(s) => Ok(s.clone()),
+            _ => Err(self.expected(path, "a bool, integer or string")),
+        }
+    }
+}
+
+fn error(path: &str, message: &str) -> DefinitionError {
+    DefinitionError { path: path.to_string(), message: message.to_string() }
+}
+
+/// `path.field`, or `field` at the root.
+fn join(path: &str, field: &str) -> String {
+    match path.is_empty() {
+        true => field.to_string(),
+        false => format!("{path}.{field}"),
+    }
+}
+
+/// The value of `field`, absent when missing or null.
+fn field<'a>(fields: &'a [(String, Node)], field: &str) -> Option<&'a Node> {
+    fields.iter()
+        .find(|(name, _)| name == field)
+        .map(|(_, node)| node)
+        .filter(|node| !matches!(node, Node::Null))
+}
+
+fn check_fields(fields: &[(String, Node)], path: &str, known: &[&[&str]]) -> Result<(), DefinitionError> {
+    match fields.iter().find(|(name, _)| !known.iter().any(|k| k.contains(&name.as_str()))) {
+        Some((name, _)) => Err(error(&join(path, name), "unknown field")),
+        None => Ok(()),
+    }
+}
+
+fn build_command(node: &Node, path: &str) -> Result<CliSubcommands, DefinitionError> {
+    let fields = node.map(path)?;
+    let mut args = CliArgs::new();
+    build_args(&mut args, fields, path)?;
+
+    let mut command = CliSubcommands::new(args);
+    if let Some(version) = field(fields, "version") {
+        command.version(version.str(&join(path, "version"))?);
+    }
+    if let Some(version_long) = field(fields, "version_long") {
+        command.version_long(version_long.str(&join(path, "version_long"))?);
+    }
+    if let Some(commands) = field(fields, "commands") {
+        let commands_path = join(path, "commands");
+        for (name, sub) in commands.map(&commands_path)? {
+            command.command(name, build_command(sub, &join(&commands_path, name))?);
+        }
+    }
+    Ok(command)
+}
+
+type MetaSetter = for<'a> fn(&'a mut CliArgs, &str) -> &'a mut CliArgs;
+
+/// Sets the metadata and registers the args of a command's `fields`.
+fn build_args(args: &mut CliArgs, fields: &[(String, Node)], path: &str) -> Result<(), DefinitionError> {
+    check_fields(fields, path, &[META_FIELDS, COMMAND_FIELDS])?;
+    let meta: [(&str, MetaSetter); 5] = [
+        ("name", CliArgs::name),
+        ("bin_name", CliArgs::bin_name),
+        ("author", CliArgs::author),
+        ("about", CliArgs::about),
+        ("long_about", CliArgs::long_about),
+    ];
+    for (name, set) in meta {
+        if let Some(value) = field(fields, name) {
+            set(args, value.str(&join(path, name))?);
+        }
+    }
+
+    if let Some(list) = field(fields, "args") {
+        let args_path = join(path, "args");
+        for (ind, arg) in list.list(&args_path)?.iter().enumerate() {
+            build_arg(args, arg, &format!("{args_path}[{ind}]"))?;
+        }
+    }
+    Ok(())
+}
+
+fn build_arg(args: &mut CliArgs, node: &Node, path: &str) -> Result<(), DefinitionError> {
+    let fields = node.map(path)?;
+    check_fields(fields, path, &[ARG_FIELDS])?;
+    let at = |name: &str| join(path, name);
+    let str_field = |name: &str| field(fields, name).map(|node| node.str(&at(name))).transpose();
+    let bool_field = |name: &str| field(fields, name).map_or(Ok(false), |node| node.bool(&at(name)));
+
+    let key = str_field("key")?.ok_or_else(|| error(&at("key"), "missing field"))?;
+    if !key.starts_with('-') || key.contains(char::is_whitespace) {
+        return Err(error(&at("key"), "keys start with `--` (long) or `-` (short)"));
+    }
+    let short = str_field("short")?;
+    if let Some(short) = short {
+        if !CliArgs::is_short_key(short) || CliArgs::is_short_key(key) {
+            return Err(error(&at("short"), "expected a short key like `-n` next to a long `key`"));
+        }
+    }
+
+    let type_name = str_field("type")?.ok_or_else(|| error(&at("type"), "missing field"))?;
+    let type_code = match type_name {
+        "bool" => 'b',
+        "int" => 'i',
+        "string" => 's',
+        _ => return Err(error(&at("type"), &format!("unknown type `{type_name}`, expected `bool`, `int` or `string`"))),
+    };
+    let greedy = bool_field("greedy")?;
+    let nargs = field(fields, "nargs").map(|node| node.int(&at("nargs"))).transpose()?;
+    let count = match (greedy, nargs) {
+        (true, Some(_)) => return Err(error(&at("nargs"), "a greedy arg takes any number of values")),
+        (_, Some(n)) if n < 1 => return Err(error(&at("nargs"), "expected at least 1")),
+        (_, Some(n)) => format!("{{{n}}}"),
+        (true, None) => "*".to_string(),
+        (false, None) => String::new(),
+    };
+    let optional = if bool_field("required")? { "" } else { "?" };
+
+    let keys = short.map_or(key.to_string(), |short| format!("{key}/{short}"));
+    let registered = args.args.len();
+    args.with(&format!("{keys}={type_code}{count}{optional}"));
+    if let Some(e) = args.schema_error.take() {
+        let path = match e {
+            SchemaError::MultiCharShortKey(_) => at(if short.is_some() { "short" } else { "key" }),
+            _ => path.to_string(),
+        };
+        return Err(error(&path, &e.to_string()));
+    }
+
+    if let Some(help) = str_field("help")? {
+        args.args[registered].set_description(help);
+    }
+    if let Some(default) = field(fields, "default") {
+        let default = default.scalar(&at("default"))?;
+        if args.apply_defaults(&HashMap::from([(key.to_string(), default.clone())])).is_err() {
+            return Err(error(&at("default"), &format!("`{default}` is not a valid {type_name}")));
+        }
+    }
+    if let Some(choices) = field(fields, "choices") {
+        let choices = choices.list(&at("choices"))?
+            .iter()
+            .enumerate()
+            .map(|(ind, choice)| choice.str(&format!("{}[{ind}]", at("choices"))))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Err(ArgError::WrongType) = args.set_choices(key, &choices, bool_field("ignore_case")?) {
+            return Err(error(&at("choices"), "only string args have choices"));
+        }
+    }
+    if let Some(env) = str_field("env")? {
+        if env.is_empty() || env.contains(['=', '\0']) {
+            return Err(error(&at("env"), "expected an environment variable name"));
+        }
+        if args.set_env(key, env).is_err() {
+            return Err(error(&at("env"), &format!("cannot be set for `{key}`")));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "definition-toml", feature = "definition-yaml"))]
+mod tests {
+    use super::DefinitionError;
+    use crate::args::{CliArgs, CliSubcommands, ParseError};
+
+    const TOML: &str = include_str!("testdata/definition.toml");
+    const YAML: &str = include_str!("testdata/definition.yaml");
+
+    fn tokens(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn error(path: &str, message: &str) -> DefinitionError {
+        DefinitionError { path: path.to_string(), message: message.to_string() }
+    }
+
+    #[test]
+    fn builds_command_tree() {
+        let mut tool = CliSubcommands::from_definition_toml(TOML).unwrap();
+        assert_eq!(tool.args().schema_error(), None);
+        tool.parse_from(&tokens("-v fetch --remote origin --mode FULL")).unwrap();
+        assert_eq!(tool.args().get_bool("--verbose").unwrap(), Some(true));
+        assert_eq!(tool.args().get_str("--config").unwrap(), Some("mytool.toml"));
+        assert_eq!(tool.args().get_int("-j").unwrap(), Some(4));
+        let (name, fetch) = tool.selected().unwrap();
+        assert_eq!(name, "fetch");
+        assert_eq!(fetch.args().get_str("--remote").unwrap(), Some("origin"));
+        assert_eq!(fetch.args().get_str("--mode").unwrap(), Some("full"));
+        assert_eq!(fetch.args().description("--remote").unwrap(), Some("Remote to fetch from"));
+
+        assert_eq!(tool.parse_from(&tokens("push --version")), Err(ParseError::Version("mytool push 2.0.0".to_string())));
+        let mut tool = CliSubcommands::from_definition_toml(TOML).unwrap();
+        assert_eq!(tool.parse_from(&tokens("fetch")), Err(ParseError::MissingRequired("--remote".to_string())));
+    }
+
+    /// The key, choices and env of every arg, which the JSON schema leaves out.
+    fn settings(args: &CliArgs) -> Vec<(&str, &[String], Option<&str>)> {
+        (0..args.args.len()).map(|ind| (args.display_key(ind), args.args[ind].choices(), args.args[ind].env())).collect()
+    }
+
+    #[test]
+    fn round_trips_with_schema_json() {
+        let mut from_toml = CliSubcommands::from_definition_toml(TOML).unwrap();
+        let mut from_yaml = CliSubcommands::from_definition_yaml(YAML).unwrap();
+        assert_eq!(from_toml.args().schema_json(), include_str!("testdata/definition_v1.json"));
+        assert_eq!(from_yaml.args().schema_json(), from_toml.args().schema_json());
+        assert_eq!(from_yaml.args().help(), from_toml.args().help());
+        assert_eq!(settings(from_toml.args())[0], ("--config", &[][..], Some("MYTOOL_CONFIG")));
+        assert_eq!(settings(from_yaml.args()), settings(from_toml.args()));
+        assert_eq!(from_toml.command_names().collect::<Vec<_>>(), ["fetch", "push", "help"]);
+        assert_eq!(from_yaml.command_names().collect::<Vec<_>>(), ["fetch", "push", "help"]);
+
+        for line in ["fetch --remote origin", "push"] {
+            from_toml.parse_from(&tokens(line)).unwrap();
+            from_yaml.parse_from(&tokens(line)).unwrap();
+            let ((name, toml), (_, yaml)) = (from_toml.selected().unwrap(), from_yaml.selected().unwrap());
+            assert_eq!(yaml.args().schema_json(), toml.args().schema_json(), "{name}");
+            assert_eq!(yaml.args().help(), toml.args().help(), "{name}");
+            assert_eq!(settings(yaml.args()), settings(toml.args()), "{name}");
+            from_toml.args_mut().reset();
+            from_yaml.args_mut().reset();
+        }
+        from_toml.parse_from(&tokens("fetch --remote origin")).unwrap();
+        let fetch = from_toml.selected().unwrap().1.args();
+        assert_eq!(settings(fetch)[1], ("--mode", &["fast".to_string(), "full".to_string()][..], None));
+    }
+
+    #[test]
+    fn reports_offending_path() {
+        let doc = "[commands.fetch]\nargs = [{ key = \"--a\", type = \"int\" }, { key = \"--b\", type = \"bool\" }, { key = \"--c\", type = \"float\" }]\n";
+        assert_eq!(
+            CliSubcommands::from_definition_toml(doc).unwrap_err(),
+            error("commands.fetch.args[2].type", "unknown type `float`, expected `bool`, `int` or `string`"),
+        );
+        assert_eq!(
+            CliSubcommands::from_definition_yaml("args:\n  - key: --port\n    type: int\n    default: http\n").unwrap_err(),
+            error("args[0].default", "`http` is not a valid int"),
+        );
+        assert_eq!(
+            CliArgs::from_definition_toml("[[args]]\nkey = \"--level\"\ntype = \"int\"\nchoices = [\"1\"]\n").unwrap_err(),
+            error("args[0].choices", "only string args have choices"),
+        );
+        assert_eq!(
+            CliArgs::from_definition_toml("[[args]]\nkey = \"--n\"\ntype = \"int\"\nrequierd = true\n").unwrap_err(),
+            error("args[0].requierd", "unknown field"),
+        );
+        assert_eq!(
+            CliArgs::from_definition_toml("[[args]]\nkey = \"--n\"\ntype = \"int\"\nenv = \"A=B\"\n").unwrap_err(),
+            error("args[0].env", "expected an environment variable name"),
+        );
+        assert_eq!(
+            CliArgs::from_definition_toml("version = \"1.0.0\"\n").unwrap_err().path,
+            "version",
+        );
+        let syntax = CliArgs::from_definition_toml("args = [").unwrap_err();
+        assert_eq!(syntax.path, "");
+        assert!(!syntax.to_string().is_empty());
+    }
+}
@@ -0,0 +1,531 @@
+//! Tokenization of a command line into [`ParseEvent`]s, shared by
+//! [`CliArgs::parse_events`] and [`CliArgs::parse_from`], which takes them
+//! one at a time while assigning values.
+
+use std::collections::VecDeque;
+use std::slice;
+
+use super::{Arg, CliArgs, ParseError};
+
+/// One step of a command line as seen by the parser, before any value is
+/// converted to its arg's type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseEvent<'a> {
+    /// A bool key, or a key with a default missing value, given without a
+    /// value. Each flag of a combined token like `-vq` is its own event,
+    /// with its own key like `-v`.
+    Flag { key: &'a str },
+    /// A value for `key`, either attached (`--key=value`) or a following token.
+    /// `--no-key` for a bool `--key` is the value `false`.
+    Value { key: &'a str, value: &'a str },
+    /// A bare token that no key is waiting for.
+    Positional { value: &'a str },
+    /// A token after `--`.
+    Trailing { value: &'a str },
+    /// `token` could not be tokenized: an unknown key, or a key that is
+    /// missing values when `token` ends it.
+    Error { token: &'a str, error: ParseError },
+}
+
+/// A [`ParseEvent`] with the index of the arg a key belongs to, which spares
+/// [`CliArgs::parse_from`] a second lookup. A `key` of `None` is a flag of a
+/// combined token like `-vq`, which goes by the short key of its arg as no
+/// token spells it out.
+#[derive(Debug)]
+pub(super) enum Indexed<'a> {
+    Flag { key: Option<&'a str>, ind: usize },
+    Value { key: Option<&'a str>, value: &'a str, ind: usize },
+    Positional { value: &'a str },
+    Trailing { value: &'a str },
+    Error { token: &'a str, error: ParseError },
+}
+
+impl<'a> Indexed<'a> {
+    fn into_event(self, cli: &'a CliArgs) -> ParseEvent<'a> {
+        match self {
+            Indexed::Flag { key, ind } => ParseEvent::Flag { key: cli.event_key(key, ind) },
+            Indexed::Value { key, value, ind } => ParseEvent::Value { key: cli.event_key(key, ind), value },
+            Indexed::Positional { value } => ParseEvent::Positional { value },
+            Indexed::Trailing { value } => ParseEvent::Trailing { value },
+            Indexed::Error { token, error } => ParseEvent::Error { token, error },
+        }
+    }
+}
+
+/// A key seen during parsing that is still waiting for its values.
+#[derive(Clone, Copy)]
+struct Pending<'a> {
+    key: &'a str,
+    ind: usize,
+    taken: usize, // bare tokens taken as values so far
+}
+
+/// Iterator returned by [`CliArgs::parse_events`].
+pub struct ParseEvents<'c, 'a> {
+    cli: &'c CliArgs,
+    tokenizer: Tokenizer<'a>,
+}
+
+/// The state of tokenizing a command line, handed the `CliArgs` at each
+/// step so [`CliArgs::parse_from`] can assign values in between.
+pub(super) struct Tokenizer<'a> {
+    tokens: slice::Iter<'a, String>,
+    pending: Option<Pending<'a>>, // key awaiting a value
+    trailing: bool, // after `--`
+    raw: Option<(Option<&'a str>, usize)>, // key and arg taking every token left, see `CliArgs::set_raw` and `CliArgs::set_join_rest`
+    queued: VecDeque<Indexed<'a>>, // events of a token that also ended a pending key, or of combined flags
+    last: &'a str, // last token, to attribute an error at the end
+}
+
+impl CliArgs {
+    /// Tokenizes `args` the same way [`CliArgs::parse_from`] does, without
+    /// assigning or checking any value, so that callers can implement their
+    /// own assignment:
+    ///
+    /// ```
+    /// # use clitrs::args::{CliArgs, ParseEvent};
+    /// let mut args = CliArgs::new();
+    /// args.with("--name/-n=s").with("-v=b?");
+    /// let tokens: Vec<String> = ["-v", "--name=alp", "x"].map(String::from).into();
+    /// let events: Vec<ParseEvent> = args.parse_events(&tokens).collect();
+    /// assert_eq!(events, [
+    ///     ParseEvent::Flag { key: "-v" },
+    ///     ParseEvent::Value { key: "--name", value: "alp" },
+    ///     ParseEvent::Positional { value: "x" },
+    /// ]);
+    /// ```
+    ///
+    /// Tokenizing continues after an [`ParseEvent::Error`]. Schema errors
+    /// are not reported, see [`CliArgs::schema_error`].
+    pub fn parse_events<'a>(&self, args: &'a [String]) -> ParseEvents<'_, 'a> {
+        ParseEvents { cli: self, tokenizer: Tokenizer::new(args) }
+    }
+}
+
+impl<'a> ParseEvents<'_, 'a> {
+    /// The tokens not yet tokenized.
+    pub(super) fn remaining(&self) -> &'a [String] {
+        self.tokenizer.tokens.as_slice()
+    }
+}
+
+impl<'a> Tokenizer<'a> {
+    pub(super) fn new(args: &'a [String]) -> Self {
+        Tokenizer {
+            tokens: args.iter(),
+            pending: None,
+            trailing: false,
+            raw: None,
+            queued: VecDeque::new(),
+            last: "",
+        }
+    }
+
+    /// The error for a key ending while still waiting for values, if it is
+    /// not greedy or has too few of its fixed count.
+    fn pending_error(&mut self, cli: &CliArgs, token: &'a str) -> Option<Indexed<'a>> {
+        let Pending { key, ind, taken } = self.pending.take()?;
+        let arg = &cli.args[ind];
+        let error = match arg.nargs() {
+            Some(expected) if taken < expected => ParseError::TooFewValues { key: key.to_string(), expected, found: taken },
+            None if !arg.is_greedy() && taken == 0 => ParseError::MissingValue(key.to_string()),
+            _ => return None,
+        };
+        Some(Indexed::Error { token, error })
+    }
+
+    fn lookup(cli: &CliArgs, token: &'a str, key: &str) -> Result<usize, Indexed<'a>> {
+        cli.lookup(key).map_err(|error| Indexed::Error { token, error })
+    }
+
+    /// The next event of the command line.
+    pub(super) fn next(&mut self, cli: &CliArgs) -> Option<Indexed<'a>> {
+        if let Some(event) = self.queued.pop_front() {
+            return Some(event);
+        }
+        while let Some(token) = self.tokens.next() {
+            self.last = token;
+            let dash = token.starts_with('-');
+            let dash_value = dash && self.takes_dash_value(cli, token);
+            let ended = if dash && !self.trailing && self.raw.is_none() && !dash_value { // a key or `--` ends the pending value
+                self.pending_error(cli, token)
+            }
+            else {
+                None
+            };
+            let event = self.token_event(cli, token, dash_value);
+            match (ended, event) {
+                (Some(ended), event) => {
+                    if let Some(event) = event {
+                        self.queued.push_front(event);
+                    }
+                    return Some(ended);
+                },
+                (None, Some(event)) => return Some(event),
+                (None, None) => {},
+            }
+        }
+        self.pending_error(cli, self.last)
+    }
+
+    /// The event of a single token, if it produces one. `dash_value` is
+    /// whether a pending key takes the token although it starts with `-`.
+    fn token_event(&mut self, cli: &CliArgs, token: &'a str, dash_value: bool) -> Option<Indexed<'a>> {
+        if let Some((key, ind)) = self.raw {
+            if token == "--" && cli.args[ind].joins_rest() {
+                (self.raw, self.trailing) = (None, true);
+                return None;
+            }
+            return Some(Indexed::Value { key, value: token, ind });
+        }
+        if self.trailing {
+            return Some(Indexed::Trailing { value: token });
+        }
+        if token == "--" {
+            self.trailing = true;
+            return None;
+        }
+
+        if CliArgs::is_long_key(token) && !dash_value {
+            let (key, val) = match token.split_once('=') {
+                Some((key, val)) => (key, Some(val)),
+                None => (token, None),
+            };
+            let ind = match Self::lookup(cli, token, key) {
+                Ok(ind) => ind,
+                Err(e) => return Some(Self::negated_flag(cli, token).unwrap_or(e)),
+            };
+            let arg = &cli.args[ind];
+            if arg.takes_rest() {
+                self.raw = Some((Some(key), ind));
+                return val.map(|value| Indexed::Value { key: Some(key), value, ind });
+            }
+            match val {
+                Some(_) if arg.nargs().is_some_and(|n| n > 1) => {
+                    let error = ParseError::AttachedValue { key: key.to_string(), expected: arg.nargs().unwrap_or_default() };
+                    Some(Indexed::Error { token, error })
+                },
+                Some(value) => Some(Indexed::Value { key: Some(key), value, ind }),
+                None if arg.is_bool() || arg.default_missing_value().is_some() => Some(Indexed::Flag { key: Some(key), ind }),
+                None => {
+                    self.pending = Some(Pending { key, ind, taken: 0 });
+                    None
+                },
+            }
+        }
+        else if CliArgs::is_short_key(token) && !dash_value {
+            let ind = match Self::lookup(cli, token, token) {
+                Ok(ind) => ind,
+                Err(e) => {
+                    let event = self.attached_short(cli, token)
+                        .or_else(|| self.combined_with_value(cli, token))
+                        .or_else(|| self.combined_flags(cli, token));
+                    return Some(event.unwrap_or(e));
+                },
+            };
+            let arg = &cli.args[ind];
+            if arg.takes_rest() {
+                self.raw = Some((Some(token), ind));
+                return None;
+            }
+            if arg.is_bool() || arg.default_missing_value().is_some() {
+                return Some(Indexed::Flag { key: Some(token), ind });
+            }
+            self.pending = Some(Pending { key: token, ind, taken: 0 });
+            None
+        }
+        else { // is val
+            let Some(pending) = self.pending.as_mut() else {
+                return Some(Indexed::Positional { value: token });
+            };
+            pending.taken += 1;
+            let Pending { key, ind, taken } = *pending;
+            if !cli.args[ind].wants_more(taken) {
+                self.pending = None;
+            }
+            Some(Indexed::Value { key: Some(key), value: token, ind })
+        }
+    }
+}
+
+impl<'a> Tokenizer<'a> {
+    /// `--no-key` for a registered bool `--key`, as the value `false`.
+    fn negated_flag(cli: &CliArgs, token: &'a str) -> Option<Indexed<'a>> {
+        let key = format!("--{}", token.strip_prefix("--no-")?);
+        let ind = *cli.keys.get(key.as_str())?;
+        cli.args[ind].is_bool().then_some(Indexed::Value { key: Some(token), value: "false", ind })
+    }
+
+    /// `-n5` or `-n=5` for a registered short `-n` taking values, as the
+    /// value `5`. Takes precedence over [`Self::combined_flags`], which only
+    /// applies if the first character is a bool.
+    fn attached_short(&mut self, cli: &CliArgs, token: &'a str) -> Option<Indexed<'a>> {
+        let (ind, key, value) = Self::short_with_value(cli, token)?;
+        let value = value.strip_prefix('=').unwrap_or(value);
+        if cli.args[ind].takes_rest() {
+            self.raw = Some((Some(key), ind));
+        }
+        match cli.args[ind].nargs() {
+            Some(expected) if expected > 1 => {
+                let error = ParseError::AttachedValue { key: key.to_string(), expected };
+                Some(Indexed::Error { token, error })
+            },
+            _ => Some(Indexed::Value { key: Some(key), value, ind }),
+        }
+    }
+
+    /// The first flag of a token like `-vn=5`, queueing the others: every
+    /// character before `=` is a flag, and the last one takes the value if
+    /// it is a short key taking values. A bool last is [`ParseError::FlagValue`].
+    fn combined_with_value(&mut self, cli: &CliArgs, token: &'a str) -> Option<Indexed<'a>> {
+        let (flags, value, inds) = Self::combined_value_inds(cli, token)?;
+        let (&last, bools) = inds.split_last()?;
+        let arg = &cli.args[last];
+        let error = match arg.nargs() {
+            _ if arg.is_bool() => Some(ParseError::FlagValue { key: format!("-{}", flags.chars().last()?), token: token.to_string() }),
+            Some(expected) if expected > 1 => Some(ParseError::AttachedValue { key: format!("-{}", flags.chars().last()?), expected }),
+            _ => None,
+        };
+        if let Some(error) = error {
+            return Some(Indexed::Error { token, error });
+        }
+        if arg.takes_rest() {
+            self.raw = Some((None, last));
+        }
+        let mut events = bools.iter().map(|ind| Indexed::Flag { key: None, ind: *ind });
+        let first = events.next();
+        self.queued.extend(events);
+        self.queued.push_back(Indexed::Value { key: None, value, ind: last });
+        first
+    }
+
+    /// The first flag of a token like `-vq` that is no registered key but
+    /// made of registered single character flags, queueing the others.
+    fn combined_flags(&mut self, cli: &CliArgs, token: &'a str) -> Option<Indexed<'a>> {
+        let inds = Self::combined_inds(cli, token)?;
+        let mut events = inds.into_iter().map(|ind| Indexed::Flag { key: None, ind });
+        let first = events.next();
+        self.queued.extend(events);
+        first
+    }
+}
+
+impl<'a> Tokenizer<'a> {
+    /// The arg, key and attached value of a token like `-n5`, if `-n` is a
+    /// registered short key taking values.
+    fn short_with_value(cli: &CliArgs, token: &'a str) -> Option<(usize, &'a str, &'a str)> {
+        let first = token.strip_prefix('-')?.chars().next().filter(|c| *c != '-')?;
+        let (key, value) = token.split_at(1 + first.len_utf8());
+        let ind = *cli.keys.get(key)?;
+        (!value.is_empty() && !cli.args[ind].is_bool()).then_some((ind, key, value))
+    }
+
+    /// The flags and value of a token like `-vn=5` and the args of its
+    /// flags, if all are registered and all but the last are bools.
+    fn combined_value_inds(cli: &CliArgs, token: &'a str) -> Option<(&'a str, &'a str, Vec<usize>)> {
+        let (flags, value) = token.split_once('=')?;
+        let inds = Self::combined_inds_with(cli, flags, |_| true)?;
+        let bools = &inds[..inds.len() - 1];
+        bools.iter().all(|ind| cli.args[*ind].is_bool()).then_some((flags, value, inds))
+    }
+
+    /// The flags making up a token like `-vq`, if all are registered bools.
+    fn combined_inds(cli: &CliArgs, token: &str) -> Option<Vec<usize>> {
+        Self::combined_inds_with(cli, token, Arg::is_bool)
+    }
+
+    /// The args of the single character keys making up a token like `-vq`,
+    /// if there are at least 2 and every one is registered and accepted.
+    fn combined_inds_with(cli: &CliArgs, token: &str, accept: impl Fn(&Arg) -> bool) -> Option<Vec<usize>> {
+        let flags = token.strip_prefix('-').filter(|flags| !flags.starts_with('-'))?;
+        if flags.chars().count() < 2 {
+            return None;
+        }
+        flags.chars()
+            .map(|c| {
+                let ind = *cli.keys.get(format!("-{c}").as_str())?;
+                accept(&cli.args[ind]).then_some(ind)
+            })
+            .collect()
+    }
+
+    /// Whether a token starting with `-` is the next value of a pending arg:
+    /// any but `--` for an arg allowing hyphen values, and one like `-10`
+    /// that does not look like a registered key for an arg with a fixed count.
+    fn takes_dash_value(&self, cli: &CliArgs, token: &'a str) -> bool {
+        let Some(Pending { ind, .. }) = self.pending else {
+            return false;
+        };
+        if cli.args[ind].allows_hyphen_values() {
+            return token != "--";
+        }
+        if cli.args[ind].nargs().is_none() || token == "--" {
+            return false;
+        }
+        let key = token.split_once('=').map_or(token, |(key, _)| key);
+        let is_key = cli.keys.contains_key(key)
+            || Self::negated_flag(cli, token).is_some()
+            || Self::short_with_value(cli, token).is_some()
+            || Self::combined_inds(cli, token).is_some()
+            || Self::combined_value_inds(cli, token).is_some();
+        !is_key
+    }
+}
+
+impl<'c: 'a, 'a> Iterator for ParseEvents<'c, 'a> {
+    type Item = ParseEvent<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.tokenizer.next(self.cli).map(|event| event.into_event(self.cli))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParseEvent;
+    use crate::args::{CliArgs, ParseError};
+
+    fn tokens(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn event_sequence() {
+        let mut args = CliArgs::new();
+        args
+            .with("--name/-n=s")
+            .with("--files=s*?")
+            .with("--point=i{2}")
+            .with("--verbose/-v=b?");
+        let line = tokens("stray -v --files a b --point 1 2 --nope -n --name x -- -v y");
+        let events: Vec<ParseEvent> = args.parse_events(&line).collect();
+        assert_eq!(events, [
+            ParseEvent::Positional { value: "stray" },
+            ParseEvent::Flag { key: "-v" },
+            ParseEvent::Value { key: "--files", value: "a" },
+            ParseEvent::Value { key: "--files", value: "b" },
+            ParseEvent::Value { key: "--point", value: "1" },
+            ParseEvent::Value { key: "--point", value: "2" },
+            ParseEvent::Error { token: "--nope", error: ParseError::UnknownKey("--nope".to_string()) },
+            ParseEvent::Error { token: "--name", error: ParseError::MissingValue("-n".to_string()) },
+            ParseEvent::Value { key: "--name", value: "x" },
+            ParseEvent::Trailing { value: "-v" },
+            ParseEvent::Trailing { value: "y" },
+        ]);
+    }
+
+    #[test]
+    fn combined_short_flags() {
+        let mut args = CliArgs::new();
+        args
+            .with("--name/-n=s?")
+            .with("--verbose/-v=b?")
+            .with("--quiet/-q=b?");
+        let line = tokens("-n -vqv -vn");
+        let events: Vec<ParseEvent> = args.parse_events(&line).collect();
+        assert_eq!(events, [
+            ParseEvent::Error { token: "-vqv", error: ParseError::MissingValue("-n".to_string()) },
+            ParseEvent::Flag { key: "-v" },
+            ParseEvent::Flag { key: "-q" },
+            ParseEvent::Flag { key: "-v" },
+            ParseEvent::Error { token: "-vn", error: ParseError::UnknownKey("-vn".to_string()) },
+        ]);
+
+        args.parse("-vv -q -vq").unwrap();
+        assert_eq!(args.get_bool_multi("--verbose").unwrap(), [true, true, true]);
+        assert_eq!(args.get_bool_multi("--quiet").unwrap(), [true, true]);
+    }
+
+    #[test]
+    fn fixed_count_values() {
+        let mut args = CliArgs::new();
+        args
+            .with("--range=i{2}")
+            .with("--name/-n=s{1}")
+            .with("--verbose/-v=b?");
+        let line = tokens("--range -10 -2 --range=1 2 --range 3 -v");
+        let events: Vec<ParseEvent> = args.parse_events(&line).collect();
+        assert_eq!(events, [
+            ParseEvent::Value { key: "--range", value: "-10" },
+            ParseEvent::Value { key: "--range", value: "-2" },
+            ParseEvent::Error { token: "--range=1", error: ParseError::AttachedValue { key: "--range".to_string(), expected: 2 } },
+            ParseEvent::Positional { value: "2" },
+            ParseEvent::Value { key: "--range", value: "3" },
+            ParseEvent::Error { token: "-v", error: ParseError::TooFewValues { key: "--range".to_string(), expected: 2, found: 1 } },
+            ParseEvent::Flag { key: "-v" },
+        ]);
+        assert_eq!(
+            args.parse("--name=alp --range=1 2").unwrap_err().to_string(),
+            "`--range` takes 2 values as separate tokens, not `--range=..`",
+        );
+    }
+
+    #[test]
+    fn attached_short_values() {
+        let mut args = CliArgs::new();
+        args
+            .with("--num/-n=i?")
+            .with("--point/-p=i{2}?")
+            .with("--all/-a=b?")
+            .with("--brief/-b=b?");
+        let line = tokens("-n5 -n=-7 -ab -a5 -an -p1 -bn");
+        let events: Vec<ParseEvent> = args.parse_events(&line).collect();
+        assert_eq!(events, [
+            ParseEvent::Value { key: "-n", value: "5" },
+            ParseEvent::Value { key: "-n", value: "-7" },
+            ParseEvent::Flag { key: "-a" },
+            ParseEvent::Flag { key: "-b" },
+            // `-a` is a bool, so its token is read as combined flags and `5` is no flag
+            ParseEvent::Error { token: "-a5", error: ParseError::UnknownKey("-a5".to_string()) },
+            ParseEvent::Error { token: "-an", error: ParseError::UnknownKey("-an".to_string()) },
+            ParseEvent::Error { token: "-p1", error: ParseError::AttachedValue { key: "-p".to_string(), expected: 2 } },
+            ParseEvent::Error { token: "-bn", error: ParseError::UnknownKey("-bn".to_string()) },
+        ]);
+
+        args.parse("-n0x10 -ba").unwrap();
+        assert_eq!(args.get_int("-n").unwrap(), Some(16));
+        assert_eq!(args.get_bool("--all").unwrap(), Some(true));
+    }
+
+    #[test]
+    fn combined_flags_with_value() {
+        let mut args = CliArgs::new();
+        args
+            .with("--num/-n=i?")
+            .with("--point/-p=i{2}?")
+            .with("--all/-a=b?")
+            .with("--brief/-b=b?");
+        let line = tokens("-abn=5 -ab=c -an=x=y -ap=1 -ax=1 -na=3");
+        let events: Vec<ParseEvent> = args.parse_events(&line).collect();
+        assert_eq!(events, [
+            ParseEvent::Flag { key: "-a" },
+            ParseEvent::Flag { key: "-b" },
+            ParseEvent::Value { key: "-n", value: "5" },
+            ParseEvent::Error { token: "-ab=c", error: ParseError::FlagValue { key: "-b".to_string(), token: "-ab=c".to_string() } },
+            ParseEvent::Flag { key: "-a" },
+            ParseEvent::Value { key: "-n", value: "x=y" },
+            ParseEvent::Error { token: "-ap=1", error: ParseError::AttachedValue { key: "-p".to_string(), expected: 2 } },
+            ParseEvent::Error { token: "-ax=1", error: ParseError::UnknownKey("-ax=1".to_string()) },
+            // `-n` takes values, so everything after it is its value
+            ParseEvent::Value { key: "-n", value: "a=3" },
+        ]);
+
+        args.parse("-bn=7").unwrap();
+        assert_eq!((args.get_bool("-b").unwrap(), args.get_int("-n").unwrap()), (Some(true), Some(7)));
+        args.reset();
+        assert_eq!(args.parse("-ba=true").unwrap_err().to_string(), "`-a` in `-ba=true` takes no value");
+    }
+
+    #[test]
+    fn pending_at_end() {
+        let mut args = CliArgs::new();
+        args.with("--point=i{2}");
+        let line = tokens("--point 1");
+        let events: Vec<ParseEvent> = args.parse_events(&line).collect();
+        assert_eq!(events, [
+            ParseEvent::Value { key: "--point", value: "1" },
+            ParseEvent::Error { token: "1", error: ParseError::TooFewValues { key: "--point".to_string(), expected: 2, found: 1 } },
+        ]);
+        assert_eq!(args.parse_events(&[]).count(), 0);
+    }
+}
@@ -0,0 +1,92 @@
+//! Exit codes for parse errors, following the `sysexits.h` conventions, and
+//! exiting with them, see [`CliArgs::exit_with`].
+
+use std::fmt::{self, Debug, Formatter};
+use std::process;
+use std::sync::Arc;
+
+use super::{color, CliArgs, ParseError};
+use crate::term::Stream;
+
+type ExitCodeFn = dyn Fn(&ParseError) -> Option<i32> + Send + Sync;
+
+/// Overrides of [`ParseError::exit_code`], see [`CliArgs::with_exit_codes`].
+#[derive(Clone)]
+pub(super) struct ExitCodes(Arc<ExitCodeFn>);
+
+impl Debug for ExitCodes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("ExitCodes")
+    }
+}
+
+impl ParseError {
+    /// The code to exit with: 0 for the outcomes that are no failure, like
+    /// [`ParseError::Help`], 2 for a wrong command line and a `sysexits.h`
+    /// code for the others: 65 (`EX_DATAERR`) for [`ParseError::NonUtf8`],
+    /// 66 (`EX_NOINPUT`) for [`ParseError::ValueFile`], 70 (`EX_SOFTWARE`)
+    /// for [`ParseError::Schema`], a bug of the program, and 78
+    /// (`EX_CONFIG`) for an environment it cannot work with, like
+    /// [`ParseError::UnknownVar`]. Several errors exit with the code of the first.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ParseError::Version(_) | ParseError::Help(_) | ParseError::Completions(_) | ParseError::CompletionsRequested(_) => 0,
+            ParseError::NonUtf8(_) | ParseError::NonUtf8Var { .. } => 65,
+            ParseError::ValueFile { .. } => 66,
+            ParseError::Schema(_) => 70,
+            ParseError::UnknownVar { .. } | ParseError::NoHome(_) | ParseError::UnknownCommand { .. } => 78,
+            ParseError::Multiple(errors) => errors.first().map_or(2, ParseError::exit_code),
+            _ => 2,
+        }
+    }
+}
+
+impl CliArgs {
+    /// Overrides [`ParseError::exit_code`] for the errors `codes` returns a
+    /// code for, e.g. `|e| matches!(e, ParseError::MissingRequired(_)).then_some(64)`.
+    pub fn with_exit_codes(&mut self, codes: impl Fn(&ParseError) -> Option<i32> + Send + Sync + 'static) -> &mut Self {
+        self.exit_codes = Some(ExitCodes(Arc::new(codes)));
+        self
+    }
+
+    /// The code [`CliArgs::exit_with`] exits with for `e`.
+    pub fn exit_code(&self, e: &ParseError) -> i32 {
+        self.exit_codes.as_ref().and_then(|codes| (codes.0)(e)).unwrap_or_else(|| e.exit_code())
+    }
+
+    /// Prints `e` and exits with [`CliArgs::exit_code`]: help, a version,
+    /// completions and a completion script to stdout, an error to stderr
+    /// after `error:`, in color if [`CliArgs::with_color_option`] allows.
+    pub fn exit_with(&self, e: ParseError) -> ! {
+        let code = self.exit_code(&e);
+        match e {
+            ParseError::Completions(candidates) => candidates.iter().for_each(|candidate| println!("{}", candidate)),
+            ParseError::Version(version) => println!("{}", version),
+            ParseError::Help(text) | ParseError::CompletionsRequested(text) => print!("{}", text),
+            e if self.colors(Stream::Stderr) => eprintln!("{} {}", color::bold_red("error:"), e),
+            e => eprintln!("error: {}", e),
+        }
+        process::exit(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::{CliArgs, ParseError, SchemaError};
+
+    #[test]
+    fn exit_codes() {
+        let mut args = CliArgs::new();
+        args.with("--port=i").with("--name=s?");
+        let missing = args.parse("--name x").unwrap_err();
+        assert_eq!(missing.exit_code(), 2);
+        assert_eq!(ParseError::Help(String::new()).exit_code(), 0);
+        assert_eq!(ParseError::Schema(SchemaError::Malformed("-x".to_string())).exit_code(), 70);
+        let several = ParseError::Multiple(vec![ParseError::NoHome("--out".to_string()), ParseError::MissingRequired("--port".to_string())]);
+        assert_eq!(several.exit_code(), 78);
+
+        args.with_exit_codes(|e| matches!(e, ParseError::MissingRequired(_)).then_some(64));
+        assert_eq!(args.exit_code(&missing), 64);
+        assert_eq!(args.exit_code(&ParseError::UnknownKey("--nope".to_string())), 2);
+    }
+}
@@ -0,0 +1,266 @@
+//! Expansion of `~` and of `$VAR` and `${VAR}` references in string
+//! values, for values that no shell expanded: quoted, from Windows `cmd`,
+//! config files or defaults.
+
+use std::env;
+
+use super::{Arg, ArgError, CliArgs, ParseError, ValueHint};
+
+/// What an expanded value does with a variable that is not set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownVar {
+    /// Keep the reference as written, `$NOPE` stays `$NOPE`.
+    Keep,
+    /// Fail the parse with [`ParseError::UnknownVar`].
+    Error,
+}
+
+impl CliArgs {
+    /// Expands environment variable references in the values of every
+    /// string arg, see [`CliArgs::set_expand_env`].
+    pub fn expand_env(&mut self, unknown: UnknownVar) -> &mut Self {
+        self.expand_env = Some(unknown);
+        self
+    }
+
+    /// Expands `$VAR` and `${VAR}` in the values of a string arg after each
+    /// parse, `$$` being a literal `$`. It applies to every value the arg
+    /// ends up with, whether given, read from its environment variable or
+    /// a default, but after choices and validators have seen the value as
+    /// written. Takes precedence over [`CliArgs::expand_env`]. The values of
+    /// a [`CliArgs::set_secret`] arg are never expanded this way, so that a
+    /// `$` in a password is kept.
+    pub fn set_expand_env(&mut self, key: &str, unknown: UnknownVar) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        match &mut self.args[ind] {
+            Arg::String { settings, .. } => settings.expand_env = Some(unknown),
+            _ => return Err(ArgError::WrongType),
+        }
+        Ok(())
+    }
+
+    /// Expands a leading `~/` in the values of a string arg after each parse
+    /// to the home directory from `$HOME` (`%USERPROFILE%` on Windows), and
+    /// on unix `~user/` to that user's. A `~` further into the value is kept.
+    /// On by default for args with a path [`ValueHint`], this turns it off
+    /// for them or on for any other string arg. Like [`CliArgs::set_expand_env`]
+    /// it applies to given, environment and default values alike, and unlike
+    /// it also to a [`CliArgs::set_secret`] arg such as a key file.
+    pub fn set_expand_tilde(&mut self, key: &str, expand: bool) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        match &mut self.args[ind] {
+            Arg::String { settings, .. } => settings.expand_tilde = Some(expand),
+            _ => return Err(ArgError::WrongType),
+        }
+        Ok(())
+    }
+
+    /// Expands the values of the arg at `ind`, if it expands any.
+    pub(super) fn expand_vals(&mut self, ind: usize) -> Result<(), ParseError> {
+        let global = self.expand_env;
+        let key = &self.display_keys[ind];
+        let Arg::String { vals, settings } = &mut self.args[ind] else {
+            return Ok(());
+        };
+        let is_path = matches!(
+            settings.value_hint,
+            Some(ValueHint::AnyPath | ValueHint::File | ValueHint::Dir | ValueHint::ExecutablePath),
+        );
+        if settings.expand_tilde.unwrap_or(is_path) {
+            for val in vals.iter_mut() {
+                *val = expand_home(val).ok_or_else(|| ParseError::NoHome(key.to_string()))?;
+            }
+        }
+        let Some(unknown) = settings.expand_env.or(global).filter(|_| !settings.secret) else {
+            return Ok(());
+        };
+        for val in vals.iter_mut() {
+            *val = expand(val, |var| env::var(var).ok(), unknown)
+                .map_err(|var| ParseError::UnknownVar { key: key.to_string(), var })?;
+        }
+        Ok(())
+    }
+}
+
+/// [`expand_tilde`] with the home directories of this process' environment.
+pub(crate) fn expand_home(val: &str) -> Option<String> {
+    expand_tilde(val, |var| env::var(var).ok(), user_home)
+}
+
+/// `val` with a leading `~` or `~user` replaced by the home directory, or
+/// `None` if it is the own home and that is unknown. An unknown user is kept.
+fn expand_tilde(val: &str, var: impl Fn(&str) -> Option<String>, user_home: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let Some(after) = val.strip_prefix('~') else {
+        return Some(val.to_string());
+    };
+    let end = after.find(|c| c == '/' || (cfg!(windows) && c == '\\')).unwrap_or(after.len());
+    let (user, path) = after.split_at(end);
+    let home = match user {
+        "" => {
+            let home = if cfg!(windows) { var("USERPROFILE").or_else(|| var("HOME")) } else { var("HOME") };
+            home.filter(|home| !home.is_empty())?
+        },
+        user => match user_home(user) {
+            Some(home) => home,
+            None => return Some(val.to_string()),
+        },
+    };
+    Some(format!("{home}{path}"))
+}
+
+/// The home directory of `user` from the password database.
+#[cfg(unix)]
+fn user_home(user: &str) -> Option<String> {
+    use std::ffi::{CStr, CString};
+
+    let name = CString::new(user).ok()?;
+    let mut buf = vec![0 as libc::c_char; 4096];
+    // SAFETY: getpwnam_r only writes to the passed buffers, and pw_dir
+    // points into `buf`, which outlives the borrow.
+    unsafe {
+        let mut pwd: libc::passwd = std::mem::zeroed();
+        let mut found = std::ptr::null_mut();
+        let res = libc::getpwnam_r(name.as_ptr(), &mut pwd, buf.as_mut_ptr(), buf.len(), &mut found);
+        if res != 0 || found.is_null() || pwd.pw_dir.is_null() {
+            return None;
+        }
+        Some(CStr::from_ptr(pwd.pw_dir).to_string_lossy().into_owned())
+    }
+}
+
+/// `~user` is not expanded on this platform.
+#[cfg(not(unix))]
+fn user_home(_user: &str) -> Option<String> {
+    None
+}
+
+/// `val` with its variable references replaced by `lookup`, or the name of
+/// the first variable `lookup` does not know when `unknown` is an error.
+fn expand(val: &str, lookup: impl Fn(&str) -> Option<String>, unknown: UnknownVar) -> Result<String, String> {
+    let mut out = String::with_capacity(val.len());
+    let mut rest = val;
+    while let Some(dollar) = rest.find('$') {
+        out.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        if let Some(escaped) = after.strip_prefix('$') {
+            out.push('$');
+            rest = escaped;
+            continue;
+        }
+        let (name, reference) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], &rest[dollar..dollar + end + 3]),
+                None => ("", "$"), // unclosed, kept as written
+            }
+        }
+        else {
+            let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+            (&after[..end], &rest[dollar..dollar + end + 1])
+        };
+        rest = &rest[dollar + reference.len()..];
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            out.push_str(reference);
+            continue;
+        }
+        match (lookup(name), unknown) {
+            (Some(value), _) => out.push_str(&value),
+            (None, UnknownVar::Keep) => out.push_str(reference),
+            (None, UnknownVar::Error) => return Err(name.to_string()),
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand, expand_tilde, UnknownVar};
+    use crate::args::{ArgError, CliArgs, ParseError, ValueHint};
+
+    fn lookup(var: &str) -> Option<String> {
+        match var {
+            "HOME" => Some("/home/alp".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expands_references() {
+        let expanded = |val| expand(val, lookup, UnknownVar::Keep).unwrap();
+        assert_eq!(expanded("$HOME/reports"), "/home/alp/reports");
+        assert_eq!(expanded("${HOME}s and $EMPTY."), "/home/alps and .");
+        assert_eq!(expanded("$$HOME costs $$5"), "$HOME costs $5");
+        assert_eq!(expanded("$NOPE ${NOPE} $ $5 ${HOME"), "$NOPE ${NOPE} $ $5 ${HOME");
+        assert_eq!(expand("a/${NOPE}/b", lookup, UnknownVar::Error), Err("NOPE".to_string()));
+    }
+
+    #[test]
+    fn expands_leading_tilde() {
+        let home = |var: &str| match var {
+            "HOME" | "USERPROFILE" => Some("/home/alp".to_string()),
+            _ => None,
+        };
+        let users = |user: &str| (user == "root").then(|| "/root".to_string());
+        let expanded = |val| expand_tilde(val, home, users);
+        assert_eq!(expanded("~/my.toml").as_deref(), Some("/home/alp/my.toml"));
+        assert_eq!(expanded("~").as_deref(), Some("/home/alp"));
+        assert_eq!(expanded("~root/.profile").as_deref(), Some("/root/.profile"));
+        assert_eq!(expanded("~nobody-here/x").as_deref(), Some("~nobody-here/x"));
+        assert_eq!(expanded("a/~/b").as_deref(), Some("a/~/b"));
+        assert_eq!(expanded("").as_deref(), Some(""));
+        assert_eq!(expand_tilde("~/x", |_| None, users), None);
+        assert_eq!(expand_tilde("~/x", |_| Some(String::new()), users), None);
+        assert_eq!(expand_tilde("x~", |_| None, users).as_deref(), Some("x~"));
+    }
+
+    #[test]
+    fn tilde_follows_path_hints() {
+        let Ok(home) = std::env::var(if cfg!(windows) { "USERPROFILE" } else { "HOME" }) else {
+            return; // nothing to expand to
+        };
+        let mut args = CliArgs::new();
+        args.with("--config=s").with("--out=s? ::>~/out").with("--name=s?").with("--raw=s?").with("--port=i?");
+        args.set_value_hint("--config", ValueHint::File).unwrap();
+        args.set_value_hint("--raw", ValueHint::File).unwrap();
+        args.set_expand_tilde("--raw", false).unwrap();
+        args.set_expand_tilde("--out", true).unwrap();
+        assert!(matches!(args.set_expand_tilde("--port", true), Err(ArgError::WrongType)));
+
+        args.parse("--config ~/my.toml --name ~/x --raw ~/y").unwrap();
+        assert_eq!(args.get_string("--config").unwrap(), Some(format!("{home}/my.toml")));
+        assert_eq!(args.get_string("--out").unwrap(), Some(format!("{home}/out")));
+        assert_eq!(args.get_str("--name").unwrap(), Some("~/x"));
+        assert_eq!(args.get_str("--raw").unwrap(), Some("~/y"));
+    }
+
+    #[test]
+    fn applies_to_every_source() {
+        let var = format!("CLITRS_TEST_EXPAND_{}", std::process::id());
+        std::env::set_var(&var, "x");
+        let mut args = CliArgs::new();
+        args
+            .with("--out=s?")
+            .with(&format!("--dir=s? ::>${var}/d"))
+            .with("--from-env=s?")
+            .with("--raw=s?")
+            .with("--count=i?")
+            .expand_env(UnknownVar::Keep);
+        args.set_env("--from-env", &format!("{var}_ENV")).unwrap();
+        std::env::set_var(format!("{var}_ENV"), format!("${{{var}}}!"));
+        args.set_expand_env("--raw", UnknownVar::Error).unwrap();
+        assert!(matches!(args.set_expand_env("--count", UnknownVar::Keep), Err(ArgError::WrongType)));
+
+        args.parse(&format!("--out ${var}/$NOPE --raw $$x")).unwrap();
+        assert_eq!(args.get_str("--out").unwrap(), Some("x/$NOPE"));
+        assert_eq!(args.get_str("--dir").unwrap(), Some("x/d"));
+        assert_eq!(args.get_str("--from-env").unwrap(), Some("x!"));
+        assert_eq!(args.get_str("--raw").unwrap(), Some("$x"));
+
+        args.reset();
+        assert_eq!(
+            args.parse("--raw $NOPE"),
+            Err(ParseError::UnknownVar { key: "--raw".to_string(), var: "NOPE".to_string() }),
+        );
+    }
+}
@@ -0,0 +1,619 @@
+//! Human readable descriptions of the registered args: help text, man page
+//! and markdown.
+
+use std::fmt::Write;
+use std::{env, mem};
+
+use super::{color, tokenize, Arg, ArgSettings, CliArgs, ParseError};
+use crate::term::Stream;
+
+/// A worked example registered with [`CliArgs::example`].
+#[derive(Debug, Clone)]
+pub(super) struct Example {
+    description: String,
+    command: String,
+}
+
+/// Application metadata shown by [`CliArgs::help`], [`CliArgs::usage`],
+/// [`CliArgs::man`] and [`CliArgs::markdown`].
+#[derive(Debug, Clone, Default)]
+pub(super) struct Meta {
+    pub(super) name: Option<String>,
+    pub(super) bin_name: Option<String>,
+    pub(super) author: Option<String>,
+    pub(super) about: Option<String>,
+    pub(super) long_about: Option<String>,
+}
+
+impl CliArgs {
+    /// The application name used as the man and markdown page title.
+    /// Defaults to the binary name.
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.meta.name = Some(name.to_string());
+        self
+    }
+
+    /// The binary name shown in usage lines. Defaults to the file name of
+    /// `argv[0]` without directories and `.exe`, override it for tools
+    /// invoked through wrappers.
+    pub fn bin_name(&mut self, bin_name: &str) -> &mut Self {
+        self.meta.bin_name = Some(bin_name.to_string());
+        self
+    }
+
+    pub fn author(&mut self, author: &str) -> &mut Self {
+        self.meta.author = Some(author.to_string());
+        self
+    }
+
+    /// A one line summary of the application, shown above the usage and
+    /// wrapped to the help width. Text of several lines is shown verbatim.
+    pub fn about(&mut self, about: &str) -> &mut Self {
+        self.meta.about = Some(about.to_string());
+        self
+    }
+
+    /// A longer description for the man and markdown pages, paragraphs
+    /// separated by blank lines.
+    pub fn long_about(&mut self, long_about: &str) -> &mut Self {
+        self.meta.long_about = Some(long_about.to_string());
+        self
+    }
+
+    /// The usage line: the binary name, the required args and `[OPTIONS]`
+    /// if any arg is optional, e.g. `mytool --port <PORT> [OPTIONS]`.
+    pub fn usage(&self) -> String {
+        let mut usage = self.resolved_bin_name();
+        let mut any_optional = false;
+        for (ind, arg) in self.args.iter().enumerate() {
+            if arg.is_optional() {
+                any_optional = true;
+            }
+            else {
+                let _ = write!(usage, " {}", self.arg_spec_short(ind));
+            }
+        }
+        if any_optional {
+            usage.push_str(" [OPTIONS]");
+        }
+        usage
+    }
+
+    /// Registers a worked example, shown under EXAMPLES in [`CliArgs::help`],
+    /// [`CliArgs::man`] and [`CliArgs::markdown`]. The command starts with
+    /// the binary name, see [`CliArgs::verify_examples`].
+    pub fn example(&mut self, description: &str, command: &str) -> &mut Self {
+        self.examples.push(Example {
+            description: description.to_string(),
+            command: command.to_string(),
+        });
+        self
+    }
+
+    /// Parses every example command, minus the binary name, against a fresh
+    /// copy of the registered args. The command is split into words like a
+    /// shell does, see [`tokenize`], so quoted words may contain spaces, and
+    /// an unclosed quote is that example's error. Returns one result per
+    /// example in registration order, meant for the application's test
+    /// suite so that examples break loudly when flags are renamed:
+    ///
+    /// ```
+    /// # use clitrs::args::CliArgs;
+    /// let mut args = CliArgs::new();
+    /// args.with("--port=i").example("listen on 8080", "mytool --port 8080");
+    /// assert!(args.verify_examples().iter().all(Result::is_ok));
+    /// ```
+    pub fn verify_examples(&self) -> Vec<Result<(), ParseError>> {
+        self.examples.iter()
+            .map(|example| {
+                let mut args = self.clone();
+                args.reset();
+                let tokens = tokenize(&example.command)?;
+                args.parse_from(tokens.get(1..).unwrap_or_default())
+            })
+            .collect()
+    }
+
+    /// The about line and usage, then the registered args with their
+    /// descriptions, followed by the examples, wrapped at [`CliArgs::help_width`].
+    /// Section headers are bold if [`CliArgs::with_color_option`] says stdout is colored.
+    pub fn help(&self) -> String {
+        let help = self.help_plain_fixed_width(Self::help_width());
+        if !self.colors(Stream::Stdout) {
+            return help;
+        }
+        help.lines()
+            .map(|line| match line {
+                "USAGE:" | "OPTIONS:" | "EXAMPLES:" => color::bold(line),
+                _ => line.to_string(),
+            })
+            .fold(String::new(), |out, line| out + &line + "\n")
+    }
+
+    /// Columns [`CliArgs::help`] wraps at when the terminal width is unknown.
+    pub const HELP_WIDTH: usize = 80;
+
+    /// Widest key column of the help layout, longer specs get a line of
+    /// their own with the description starting below.
+    pub const HELP_SPEC_MAX_WIDTH: usize = 30;
+
+    /// The terminal width as exported by the shell in `$COLUMNS`, or
+    /// [`CliArgs::HELP_WIDTH`] when it is not set to a width.
+    pub fn help_width() -> usize {
+        env::var("COLUMNS").ok()
+            .and_then(|columns| columns.trim().parse().ok())
+            .filter(|width| *width > 0)
+            .unwrap_or(Self::HELP_WIDTH)
+    }
+
+    /// [`CliArgs::help`] wrapped at `width` columns, byte for byte the same
+    /// on every platform: no terminal queries, no locale, `\n` line endings.
+    /// Set [`CliArgs::bin_name`] too, as it otherwise comes from `argv[0]`.
+    /// Meant for snapshot tests, see [`crate::testing::assert_help_snapshot`].
+    ///
+    /// Args are laid out in two columns, keys on the left and descriptions
+    /// on the right. The key column fits the longest spec that is at most
+    /// [`CliArgs::HELP_SPEC_MAX_WIDTH`] wide, and every description starts
+    /// at the same column and is wrapped into the remaining width. An `[env: ..]`
+    /// or `[default: ..]` group is never split across lines.
+    pub fn help_plain_fixed_width(&self, width: usize) -> String {
+        let specs: Vec<String> = (0..self.args.len()).map(|ind| self.arg_spec(ind)).collect();
+        let spec_width = specs.iter().map(String::len).filter(|len| *len <= Self::HELP_SPEC_MAX_WIDTH).max().unwrap_or(0);
+        let column = 4 + spec_width + 4;
+        let about_width = width.saturating_sub(column).max(MIN_WRAP_WIDTH);
+
+        let mut out = String::new();
+        match &self.meta.about {
+            Some(about) if about.contains('\n') => { let _ = writeln!(out, "{}\n", about.trim_end()); },
+            Some(about) => {
+                let words: Vec<String> = about.split_whitespace().map(String::from).collect();
+                for line in wrap(&words, width.max(MIN_WRAP_WIDTH)) {
+                    let _ = writeln!(out, "{line}");
+                }
+                out.push('\n');
+            },
+            None => {},
+        }
+        let _ = writeln!(out, "USAGE:\n    {}", self.usage());
+        if !specs.is_empty() {
+            out.push_str("\nOPTIONS:\n");
+        }
+        for (ind, spec) in specs.iter().enumerate() {
+            let lines = wrap(&self.arg_about_units(ind), about_width);
+            let mut lines = lines.iter();
+            match lines.next() {
+                Some(first) if spec.len() <= spec_width => { let _ = writeln!(out, "    {spec:spec_width$}    {first}"); },
+                Some(first) => { let _ = writeln!(out, "    {spec}\n{:column$}{first}", ""); },
+                None => { let _ = writeln!(out, "    {spec}"); },
+            }
+            for line in lines {
+                let _ = writeln!(out, "{:column$}{line}", "");
+            }
+        }
+        if !self.examples.is_empty() {
+            out.push_str("\nEXAMPLES:\n");
+        }
+        for (ind, example) in self.examples.iter().enumerate() {
+            if ind > 0 {
+                out.push('\n');
+            }
+            let _ = writeln!(out, "    {}", example.description);
+            let _ = writeln!(out, "        {}", example.command);
+        }
+        out
+    }
+
+    /// Renders a roff man page in section 1.
+    pub fn man(&self) -> String {
+        let name = self.resolved_name();
+        let mut out = String::new();
+        // quoted, as a title with spaces would otherwise be read as several arguments
+        let _ = writeln!(out, ".TH \"{}\" 1", roff_escape(&name.to_uppercase()).replace('"', "\"\""));
+        out.push_str(".SH NAME\n");
+        match &self.meta.about {
+            Some(about) => { let _ = writeln!(out, "{} \\- {}", roff_escape(&name), roff_escape(about)); },
+            None => { let _ = writeln!(out, "{}", roff_escape(&name)); },
+        }
+        out.push_str(".SH SYNOPSIS\n");
+        let _ = writeln!(out, "{}", roff_escape(&self.usage()));
+        if let Some(long_about) = &self.meta.long_about {
+            out.push_str(".SH DESCRIPTION\n");
+            for (ind, paragraph) in paragraphs(long_about).enumerate() {
+                if ind > 0 {
+                    out.push_str(".PP\n");
+                }
+                let _ = writeln!(out, "{}", roff_escape(&paragraph));
+            }
+        }
+        if !self.args.is_empty() {
+            out.push_str(".SH OPTIONS\n");
+        }
+        for ind in 0..self.args.len() {
+            out.push_str(".TP\n");
+            let _ = writeln!(out, "{}", roff_escape(&self.arg_spec(ind)));
+            if let Some(about) = self.arg_about(ind) {
+                let _ = writeln!(out, "{}", roff_escape(&about));
+            }
+        }
+        if !self.examples.is_empty() {
+            out.push_str(".SH EXAMPLES\n");
+        }
+        for example in &self.examples {
+            out.push_str(".PP\n");
+            let _ = writeln!(out, "{}", roff_escape(&example.description));
+            out.push_str(".PP\n.RS\n.nf\n");
+            let _ = writeln!(out, "{}", roff_escape(&example.command));
+            out.push_str(".fi\n.RE\n");
+        }
+        if let Some(author) = &self.meta.author {
+            out.push_str(".SH AUTHOR\n");
+            let _ = writeln!(out, "{}", roff_escape(author));
+        }
+        out
+    }
+
+    /// Renders a markdown page.
+    pub fn markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# {}", self.resolved_name());
+        if let Some(about) = &self.meta.about {
+            let _ = write!(out, "\n{about}\n");
+        }
+        for paragraph in self.meta.long_about.iter().flat_map(|s| paragraphs(s)) {
+            let _ = write!(out, "\n{paragraph}\n");
+        }
+        let _ = write!(out, "\n## Usage\n\n```sh\n{}\n```\n", self.usage());
+        if !self.args.is_empty() {
+            out.push_str("\n## Options\n\n");
+        }
+        for ind in 0..self.args.len() {
+            let _ = match self.arg_about(ind) {
+                Some(about) => writeln!(out, "- `{}`: {}", self.arg_spec(ind), about),
+                None => writeln!(out, "- `{}`", self.arg_spec(ind)),
+            };
+        }
+        if !self.examples.is_empty() {
+            out.push_str("\n## Examples\n");
+        }
+        for example in &self.examples {
+            let _ = write!(out, "\n{}\n\n```sh\n{}\n```\n", example.description, example.command);
+        }
+        if let Some(author) = &self.meta.author {
+            let _ = write!(out, "\n## Author\n\n{author}\n");
+        }
+        out
+    }
+
+    pub(super) fn meta_about(&self) -> Option<&str> {
+        self.meta.about.as_deref()
+    }
+
+    pub(super) fn resolved_name(&self) -> String {
+        self.meta.name.clone().unwrap_or_else(|| self.resolved_bin_name())
+    }
+
+    pub(super) fn resolved_bin_name(&self) -> String {
+        match &self.meta.bin_name {
+            Some(bin_name) => bin_name.clone(),
+            None => env::args().next().map(|argv0| bin_name_from(&argv0)).unwrap_or_default(),
+        }
+    }
+
+    /// The description of the arg at `ind` followed by its annotations.
+    fn arg_about(&self, ind: usize) -> Option<String> {
+        let units = self.arg_about_units(ind);
+        (!units.is_empty()).then(|| units.join(" "))
+    }
+
+    /// [`Self::arg_about`] as the units wrapping may break between: the
+    /// words of the description, then each annotation whole.
+    fn arg_about_units(&self, ind: usize) -> Vec<String> {
+        let mut units: Vec<String> = self.args[ind].description()
+            .map(|d| d.split_whitespace().map(String::from).collect())
+            .unwrap_or_default();
+        units.extend(self.arg_annotations(ind));
+        units
+    }
+
+    /// The bracketed `[env: ..]`, length limit and `[default: ..]` groups of the arg at
+    /// `ind`. Each group is a single unit that must not be split when wrapping.
+    fn arg_annotations(&self, ind: usize) -> Vec<String> {
+        let arg = &self.args[ind];
+        let mut annotations = Vec::new();
+        if let Some(var) = arg.env().filter(|_| !self.hide_env) {
+            annotations.push(format!("[env: {var}]"));
+        }
+        if let Arg::String { settings, .. } = arg {
+            if settings.non_empty {
+                annotations.push("[non-empty]".to_string());
+            }
+            match (settings.min_len, settings.max_len) {
+                (Some(min), Some(max)) => annotations.push(format!("[length: {min}-{max}]")),
+                (Some(min), None) => annotations.push(format!("[length: >= {min}]")),
+                (None, Some(max)) => annotations.push(format!("[length: <= {max}]")),
+                (None, None) => {},
+            }
+        }
+        let default = match arg {
+            Arg::Bool { settings, .. } => settings.shown_default().map(bool::to_string),
+            Arg::Int { settings, .. } => settings.shown_default().map(i32::to_string),
+            Arg::String { settings, .. } => settings.shown_default().cloned(),
+        };
+        if let Some(default) = default {
+            annotations.push(format!("[default: {default}]"));
+        }
+        annotations
+    }
+
+    /// The longest key and value placeholder of the arg at `ind`, e.g. `--name <NAME>`.
+    fn arg_spec_short(&self, ind: usize) -> String {
+        let (key_l, key_s) = self.arg_keys(ind);
+        let spec = self.arg_spec(ind);
+        match (key_l, key_s) {
+            (Some(_), Some(key_s)) => spec[key_s.len() + 2..].to_string(),
+            _ => spec,
+        }
+    }
+
+    /// The keys and value placeholder of the arg at `ind`, e.g. `-n, --name <NAME>`.
+    /// The placeholder is named after the long key, or the type without one.
+    /// A flag on by default shows its off switch, `--[no-]cache`.
+    /// A positional is just its placeholder, e.g. `<TARGET>`.
+    fn arg_spec(&self, ind: usize) -> String {
+        if self.is_positional(ind) {
+            return format!("<{}>", self.display_key(ind).to_uppercase());
+        }
+        let (key_l, key_s) = self.arg_keys(ind);
+        let arg = &self.args[ind];
+        // a flag on by default is turned off by its `--no-` form
+        let key_l = match arg {
+            Arg::Bool { settings: ArgSettings { default_val: Some(true), .. }, .. } => key_l.map(|key| format!("--[no-]{}", &key[2..])),
+            _ => key_l.map(str::to_string),
+        };
+        let mut spec = [key_s.map(str::to_string), key_l.clone()].into_iter().flatten().collect::<Vec<_>>().join(", ");
+        let type_name = match arg {
+            Arg::Bool { .. } => return spec,
+            Arg::Int { .. } => "INT",
+            Arg::String { .. } => "STRING",
+        };
+        let value = match arg {
+            Arg::String { settings: ArgSettings { tuple: Some(tuple), .. }, .. } => tuple.placeholder(),
+            _ => format!("<{}>", key_l.map_or(type_name.to_string(), |key| key[2..].to_uppercase())),
+        };
+        match arg.nargs() {
+            _ if arg.is_raw() => spec.push_str(" <ARGS>..."),
+            _ if arg.default_missing_value().is_some() => { let _ = write!(spec, "[={value}]"); },
+            Some(n) => (0..n).for_each(|_| { let _ = write!(spec, " {value}"); }),
+            None if arg.is_greedy() => { let _ = write!(spec, " {value}..."); },
+            None => { let _ = write!(spec, " {value}"); },
+        }
+        spec
+    }
+}
+
+/// Narrowest column wrapped text is squeezed into, however little room is left.
+const MIN_WRAP_WIDTH: usize = 20;
+
+/// Greedily fills lines of at most `width` columns with `units` separated
+/// by spaces. A unit wider than `width` gets a line of its own.
+fn wrap(units: &[String], width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for unit in units {
+        if !line.is_empty() && line.chars().count() + 1 + unit.chars().count() > width {
+            lines.push(mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(unit);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// The binary name in `argv[0]`: its file name without directories and `.exe`.
+fn bin_name_from(argv0: &str) -> String {
+    let file_name = argv0.rsplit(['/', '\\']).next().unwrap_or(argv0);
+    file_name.strip_suffix(".exe").unwrap_or(file_name).to_string()
+}
+
+/// The blank line separated paragraphs of `text`, each joined into one line.
+fn paragraphs(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split("\n\n")
+        .map(|p| p.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|p| !p.is_empty())
+}
+
+/// Escapes text for a roff line: backslashes, hyphens and leading control characters.
+fn roff_escape(s: &str) -> String {
+    let escaped = s.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with(['.', '\'']) {
+        format!("\\&{escaped}")
+    }
+    else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bin_name_from, roff_escape, CliArgs};
+    use crate::args::ParseError;
+
+    fn server() -> CliArgs {
+        let mut args = CliArgs::new();
+        args
+            .bin_name("mytool")
+            .with("--port/-p=i # Port to listen on")
+            .with("--verbose/-v=b?")
+            .example("start a server on port 8080", "mytool --port 8080")
+            .example("be chatty", "mytool -p 80 -v");
+        args
+    }
+
+    #[test]
+    fn help_lists_options_and_examples() {
+        assert_eq!(server().help(), "\
+USAGE:
+    mytool --port <PORT> [OPTIONS]
+
+OPTIONS:
+    -p, --port <PORT>    Port to listen on
+    -v, --verbose
+
+EXAMPLES:
+    start a server on port 8080
+        mytool --port 8080
+
+    be chatty
+        mytool -p 80 -v
+");
+    }
+
+    #[test]
+    fn man_and_markdown_examples() {
+        let man = server().man();
+        assert!(man.starts_with(".TH \"MYTOOL\" 1\n"));
+        assert!(man.contains(".SH EXAMPLES\n.PP\nstart a server on port 8080\n.PP\n.RS\n.nf\nmytool \\-\\-port 8080\n.fi\n.RE\n"));
+
+        let markdown = server().markdown();
+        assert!(markdown.contains("- `-p, --port <PORT>`: Port to listen on\n"));
+        assert!(markdown.ends_with("## Examples\n\nstart a server on port 8080\n\n```sh\nmytool --port 8080\n```\n\nbe chatty\n\n```sh\nmytool -p 80 -v\n```\n"));
+    }
+
+    #[test]
+    fn verify_examples_reports_each() {
+        let mut args = server();
+        args.with("--name=s?")
+            .example("renamed flag", "mytool --listen 80")
+            .example("quoted", "mytool -p 80 --name 'Alp Y'")
+            .example("unclosed quote", "mytool -p 80 --name 'Alp");
+        let results = args.verify_examples();
+        assert_eq!(results.len(), 5);
+        assert!(results[0].is_ok() && results[1].is_ok());
+        assert_eq!(results[2], Err(ParseError::UnknownKey("--listen".to_string())));
+        assert_eq!(results[3], Ok(()));
+        assert_eq!(results[4], Err(ParseError::UnclosedQuote('\'')));
+    }
+
+    #[test]
+    fn env_and_default_annotations() {
+        let mut args = CliArgs::new();
+        args
+            .with("--token=s ::>none # API token")
+            .with("--secret=s ::>hunter2")
+            .with("-j=i ::>4");
+        args.set_env("--token", "MYTOOL_TOKEN").unwrap();
+        args.set_env("--secret", "MYTOOL_SECRET").unwrap();
+        args.set_hide_default("--secret", true).unwrap();
+
+        assert_eq!(args.help().split_once("\n\n").unwrap().1, "\
+OPTIONS:
+    --token <TOKEN>      API token [env: MYTOOL_TOKEN] [default: none]
+    --secret <SECRET>    [env: MYTOOL_SECRET]
+    -j <INT>             [default: 4]
+");
+        assert!(args.markdown().contains("- `--token <TOKEN>`: API token [env: MYTOOL_TOKEN] [default: none]\n"));
+        assert!(args.man().contains("API token [env: MYTOOL_TOKEN] [default: none]\n"));
+
+        args.hide_env(true);
+        assert!(args.help().contains("--token <TOKEN>      API token [default: none]\n"));
+        assert!(args.help().contains("--secret <SECRET>\n"));
+    }
+
+    #[test]
+    fn metadata() {
+        let mut args = server();
+        args
+            .name("My Tool")
+            .author("Jane Doe <jane@example.com>")
+            .about("Serves things")
+            .long_about("First paragraph\nwrapped.\n\nSecond paragraph.");
+
+        assert!(args.help().starts_with("Serves things\n\nUSAGE:\n    mytool --port <PORT> [OPTIONS]\n\nOPTIONS:\n"));
+
+        let man = args.man();
+        assert!(man.starts_with(".TH \"MY TOOL\" 1\n.SH NAME\nMy Tool \\- Serves things\n.SH SYNOPSIS\nmytool \\-\\-port <PORT> [OPTIONS]\n"));
+        assert!(man.contains(".SH DESCRIPTION\nFirst paragraph wrapped.\n.PP\nSecond paragraph.\n"));
+        assert!(man.ends_with(".SH AUTHOR\nJane Doe <jane@example.com>\n"));
+
+        let markdown = args.markdown();
+        assert!(markdown.starts_with("# My Tool\n\nServes things\n\nFirst paragraph wrapped.\n\nSecond paragraph.\n\n## Usage\n\n```sh\nmytool --port <PORT> [OPTIONS]\n```\n"));
+        assert!(markdown.ends_with("## Author\n\nJane Doe <jane@example.com>\n"));
+
+        args.about("Serves things:\n  - files\n  - directories\n");
+        assert!(args.help().starts_with("Serves things:\n  - files\n  - directories\n\nUSAGE:\n    mytool"));
+    }
+
+    #[test]
+    fn bin_name_from_argv0() {
+        assert_eq!(bin_name_from("/usr/local/bin/mytool"), "mytool");
+        assert_eq!(bin_name_from("C:\\tools\\mytool.exe"), "mytool");
+        assert_eq!(bin_name_from("my.tool"), "my.tool");
+        assert_eq!(bin_name_from("mytool"), "mytool");
+    }
+
+    #[test]
+    fn help_snapshot() {
+        let mut args = CliArgs::new();
+        args
+            .bin_name("mytool")
+            .about("Serves files from a directory over HTTP, with optional TLS and access logs written as JSON lines.")
+            .with("--root/-r=s # Directory to serve, every file below it is reachable")
+            .with("--port/-p=i? ::>8080 # Port to listen on")
+            .with("--token=s? ::>none # Bearer token clients have to send in the Authorization header")
+            .with("--allow=s*? # Addresses allowed to connect")
+            .with("--point=i{2}?")
+            .with("--verbose/-v=b?")
+            .example("serve the current directory", "mytool -r .");
+        args.set_env("--token", "MYTOOL_TOKEN").unwrap();
+        crate::testing::assert_help_snapshot(&args, include_str!("testdata/help_v1.txt"));
+    }
+
+    #[test]
+    fn wraps_without_splitting_annotations() {
+        let mut args = CliArgs::new();
+        args.bin_name("t").with("--token=s ::>none # API token used for every request");
+        args.set_env("--token", "MYTOOL_TOKEN").unwrap();
+        let help = args.help_plain_fixed_width(50);
+        assert!(help.ends_with("\
+    --token <TOKEN>    API token used for every
+                       request [env: MYTOOL_TOKEN]
+                       [default: none]
+"));
+        assert!(help.lines().all(|l| l.len() <= 50));
+    }
+
+    #[test]
+    fn aligns_descriptions() {
+        let mut args = CliArgs::new();
+        args
+            .bin_name("t")
+            .with("-v=b? # Say more")
+            .with("--name/-n=s? # Who to greet, wrapped over more than one line of the description column")
+            .with("--point=i{2}? # A point")
+            .with("--a-rather-long-option/-l=s? # Too long for the key column");
+        let help = args.help_plain_fixed_width(60);
+        assert_eq!(help.split_once("OPTIONS:\n").unwrap().1, "    \
+    -v                         Say more
+    -n, --name <NAME>          Who to greet, wrapped over
+                               more than one line of the
+                               description column
+    --point <POINT> <POINT>    A point
+    -l, --a-rather-long-option <A-RATHER-LONG-OPTION>
+                               Too long for the key column
+");
+        let column = 4 + "--point <POINT> <POINT>".len() + 4;
+        for line in help.lines().skip_while(|l| *l != "OPTIONS:").skip(1).filter(|l| !l.contains("<A-RATHER")) {
+            assert!(line[column - 4..column] == *"    " && !line[column..].starts_with(' '), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn escapes_roff() {
+        assert_eq!(roff_escape(".a-b\\c"), "\\&.a\\-b\\ec");
+    }
+}
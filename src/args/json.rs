@@ -0,0 +1,170 @@
+//! Machine readable export of the registered args.
+
+use std::fmt::Write;
+
+use super::{Arg, ArgSettings, CliArgs};
+use super::secret::REDACTED;
+
+impl CliArgs {
+    /// Version of the [`CliArgs::schema_json`] format, bumped on any
+    /// incompatible change. New fields may be added without a bump.
+    pub const SCHEMA_JSON_VERSION: u32 = 1;
+
+    /// Describes every registered arg as pretty printed JSON, in registration
+    /// order, with the declared positionals apart under their name:
+    ///
+    /// ```json
+    /// {
+    ///   "schema_version": 1,
+    ///   "args": [
+    ///     {
+    ///       "long": "--name",
+    ///       "short": "-n",
+    ///       "type": "string",
+    ///       "required": true,
+    ///       "default": null,
+    ///       "greedy": false,
+    ///       "nargs": null,
+    ///       "description": "The user's name"
+    ///     }
+    ///   ],
+    ///   "positionals": [
+    ///     {
+    ///       "name": "target",
+    ///       "type": "string",
+    ///       "required": false,
+    ///       "default": null,
+    ///       "greedy": false,
+    ///       "nargs": null,
+    ///       "description": null
+    ///     }
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// Absent keys and defaults are `null`. An arg with a default is still
+    /// `required` if it was not declared optional, the default satisfies it.
+    /// The default of a [`CliArgs::set_secret`] arg is `"***"`.
+    pub fn schema_json(&self) -> String {
+        self.schema_json_with(false)
+    }
+
+    /// [`CliArgs::schema_json`], with the defaults of secret args in the
+    /// clear if `include_secrets` is set.
+    pub fn schema_json_with(&self, include_secrets: bool) -> String {
+        let mut out = String::new();
+        out.push_str("{\n");
+        let _ = writeln!(out, "  \"schema_version\": {},", Self::SCHEMA_JSON_VERSION);
+        let keyed: Vec<usize> = (0..self.args.len()).filter(|ind| !self.is_positional(*ind)).collect();
+        let args: Vec<_> = keyed.iter().map(|&ind| {
+            let (key_l, key_s) = self.arg_keys(ind);
+            let mut fields = vec![
+                ("long", key_l.map_or("null".to_string(), json_str)),
+                ("short", key_s.map_or("null".to_string(), json_str)),
+            ];
+            fields.extend(self.json_fields(ind, include_secrets));
+            fields
+        }).collect();
+        write_array(&mut out, "args", &args);
+        out.push_str(",\n");
+        let positionals: Vec<_> = self.positionals.iter().map(|&ind| {
+            let mut fields = vec![("name", json_str(self.display_key(ind)))];
+            fields.extend(self.json_fields(ind, include_secrets));
+            fields
+        }).collect();
+        write_array(&mut out, "positionals", &positionals);
+        out.push_str("\n}\n");
+        out
+    }
+
+    /// The fields describing the arg at `ind` after its keys.
+    fn json_fields(&self, ind: usize, include_secrets: bool) -> Vec<(&'static str, String)> {
+        let arg = &self.args[ind];
+        let (type_name, default, optional, greedy, nargs) = match arg {
+            Arg::Bool { settings, .. } => ("bool", json_default(settings, include_secrets, |v| v.to_string()), settings.optional, false, None),
+            Arg::Int { settings, .. } => ("int", json_default(settings, include_secrets, |v| v.to_string()), settings.optional, settings.greedy, settings.nargs),
+            Arg::String { settings, .. } => ("string", json_default(settings, include_secrets, |v| json_str(v)), settings.optional, settings.greedy, settings.nargs),
+        };
+        vec![
+            ("type", json_str(type_name)),
+            ("required", (!optional).to_string()),
+            ("default", default),
+            ("greedy", greedy.to_string()),
+            ("nargs", nargs.map_or("null".to_string(), |n| n.to_string())),
+            ("description", arg.description().map_or("null".to_string(), json_str)),
+        ]
+    }
+}
+
+/// Writes `"name": [..]` with one object per entry of `objects`, without
+/// a trailing newline.
+fn write_array(out: &mut String, name: &str, objects: &[Vec<(&str, String)>]) {
+    let _ = write!(out, "  \"{}\": [", name);
+    for (n, fields) in objects.iter().enumerate() {
+        out.push_str(if n == 0 { "\n    {\n" } else { ",\n    {\n" });
+        for (m, (field, value)) in fields.iter().enumerate() {
+            let _ = write!(out, "      \"{}\": {}", field, value);
+            out.push_str(if m + 1 == fields.len() { "\n" } else { ",\n" });
+        }
+        out.push_str("    }");
+    }
+    out.push_str(if objects.is_empty() { "]" } else { "\n  ]" });
+}
+
+fn json_default<T: std::fmt::Debug>(settings: &ArgSettings<T>, include_secrets: bool, f: impl Fn(&T) -> String) -> String {
+    match &settings.default_val {
+        Some(_) if settings.secret && !include_secrets => json_str(REDACTED),
+        Some(default) => f(default),
+        None => "null".to_string(),
+    }
+}
+
+fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            },
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json_str, CliArgs};
+
+    #[test]
+    fn schema_json_golden() {
+        let mut args = CliArgs::new();
+        args
+            .with("--name/-n=s # The user's \"name\"")
+            .with("--age/-a=i? ::>18")
+            .with("-v=b?")
+            .with("--files=s*?")
+            .with("--point=i{2}")
+            .with("--greeting=s ::>say \"hi\"")
+            .with_positional("target=s? # where to \"greet\"")
+            .with_positional("count=i ::>1");
+        assert_eq!(args.schema_json(), include_str!("testdata/schema_v1.json"));
+    }
+
+    #[test]
+    fn schema_json_empty() {
+        assert_eq!(CliArgs::new().schema_json(), "{\n  \"schema_version\": 1,\n  \"args\": [],\n  \"positionals\": []\n}\n");
+    }
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(json_str("a\"b\\c\nd\u{1}"), "\"a\\\"b\\\\c\\nd\\u0001\"");
+    }
+}
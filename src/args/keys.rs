@@ -0,0 +1,136 @@
+//! The table of registered keys. Keys are interned into one buffer at
+//! registration, so looking one up for every token reads a few small
+//! arrays instead of a separate allocation per key.
+
+use std::fmt::{self, Debug, Formatter};
+
+/// Every registered key with the index of its arg, in registration order.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub(super) struct Keys {
+    text: String, // the keys back to back
+    entries: Vec<Entry>,
+    slots: Vec<u32>, // open addressing by hash: an index into `entries` plus one, 0 if free
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    hash: u32,
+    start: u32,
+    end: u32,
+    ind: usize,
+}
+
+impl Keys {
+    /// The index of the arg registered for `key`.
+    pub(super) fn get(&self, key: &str) -> Option<&usize> {
+        if self.slots.is_empty() {
+            return None;
+        }
+        let hash = hash(key);
+        let mask = self.slots.len() - 1;
+        let mut slot = hash as usize & mask;
+        loop {
+            let entry = &self.entries[self.slots[slot].checked_sub(1)? as usize];
+            if entry.hash == hash && self.key(entry) == key {
+                return Some(&entry.ind);
+            }
+            slot = (slot + 1) & mask;
+        }
+    }
+
+    pub(super) fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Registers `key` for the arg at `ind`. Callers check that `key` is
+    /// not taken yet.
+    pub(super) fn insert(&mut self, key: String, ind: usize) {
+        debug_assert!(!self.contains_key(&key), "`{key}` is registered twice");
+        if (self.entries.len() + 1) * 2 > self.slots.len() {
+            self.grow();
+        }
+        let start = self.text.len() as u32;
+        self.text.push_str(&key);
+        let entry = Entry { hash: hash(&key), start, end: self.text.len() as u32, ind };
+        self.entries.push(entry);
+        self.place(self.entries.len() - 1);
+    }
+
+    /// The keys and their arg indices, in registration order.
+    pub(super) fn iter(&self) -> impl Iterator<Item = (&str, &usize)> {
+        self.entries.iter().map(|entry| (self.key(entry), &entry.ind))
+    }
+
+    fn key(&self, entry: &Entry) -> &str {
+        &self.text[entry.start as usize..entry.end as usize]
+    }
+
+    /// Doubles the slots, keeping at most half of them taken.
+    fn grow(&mut self) {
+        self.slots = vec![0; (self.slots.len() * 2).max(16)];
+        for ind in 0..self.entries.len() {
+            self.place(ind);
+        }
+    }
+
+    /// Puts the entry at `ind` into the first free slot from its hash on.
+    fn place(&mut self, ind: usize) {
+        let mask = self.slots.len() - 1;
+        let mut slot = self.entries[ind].hash as usize & mask;
+        while self.slots[slot] != 0 {
+            slot = (slot + 1) & mask;
+        }
+        self.slots[slot] = ind as u32 + 1;
+    }
+}
+
+impl Debug for Keys {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// FxHash, eight bytes at a time as keys are short. The upper half is kept,
+/// the multiplication mixing every bit of a word only into the higher bits.
+fn hash(key: &str) -> u32 {
+    fn add(hash: u64, word: u64) -> u64 {
+        (hash.rotate_left(5) ^ word).wrapping_mul(0x517cc1b727220a95)
+    }
+
+    let (mut hash, mut bytes) = (0, key.as_bytes());
+    while let Some((word, rest)) = bytes.split_first_chunk::<8>() {
+        hash = add(hash, u64::from_le_bytes(*word));
+        bytes = rest;
+    }
+    if let Some((word, rest)) = bytes.split_first_chunk::<4>() {
+        hash = add(hash, u32::from_le_bytes(*word) as u64);
+        bytes = rest;
+    }
+    for byte in bytes {
+        hash = add(hash, *byte as u64);
+    }
+    (hash >> 32) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Keys;
+
+    #[test]
+    fn lookup_after_growing() {
+        let mut keys = Keys::default();
+        assert_eq!(keys.get("--a"), None);
+        for ind in 0..100 {
+            keys.insert(format!("--key{ind}"), ind);
+            keys.insert(format!("-{ind}"), ind);
+        }
+        for ind in 0..100 {
+            assert_eq!(keys.get(&format!("--key{ind}")), Some(&ind));
+            assert_eq!(keys.get(&format!("-{ind}")), Some(&ind));
+        }
+        assert!(!keys.contains_key("--key100"));
+        assert!(!keys.contains_key("--key"));
+        assert!(!keys.contains_key(""));
+        assert_eq!(keys.iter().take(3).collect::<Vec<_>>(), [("--key0", &0), ("-0", &0), ("--key1", &1)]);
+    }
+}
@@ -0,0 +1,149 @@
+//! `key=value` entries of a repeated string arg read as a map, as for
+//! `-D name=alp -D jobs=4`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use super::{Arg, ArgError, ArgSettings, CliArgs, ParseError};
+
+/// Why the values of an arg could not be read as a map.
+#[derive(Debug)]
+pub enum MapError {
+    /// The key is unknown or not of a string arg.
+    Arg { key: String, error: ArgError },
+    /// A value without `=`.
+    NotAnEntry { key: String, entry: String },
+    /// The value of an entry could not be parsed to the requested type.
+    InvalidValue { key: String, entry: String },
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Arg { key, error: ArgError::WrongKey } => write!(f, "no arg is registered for `{}`", key),
+            MapError::Arg { key, error: ArgError::WrongType } => write!(f, "`{}` is no string arg", key),
+            MapError::Arg { key, error: ArgError::Missing } => write!(f, "`{}` has no value", key),
+            MapError::NotAnEntry { key, entry } => write!(f, "`{}` of `{}` is no `key=value` entry", entry, key),
+            MapError::InvalidValue { key, entry } => write!(f, "the value of `{}` of `{}` has the wrong type", entry, key),
+        }
+    }
+}
+
+impl Error for MapError {}
+
+impl CliArgs {
+    /// Restricts the `key=value` entries of a string arg to the given keys.
+    /// Every value must then be such an entry, one of another key fails the
+    /// parse with [`ParseError::UnknownMapKey`] listing the allowed keys.
+    pub fn set_map_keys(&mut self, key: &str, keys: &[&str]) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        match &mut self.args[ind] {
+            Arg::String { settings, .. } => settings.map_keys = keys.iter().map(|key| key.to_string()).collect(),
+            _ => return Err(ArgError::WrongType),
+        }
+        Ok(())
+    }
+
+    /// The `key=value` entries of a string arg in command line order, split
+    /// at the first `=`. A later entry for the same key does not replace an
+    /// earlier one here.
+    ///
+    /// ```
+    /// # use clitrs::args::CliArgs;
+    /// let mut args = CliArgs::new();
+    /// args.with("--define/-D=s?").parse("-D a=1 -D b=x=y -D a=2").unwrap();
+    /// assert_eq!(args.get_map_multi("-D").unwrap(), [("a", "1"), ("b", "x=y"), ("a", "2")]);
+    /// ```
+    pub fn get_map_multi(&self, key: &str) -> Result<Vec<(&str, &str)>, MapError> {
+        self.get_string_multi(key)
+            .map_err(|error| MapError::Arg { key: key.to_string(), error })?
+            .iter()
+            .map(|entry| entry.split_once('=').ok_or_else(|| MapError::NotAnEntry { key: key.to_string(), entry: entry.clone() }))
+            .collect()
+    }
+
+    /// The `key=value` entries of a string arg with every value parsed to
+    /// `T`, a later entry for the same key replacing an earlier one. The
+    /// first entry whose value does not parse is [`MapError::InvalidValue`].
+    pub fn get_map_as<T: FromStr>(&self, key: &str) -> Result<HashMap<String, T>, MapError> {
+        self.get_map_multi(key)?.into_iter()
+            .map(|(entry_key, value)| match value.parse() {
+                Ok(value) => Ok((entry_key.to_string(), value)),
+                Err(_) => Err(MapError::InvalidValue { key: key.to_string(), entry: format!("{entry_key}={value}") }),
+            })
+            .collect()
+    }
+}
+
+impl ArgSettings<String> {
+    /// Checks a value against [`CliArgs::set_map_keys`], if set.
+    pub(super) fn check_map_key(&self, key: &str, val: &str) -> Result<(), ParseError> {
+        if self.map_keys.is_empty() {
+            return Ok(());
+        }
+        let Some((entry_key, _)) = val.split_once('=') else {
+            return Err(ParseError::InvalidValue { key: key.to_string(), value: val.to_string() });
+        };
+        match self.map_keys.iter().any(|allowed| allowed == entry_key) {
+            true => Ok(()),
+            false => Err(ParseError::UnknownMapKey {
+                key: key.to_string(),
+                entry: entry_key.to_string(),
+                allowed: self.map_keys.clone(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::MapError;
+    use crate::args::{CliArgs, ParseError};
+
+    #[test]
+    fn typed_maps() {
+        let mut args = CliArgs::new();
+        args.with("--define/-D=s?").with("--set=s*?");
+        args.parse("-D jobs=4 -D retries=2 -D jobs=8 --set a=x b=").unwrap();
+
+        assert_eq!(args.get_map_as::<i32>("-D").unwrap(), HashMap::from([("jobs".to_string(), 8), ("retries".to_string(), 2)]));
+        assert_eq!(args.get_map_multi("-D").unwrap(), [("jobs", "4"), ("retries", "2"), ("jobs", "8")]);
+        assert_eq!(args.get_map_as::<String>("--set").unwrap()["b"], "");
+
+        let e = args.get_map_as::<i32>("--set").unwrap_err();
+        assert!(matches!(&e, MapError::InvalidValue { key, entry } if key == "--set" && entry == "a=x"));
+        assert_eq!(e.to_string(), "the value of `a=x` of `--set` has the wrong type");
+
+        args.reset();
+        args.parse("-D jobs").unwrap();
+        assert!(matches!(args.get_map_multi("-D"), Err(MapError::NotAnEntry { entry, .. }) if entry == "jobs"));
+
+        let e = args.get_map_multi("--nope").unwrap_err();
+        assert_eq!(e.to_string(), "no arg is registered for `--nope`");
+        args.with("-v=b?");
+        assert_eq!(args.get_map_multi("-v").unwrap_err().to_string(), "`-v` is no string arg");
+    }
+
+    #[test]
+    fn allowed_map_keys() {
+        let mut args = CliArgs::new();
+        args.with("--define/-D=s?");
+        args.set_map_keys("-D", &["jobs", "retries"]).unwrap();
+        args.parse("-D jobs=4 -D retries=1").unwrap();
+
+        args.reset();
+        let e = args.parse("-D jobs=4 -D job=2 -D retries").unwrap_err();
+        assert_eq!(e, ParseError::Multiple(vec![
+            ParseError::UnknownMapKey { key: "-D".to_string(), entry: "job".to_string(), allowed: vec!["jobs".to_string(), "retries".to_string()] },
+            ParseError::InvalidValue { key: "-D".to_string(), value: "retries".to_string() },
+        ]));
+        assert_eq!(
+            e.to_string(),
+            "2 errors:\n  - unknown key `job` in `-D`, expected one of `jobs`, `retries`\n  - invalid value `retries` for `-D`",
+        );
+    }
+}
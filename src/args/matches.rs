@@ -0,0 +1,140 @@
+//! A typed view of parsed values with a single error surface, see
+//! [`CliArgs::matches`].
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use super::{ArgError, CliArgs};
+
+/// The first lookup that failed through a [`Matches`], see [`Matches::finish`].
+#[derive(Debug)]
+pub struct MatchesError {
+    pub key: String,
+    pub error: ArgError,
+}
+
+impl Display for MatchesError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.error {
+            ArgError::WrongKey => write!(f, "no arg is registered for key `{}`", self.key),
+            ArgError::WrongType => write!(f, "arg `{}` was read as the wrong type", self.key),
+            ArgError::Missing => write!(f, "arg `{}` has no value", self.key),
+        }
+    }
+}
+
+impl Error for MatchesError {}
+
+/// Parsed values read without unwrapping each one: an absent value is the
+/// type's default, and a key that is unknown or of another type reads as
+/// the default too, with the first such mistake kept for [`Matches::finish`].
+///
+/// ```
+/// # use clitrs::args::CliArgs;
+/// let mut args = CliArgs::new();
+/// args.with("--name=s").with("--jobs=i? ::>4").with("--verbose/-v=b?");
+/// args.parse("--name alp -v").unwrap();
+///
+/// let m = args.matches();
+/// assert_eq!((m.str("--name"), m.int("--jobs"), m.flag("-v")), ("alp", 4, true));
+/// assert!(m.finish().is_ok());
+/// ```
+#[must_use = "mistaken lookups are only reported by `Matches::finish`"]
+pub struct Matches<'a> {
+    args: &'a CliArgs,
+    error: RefCell<Option<MatchesError>>,
+}
+
+impl CliArgs {
+    /// A view of the parsed values with plain getters, see [`Matches`].
+    pub fn matches(&self) -> Matches<'_> {
+        Matches { args: self, error: RefCell::new(None) }
+    }
+}
+
+impl<'a> Matches<'a> {
+    /// The first value of an int arg, or 0.
+    pub fn int(&self, key: &str) -> i32 {
+        self.read(key, self.args.get_int(key)).unwrap_or_default()
+    }
+
+    /// The first value of a string arg, or `""`.
+    pub fn str(&self, key: &str) -> &'a str {
+        self.read(key, self.args.get_str(key)).unwrap_or_default()
+    }
+
+    /// Whether a flag is set, false if it was not given and has no default.
+    pub fn flag(&self, key: &str) -> bool {
+        self.read(key, self.args.get_bool(key)).unwrap_or_default()
+    }
+
+    /// [`CliArgs::verbosity`] of the parsed values.
+    pub fn verbosity(&self) -> i8 {
+        self.args.verbosity()
+    }
+
+    /// [`CliArgs::log_level_filter`] of the parsed values.
+    #[cfg(feature = "log")]
+    pub fn log_level_filter(&self) -> log::LevelFilter {
+        self.args.log_level_filter()
+    }
+
+    /// The first failed lookup, if any.
+    #[must_use = "the values read may be defaults standing in for a mistaken lookup"]
+    pub fn finish(self) -> Result<(), MatchesError> {
+        self.error.into_inner().map_or(Ok(()), Err)
+    }
+
+    fn read<T>(&self, key: &str, value: Result<Option<T>, ArgError>) -> Option<T> {
+        value.unwrap_or_else(|error| {
+            self.error.borrow_mut().get_or_insert(MatchesError { key: key.to_string(), error });
+            None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::{ArgError, CliArgs};
+
+    #[test]
+    fn typed_values_and_first_error() {
+        let mut args = CliArgs::new();
+        args
+            .with("--name/-n=s")
+            .with("--jobs/-j=i? ::>4")
+            .with("--retries=i?")
+            .with("--verbose/-v=b?")
+            .with("--dry-run=b?");
+        args.parse("-n alp -j 0x10 -v").unwrap();
+
+        let m = args.matches();
+        assert_eq!(m.str("--name"), "alp");
+        assert_eq!(m.int("-j"), 16);
+        assert_eq!(m.int("--retries"), 0);
+        assert!(m.flag("--verbose") && !m.flag("--dry-run"));
+        assert!(m.finish().is_ok());
+
+        let m = args.matches();
+        assert_eq!(m.int("--name"), 0);
+        assert!(!m.flag("--nope"));
+        let e = m.finish().unwrap_err();
+        assert_eq!(e.key, "--name");
+        assert!(matches!(e.error, ArgError::WrongType));
+        assert_eq!(e.to_string(), "arg `--name` was read as the wrong type");
+    }
+
+    #[test]
+    fn verbosity_of_the_parsed_values() {
+        let mut args = CliArgs::new();
+        args.with_verbosity().unwrap();
+        args.parse("-vv -q").unwrap();
+
+        let m = args.matches();
+        assert_eq!(m.verbosity(), 1);
+        #[cfg(feature = "log")]
+        assert_eq!(m.log_level_filter(), log::LevelFilter::Info);
+        assert!(m.finish().is_ok());
+    }
+}
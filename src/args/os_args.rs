@@ -0,0 +1,138 @@
+//! Command lines and environment variables that are not valid UTF-8, as
+//! Unix allows, see [`CliArgs::set_non_utf8`].
+
+use std::env;
+use std::ffi::OsString;
+
+use super::{CliArgs, ParseError};
+
+/// What a parse does with a token or an environment variable value that
+/// is not valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonUtf8 {
+    /// Replace the invalid bytes by `U+FFFD`, so a string arg takes the
+    /// lossy value, and note it in [`CliArgs::warnings`].
+    #[default]
+    Lossy,
+    /// Fail the parse with [`ParseError::NonUtf8`], or [`ParseError::NonUtf8Var`]
+    /// naming the variable.
+    Strict,
+    /// Treat the variable as unset. A token fails the parse as with
+    /// [`NonUtf8::Strict`], as leaving it out would hand the tokens after it
+    /// to the wrong keys: `--out <invalid> file2` would set `--out` to `file2`.
+    Skip,
+}
+
+impl CliArgs {
+    /// Decides about tokens of [`CliArgs::parse_cmd_os`] and values of
+    /// [`CliArgs::set_env`] variables that are not valid UTF-8, lossily
+    /// converted by default.
+    pub fn set_non_utf8(&mut self, non_utf8: NonUtf8) -> &mut Self {
+        self.non_utf8 = non_utf8;
+        self
+    }
+
+    /// What the last parses noted without failing, like a lossily converted
+    /// value, until [`CliArgs::reset`].
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Parses the process arguments like [`CliArgs::parse_cmd`] without
+    /// panicking on one that is not valid UTF-8, see [`CliArgs::set_non_utf8`].
+    pub fn parse_cmd_os(&mut self) -> Result<(), ParseError> {
+        let tokens: Vec<OsString> = env::args_os().skip(1).collect();
+        self.parse_from_os(&tokens)
+    }
+
+    /// Parses already split tokens like [`CliArgs::parse_from`], which may
+    /// not be valid UTF-8.
+    pub fn parse_from_os(&mut self, tokens: &[OsString]) -> Result<(), ParseError> {
+        let mut converted = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            converted.extend(self.utf8(token.clone(), None)?);
+        }
+        self.parse_from(&converted)
+    }
+
+    /// The value of the environment variable `var` read for `key`, `None`
+    /// if it is unset or skipped.
+    pub(super) fn env_var(&mut self, var: &str, key: &str) -> Result<Option<String>, ParseError> {
+        match env::var_os(var) {
+            Some(value) => self.utf8(value, Some((var, key))),
+            None => Ok(None),
+        }
+    }
+
+    /// `value` as a string following [`CliArgs::set_non_utf8`], a token or,
+    /// with its name and the key reading it, an environment variable's value.
+    fn utf8(&mut self, value: OsString, var: Option<(&str, &str)>) -> Result<Option<String>, ParseError> {
+        let value = match value.into_string() {
+            Ok(value) => return Ok(Some(value)),
+            Err(value) => value.to_string_lossy().into_owned(),
+        };
+        match (self.non_utf8, var) {
+            (NonUtf8::Lossy, None) => self.warnings.push(format!("`{value}` is not valid UTF-8, invalid bytes are replaced")),
+            (NonUtf8::Lossy, Some((var, _))) => self.warnings.push(format!("`${var}` is not valid UTF-8, invalid bytes are replaced")),
+            (NonUtf8::Strict | NonUtf8::Skip, None) => return Err(ParseError::NonUtf8(value)),
+            (NonUtf8::Strict, Some((var, key))) => return Err(ParseError::NonUtf8Var { key: key.to_string(), var: var.to_string() }),
+            (NonUtf8::Skip, Some(_)) => return Ok(None),
+        }
+        Ok(Some(value))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::env;
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    use super::NonUtf8;
+    use crate::args::{CliArgs, ParseError};
+
+    #[test]
+    fn invalid_utf8_tokens() {
+        let tokens = [OsString::from("--name"), OsString::from_vec(b"caf\xe9".to_vec())];
+        let mut args = CliArgs::new();
+        args.with("--name=s?");
+        args.parse_from_os(&tokens).unwrap();
+        assert_eq!(args.get_str("--name").unwrap(), Some("caf\u{FFFD}"));
+        assert_eq!(args.warnings(), ["`caf\u{FFFD}` is not valid UTF-8, invalid bytes are replaced"]);
+
+        args.reset();
+        let e = args.set_non_utf8(NonUtf8::Strict).parse_from_os(&tokens).unwrap_err();
+        assert_eq!(e, ParseError::NonUtf8("caf\u{FFFD}".to_string()));
+        assert_eq!(e.to_string(), "`caf\u{FFFD}` is not valid UTF-8");
+        assert!(args.warnings().is_empty());
+
+        // a skipped value would hand `file2` to `--out`
+        let tokens = [OsString::from("--out"), OsString::from_vec(b"\xff".to_vec()), OsString::from("file2")];
+        let mut args = CliArgs::new();
+        args.with("--out=s?").with_positional("rest=s?");
+        let e = args.set_non_utf8(NonUtf8::Skip).parse_from_os(&tokens).unwrap_err();
+        assert_eq!(e, ParseError::NonUtf8("\u{FFFD}".to_string()));
+    }
+
+    #[test]
+    fn invalid_utf8_variables() {
+        let var = format!("CLITRS_TEST_LATIN1_{}", std::process::id());
+        env::set_var(&var, OsString::from_vec(b"M\xfcller".to_vec()));
+        let mut args = CliArgs::new();
+        args.with("--name=s? ::>nobody");
+        args.set_env("--name", &var).unwrap();
+
+        args.parse("").unwrap();
+        assert_eq!(args.get_str("--name").unwrap(), Some("M\u{FFFD}ller"));
+        assert_eq!(args.warnings(), [format!("`${var}` is not valid UTF-8, invalid bytes are replaced")]);
+
+        args.reset();
+        args.set_non_utf8(NonUtf8::Skip).parse("").unwrap();
+        assert_eq!(args.get_str("--name").unwrap(), Some("nobody"));
+
+        args.reset();
+        let e = args.set_non_utf8(NonUtf8::Strict).parse("").unwrap_err();
+        assert_eq!(e, ParseError::NonUtf8Var { key: "--name".to_string(), var: var.clone() });
+        env::remove_var(&var);
+    }
+}
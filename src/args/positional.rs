@@ -0,0 +1,200 @@
+//! Declared positionals, filled in order by the values that belong to no
+//! key, see [`CliArgs::with_positional`].
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use super::{Arg, CliArgs, SchemaError};
+
+/// Why a positional could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PositionalError {
+    /// No positional is declared under the name or at the index.
+    NotDeclared(String),
+    /// The positional is declared, but the last parse gave it no value.
+    Missing(String),
+    /// The value could not be parsed to the requested type.
+    Invalid { name: String, value: String },
+}
+
+impl Display for PositionalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PositionalError::NotDeclared(name) => write!(f, "no positional `{}` is declared", name),
+            PositionalError::Missing(name) => write!(f, "positional `{}` was not given", name),
+            PositionalError::Invalid { name, value } => write!(f, "invalid value `{}` for positional `{}`", value, name),
+        }
+    }
+}
+
+impl Error for PositionalError {}
+
+impl CliArgs {
+    /// Declares the next positional from a schema like [`CliArgs::with`]
+    /// without the leading dashes, `target=s` or `count=i? ::>1 # how many`.
+    /// Values without a key fill the declared positionals in order, those
+    /// after `--` too. Values left over before `--` are
+    /// [`ParseError::UnexpectedValue`](super::ParseError::UnexpectedValue),
+    /// or [`CliArgs::remaining_positionals`] under
+    /// [`CliArgs::allow_extra_positionals`], and those after it
+    /// [`CliArgs::trailing`]. A required one not given is
+    /// [`ParseError::MissingRequired`](super::ParseError::MissingRequired).
+    ///
+    /// ```
+    /// # use clitrs::args::CliArgs;
+    /// # use std::path::PathBuf;
+    /// let mut args = CliArgs::new();
+    /// args.with("--force/-f=b?").with_positional("target=s").with_positional("count=i? ::>1");
+    /// args.allow_extra_positionals(true);
+    /// args.parse("build -f 3 a b").unwrap();
+    /// assert_eq!(args.get_positional::<PathBuf>("target").unwrap(), PathBuf::from("build"));
+    /// assert_eq!(args.positional_at::<i32>(1).unwrap(), 3);
+    /// assert_eq!(args.remaining_positionals(), ["a", "b"]);
+    /// ```
+    ///
+    /// The name is registered as a key, so [`CliArgs::set_choices`],
+    /// [`CliArgs::with_range`] and the other settings take it. A name that
+    /// starts with `-`, has a second key or is greedy is a schema error.
+    pub fn with_positional(&mut self, schema: &str) -> &mut Self {
+        let malformed = || SchemaError::Malformed(schema.to_string());
+        let parsed = match schema.starts_with('-') {
+            true => Err(malformed()),
+            false => Self::parse_schema(&format!("--{schema}")),
+        };
+        let (name, arg) = match parsed {
+            Ok((Some(key_l), None, arg)) if !arg.is_greedy() && arg.nargs().is_none() => (key_l[2..].to_string(), arg),
+            Ok(_) => {
+                self.schema_error.get_or_insert(malformed());
+                return self;
+            },
+            Err(SchemaError::Malformed(_) | SchemaError::MissingKeyPrefix(_)) => {
+                self.schema_error.get_or_insert(malformed());
+                return self;
+            },
+            Err(e) => {
+                self.schema_error.get_or_insert(e);
+                return self;
+            },
+        };
+        if self.keys.contains_key(name.as_str()) {
+            self.schema_error.get_or_insert(SchemaError::KeyTaken(name));
+            return self;
+        }
+        self.display_keys.push(name.clone());
+        self.keys.insert(name, self.args.len());
+        self.positionals.push(self.args.len());
+        self.args.push(arg);
+        self
+    }
+
+    /// The value of the positional declared as `name`, parsed to `T`.
+    pub fn get_positional<T: FromStr>(&self, name: &str) -> Result<T, PositionalError> {
+        match self.keys.get(name).filter(|ind| self.is_positional(**ind)) {
+            Some(&ind) => self.positional_value(ind, name),
+            None => Err(PositionalError::NotDeclared(name.to_string())),
+        }
+    }
+
+    /// The value of the positional declared at `index`, counting from 0,
+    /// parsed to `T`.
+    pub fn positional_at<T: FromStr>(&self, index: usize) -> Result<T, PositionalError> {
+        match self.positionals.get(index) {
+            Some(&ind) => self.positional_value(ind, self.display_key(ind)),
+            None => Err(PositionalError::NotDeclared(index.to_string())),
+        }
+    }
+
+    /// The values without a key left over after the declared positionals
+    /// are filled, in order, see [`CliArgs::allow_extra_positionals`].
+    pub fn remaining_positionals(&self) -> &[String] {
+        &self.remaining
+    }
+
+    /// Whether the arg at `ind` was declared by [`CliArgs::with_positional`].
+    pub(super) fn is_positional(&self, ind: usize) -> bool {
+        self.positionals.contains(&ind)
+    }
+
+    fn positional_value<T: FromStr>(&self, ind: usize, name: &str) -> Result<T, PositionalError> {
+        let value = match &self.args[ind] {
+            Arg::Bool { vals, .. } => vals.first().map(bool::to_string),
+            Arg::Int { vals, .. } => vals.first().map(i32::to_string),
+            Arg::String { vals, .. } => vals.first().cloned(),
+        };
+        let value = value.ok_or_else(|| PositionalError::Missing(name.to_string()))?;
+        value.parse().map_err(|_| PositionalError::Invalid { name: name.to_string(), value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::PositionalError;
+    use crate::args::{CliArgs, ParseError, SchemaError};
+
+    fn copy() -> CliArgs {
+        let mut args = CliArgs::new();
+        args
+            .with("--verbose/-v=b?")
+            .with_positional("source=s # file to copy")
+            .with_positional("mode=s? ::>fast")
+            .with_positional("jobs=i?")
+            .with_range("jobs", 1, 8);
+        args.set_choices("mode", &["fast", "safe"], false).unwrap();
+        args
+    }
+
+    #[test]
+    fn typed_positionals() {
+        let mut args = copy();
+        args.allow_extra_positionals(true);
+        args.parse("a.txt -v safe 4 x y").unwrap();
+        assert_eq!(args.get_positional::<PathBuf>("source").unwrap(), PathBuf::from("a.txt"));
+        assert_eq!(args.get_positional::<String>("mode").unwrap(), "safe");
+        assert_eq!(args.positional_at::<u8>(2).unwrap(), 4);
+        assert_eq!(args.remaining_positionals(), ["x", "y"]);
+        assert_eq!(args.get_positional::<i32>("source"), Err(PositionalError::Invalid { name: "source".to_string(), value: "a.txt".to_string() }));
+
+        args.reset();
+        args.parse("b.txt").unwrap();
+        assert_eq!(args.get_positional::<String>("mode").unwrap(), "fast");
+        assert_eq!(args.positional_at::<i32>(2), Err(PositionalError::Missing("jobs".to_string())));
+        assert_eq!(args.get_positional::<bool>("verbose"), Err(PositionalError::NotDeclared("verbose".to_string())));
+        assert_eq!(args.positional_at::<i32>(3), Err(PositionalError::NotDeclared("3".to_string())));
+        assert!(args.remaining_positionals().is_empty());
+        assert!(args.usage().ends_with(" <SOURCE> [OPTIONS]"));
+        assert!(args.help().contains("<SOURCE>") && args.help().contains("file to copy"));
+    }
+
+    #[test]
+    fn positional_constraints() {
+        let mut args = copy();
+        assert_eq!(args.parse("-v"), Err(ParseError::MissingRequired("source".to_string())));
+
+        args.reset();
+        let e = args.parse("a.txt slow 9").unwrap_err();
+        assert!(matches!(&e, ParseError::Multiple(errors) if errors.len() == 2));
+
+        args.reset();
+        assert_eq!(args.parse("a.txt safe 4 x"), Err(ParseError::UnexpectedValue("x".to_string())));
+        assert!(args.remaining_positionals().is_empty());
+
+        args.reset();
+        args.parse("-v -- -a.txt safe 2 --x").unwrap();
+        assert_eq!(args.get_positional::<String>("source").unwrap(), "-a.txt");
+        assert_eq!(args.positional_at::<i32>(2).unwrap(), 2);
+        assert_eq!(args.trailing(), ["--x"]);
+
+        let mut args = CliArgs::new();
+        args.with_positional("target=s").with_positional("target=i?");
+        assert_eq!(args.schema_error(), Some(&SchemaError::KeyTaken("target".to_string())));
+
+        for schema in ["-t=s", "files=s*", "a/b=s"] {
+            let mut args = CliArgs::new();
+            args.with_positional(schema);
+            assert_eq!(args.schema_error(), Some(&SchemaError::Malformed(schema.to_string())));
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Splitting of schema strings such as `--name/-n=s*?` into their parts.
+//!
+//! The default path is a small hand written parser; the `regex-schema`
+//! feature switches to the original regex based one. Both accept exactly
+//! the same grammar.
+
+/// The parts of a schema before `::>`, with whitespace already removed.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct SchemaParts<'a> {
+    pub key_l: Option<&'a str>,
+    pub key_s: Option<&'a str>,
+    pub type_code: char,
+    pub greedy: bool,
+    pub nargs: Option<&'a str>,
+    pub optional: bool,
+}
+
+#[cfg(not(feature = "regex-schema"))]
+pub(crate) fn split_schema(schema: &str) -> Option<SchemaParts<'_>> {
+    split(schema)
+}
+
+#[cfg(feature = "regex-schema")]
+pub(crate) fn split_schema(schema: &str) -> Option<SchemaParts<'_>> {
+    split_regex(schema)
+}
+
+#[cfg_attr(feature = "regex-schema", allow(dead_code))]
+fn split(schema: &str) -> Option<SchemaParts<'_>> {
+    let (keys, rest) = schema.split_once('=')?;
+    let (key_l, key_s) = match keys.split_once('/') {
+        Some((key_l, key_s)) if is_long(key_l) && is_short(key_s) => (Some(key_l), Some(key_s)),
+        Some(_) => return None,
+        None if is_long(keys) => (Some(keys), None),
+        None if is_short(keys) => (None, Some(keys)),
+        None => return None,
+    };
+
+    let mut chars = rest.chars();
+    let type_code = chars.next().filter(|c| matches!(c, 'b' | 'i' | 's'))?;
+    let mut rest = chars.as_str();
+
+    let mut greedy = false;
+    let mut nargs = None;
+    if let Some(r) = rest.strip_prefix('*') {
+        greedy = true;
+        rest = r;
+    }
+    else if let Some(r) = rest.strip_prefix('{') {
+        let (digits, r) = r.split_once('}')?;
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        nargs = Some(digits);
+        rest = r;
+    }
+
+    let optional = match rest {
+        "" => false,
+        "?" => true,
+        _ => return None,
+    };
+
+    Some(SchemaParts { key_l, key_s, type_code, greedy, nargs, optional })
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+fn is_long(s: &str) -> bool {
+    s.strip_prefix("--").is_some_and(|k| !k.is_empty() && k.chars().all(is_key_char))
+}
+
+fn is_short(s: &str) -> bool {
+    s.strip_prefix('-').is_some_and(|k| !k.is_empty() && k.chars().all(is_key_char))
+}
+
+#[cfg(feature = "regex-schema")]
+fn split_regex(schema: &str) -> Option<SchemaParts<'_>> {
+    use std::sync::OnceLock;
+    use regex::Regex;
+
+    // const SCHEMA_REGEX: &'static str = r#"((?P<kl>--[\w_-]+)|(?P<ks>-[\w_-]+)|(?P<kls>--[\w_-]+/-[\w_-]+))=(?P<type>[bis])\??(:(?P<default_val>.+))?"#;
+    const SCHEMA_REGEX: &str = r#"^((?P<kl>--[\w_-]+)|(?P<ks>-[\w_-]+)|(?P<kls>--[\w_-]+/-[\w_-]+))=(?P<type>[bis])((?P<greedy>\*)|\{(?P<nargs>\d+)\})?(?P<optional>\?)?$"#;
+    static RE: OnceLock<Regex> = OnceLock::new();
+
+    let captures = RE.get_or_init(|| Regex::new(SCHEMA_REGEX).unwrap()).captures(schema)?;
+    let (key_l, key_s) = match captures.name("kls") {
+        Some(kls) => {
+            let (key_l, key_s) = kls.as_str().split_once('/')?;
+            (Some(key_l), Some(key_s))
+        },
+        None => (captures.name("kl").map(|m| m.as_str()), captures.name("ks").map(|m| m.as_str())),
+    };
+
+    Some(SchemaParts {
+        key_l,
+        key_s,
+        type_code: captures.name("type")?.as_str().chars().next()?,
+        greedy: captures.name("greedy").is_some(),
+        nargs: captures.name("nargs").map(|m| m.as_str()),
+        optional: captures.name("optional").is_some(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split, SchemaParts};
+
+    const CORPUS: &[&str] = &[
+        "--name/-n=s",
+        "--age/-a=i?",
+        "--adult=b?",
+        "-v=b",
+        "--files/-f=s*?",
+        "--nums=i*",
+        "--point=i{2}",
+        "--point=i{2}?",
+        "--snake_case-key=s",
+        "---x=s",
+        "--x/--y=s",
+        "--ünï=s",
+        "name=s",
+        "-=s",
+        "--=s",
+        "--name",
+        "--name=",
+        "--name=x",
+        "--name=ss",
+        "--name=s?*",
+        "--name=i{}",
+        "--name=i{2",
+        "--name=i{a}",
+        "-a/-b=s",
+        "--a/b=s",
+        "--a/-b/-c=s",
+        "x--name=s",
+        "--na me=s",
+        "",
+    ];
+
+    #[test]
+    fn splits_parts() {
+        assert_eq!(split("--name/-n=s*?"), Some(SchemaParts {
+            key_l: Some("--name"),
+            key_s: Some("-n"),
+            type_code: 's',
+            greedy: true,
+            nargs: None,
+            optional: true,
+        }));
+        assert_eq!(split("-p=i{12}"), Some(SchemaParts {
+            key_l: None,
+            key_s: Some("-p"),
+            type_code: 'i',
+            greedy: false,
+            nargs: Some("12"),
+            optional: false,
+        }));
+
+        let accepted: Vec<&str> = CORPUS.iter().copied().filter(|s| split(s).is_some()).collect();
+        assert_eq!(accepted, [
+            "--name/-n=s", "--age/-a=i?", "--adult=b?", "-v=b", "--files/-f=s*?", "--nums=i*",
+            "--point=i{2}", "--point=i{2}?", "--snake_case-key=s", "---x=s", "--x/--y=s", "--ünï=s", "--=s",
+        ]);
+    }
+
+    #[cfg(feature = "regex-schema")]
+    mod regex {
+        use proptest::prelude::*;
+        use super::super::{split, split_regex};
+        use super::CORPUS;
+
+        #[test]
+        fn corpus_matches_hand_parser() {
+            for schema in CORPUS {
+                assert_eq!(split(schema), split_regex(schema), "{}", schema);
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn matches_hand_parser(schema in "-{0,3}[a-z_]{0,3}(/-{0,2}[a-z]{0,2})?=?[bisx]?(\\*|\\{[0-9a]{0,2}\\}?)?\\??") {
+                prop_assert_eq!(split(&schema), split_regex(&schema));
+            }
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Redaction of [`CliArgs::set_secret`] values: the `Debug` impls of the
+//! types holding values, which cannot be derived for that reason.
+
+use std::fmt::{self, Debug, Formatter};
+
+use super::{Arg, ArgSettings, ArgValues, CliArgs, OrderedArg};
+
+/// Stands in for a secret value wherever it would be shown.
+pub(super) const REDACTED: &str = "***";
+
+/// Prints as [`REDACTED`].
+struct Redacted;
+
+impl Debug for Redacted {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// `value`, or [`Redacted`] if `secret`.
+fn redact(value: &dyn Debug, secret: bool) -> &dyn Debug {
+    if secret { &Redacted } else { value }
+}
+
+impl<T: Debug> ArgSettings<T> {
+    /// The default as shown in help, neither hidden nor secret.
+    pub(super) fn shown_default(&self) -> Option<&T> {
+        self.default_val.as_ref().filter(|_| !self.hide_default && !self.secret)
+    }
+}
+
+impl Arg {
+    pub(super) fn is_secret(&self) -> bool {
+        match self {
+            Arg::Bool { settings, .. } => settings.secret,
+            Arg::Int { settings, .. } => settings.secret,
+            Arg::String { settings, .. } => settings.secret,
+        }
+    }
+}
+
+impl<T: Debug> Debug for ArgSettings<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArgSettings")
+            .field("optional", &self.optional)
+            .field("default_val", redact(&self.default_val, self.secret && self.default_val.is_some()))
+            .field("greedy", &self.greedy)
+            .field("nargs", &self.nargs)
+            .field("description", &self.description)
+            .field("file_value", &self.file_value)
+            .field("env", &self.env)
+            .field("hide_default", &self.hide_default)
+            .field("choices", &self.choices)
+            .field("value_aliases", &self.value_aliases)
+            .field("ignore_case", &self.ignore_case)
+            .field("value_hint", &self.value_hint)
+            .field("completer", &self.completer)
+            .field("validators", &self.validators)
+            .field("expand_env", &self.expand_env)
+            .field("expand_tilde", &self.expand_tilde)
+            .field("transforms", &self.transforms)
+            .field("secret", &self.secret)
+            .field("non_empty", &self.non_empty)
+            .field("min_len", &self.min_len)
+            .field("max_len", &self.max_len)
+            .field("tuple", &self.tuple)
+            .field("default_missing", &self.default_missing)
+            .field("hyphen_values", &self.hyphen_values)
+            .field("raw", &self.raw)
+            .field("join_rest", &self.join_rest)
+            .field("map_keys", &self.map_keys)
+            .field("post_transform", &self.post_transform)
+            .finish()
+    }
+}
+
+impl Debug for Arg {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let secret = self.is_secret();
+        match self {
+            Arg::Bool { vals, settings } => f.debug_struct("Bool").field("vals", redact(vals, secret)).field("settings", settings).finish(),
+            Arg::Int { vals, settings } => f.debug_struct("Int").field("vals", redact(vals, secret)).field("settings", settings).finish(),
+            Arg::String { vals, settings } => f.debug_struct("String").field("vals", redact(vals, secret)).field("settings", settings).finish(),
+        }
+    }
+}
+
+impl Debug for CliArgs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ordered: Vec<OrderedArg> = self.ordered.iter()
+            .map(|entry| {
+                let secret = entry.key.as_deref().and_then(|key| self.get_arg(key)).is_some_and(Arg::is_secret);
+                match secret {
+                    true => OrderedArg { key: entry.key.clone(), value: entry.value.as_ref().map(|_| REDACTED.to_string()) },
+                    false => entry.clone(),
+                }
+            })
+            .collect();
+        f.debug_struct("CliArgs")
+            .field("keys", &self.keys)
+            .field("args", &self.args)
+            .field("trailing", &self.trailing)
+            .field("ordered", &ordered)
+            .field("unknown", &self.unknown)
+            .field("all_or_none", &self.all_or_none)
+            .field("implications", &self.implications)
+            .field("given", &self.given)
+            .field("ignore_unknown", &self.ignore_unknown)
+            .field("extra_positionals", &self.extra_positionals)
+            .field("positionals", &self.positionals)
+            .field("remaining", &self.remaining)
+            .field("record_order", &self.record_order)
+            .field("multi_char_short", &self.multi_char_short)
+            .field("hide_env", &self.hide_env)
+            .field("expand_env", &self.expand_env)
+            .field("non_utf8", &self.non_utf8)
+            .field("warnings", &self.warnings)
+            .field("color_option", &self.color_option)
+            .field("exit_codes", &self.exit_codes)
+            .field("generate_completions", &self.generate_completions)
+            .field("stdin", redact(&self.stdin, self.stdin.is_some())) // may be meant for a secret
+            .field("schema_error", &self.schema_error)
+            .field("examples", &self.examples)
+            .field("meta", &self.meta)
+            .finish()
+    }
+}
+
+impl Debug for ArgValues {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let vals: Vec<&dyn Debug> = self.vals.iter().zip(&self.secret)
+            .map(|(vals, secret)| redact(vals, *secret))
+            .collect();
+        f.debug_struct("ArgValues")
+            .field("keys", &self.keys)
+            .field("vals", &vals)
+            .field("trailing", &self.trailing)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::{CliArgs, ParseError};
+
+    fn with_token() -> CliArgs {
+        let mut args = CliArgs::new();
+        args
+            .with("--token/-t=s? ::>hunter1 # API token")
+            .with("--pin=i?")
+            .with("--user=s?");
+        args.set_secret("--token", true).unwrap();
+        args.set_secret("--pin", true).unwrap();
+        args
+    }
+
+    #[test]
+    fn debug_output_is_redacted() {
+        let mut args = with_token();
+        let tokens: Vec<String> = ["--token", "hunter2", "--user", "alp"].map(String::from).into();
+        args.parse_interleaved(&tokens).unwrap();
+        assert_eq!(args.get_str("--token").unwrap(), Some("hunter2"));
+
+        let debug = format!("{:?}", args);
+        assert!(!debug.contains("hunter"), "{debug}");
+        assert!(debug.contains("vals: ***") && debug.contains("default_val: ***"));
+        assert!(debug.contains("\"alp\""));
+        let snapshot = format!("{:?}", args.values_snapshot());
+        assert!(!snapshot.contains("hunter2") && snapshot.contains("alp"), "{snapshot}");
+    }
+
+    #[test]
+    fn help_and_json_leave_secrets_out() {
+        let args = with_token();
+        assert!(args.help().contains("API token\n"));
+        assert!(!args.help().contains("hunter1"));
+        assert!(!args.markdown().contains("hunter1") && !args.man().contains("hunter1"));
+        assert!(args.schema_json().contains("\"default\": \"***\""));
+        assert!(args.schema_json_with(true).contains("\"default\": \"hunter1\""));
+    }
+
+    #[test]
+    fn errors_do_not_repeat_secrets() {
+        let mut args = with_token();
+        args.with_validator("--token", |val| if val.len() < 8 { Err("is too short".to_string()) } else { Ok(()) });
+        let e = args.parse("--token abc --pin 12x4").unwrap_err();
+        assert_eq!(e, ParseError::Multiple(vec![
+            ParseError::InvalidSecret { key: "--token".to_string(), reason: Some("is too short".to_string()) },
+            ParseError::InvalidSecret { key: "--pin".to_string(), reason: None },
+        ]));
+        let message = e.to_string();
+        assert!(!message.contains("abc") && !message.contains("12x4"), "{message}");
+    }
+
+    #[test]
+    fn secrets_expand_only_tilde() {
+        let Ok(home) = std::env::var(if cfg!(windows) { "USERPROFILE" } else { "HOME" }) else {
+            return; // nothing to expand to
+        };
+        let mut args = with_token();
+        args.with("--key=s?");
+        args.set_secret("--key", true).unwrap();
+        args.set_value_hint("--key", crate::args::ValueHint::File).unwrap();
+        args.expand_env(crate::args::UnknownVar::Error);
+        args.parse("--token pa$$word$X --user $$ --key ~/$X").unwrap();
+        assert_eq!(args.get_str("--token").unwrap(), Some("pa$$word$X"));
+        assert_eq!(args.get_str("--user").unwrap(), Some("$"));
+        assert_eq!(args.get_string("--key").unwrap(), Some(format!("{home}/$X")));
+    }
+}
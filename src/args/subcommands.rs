@@ -0,0 +1,651 @@
+//! A tree of commands, each level with its own [`CliArgs`].
+
+use std::fmt::Write;
+use std::env;
+
+use super::{rank_candidates, CliArgs, ParseError, ParseEvent};
+
+/// A level's own tokens and the named subcommand with the tokens after its name.
+type Split<'a> = (&'a [String], Option<(usize, &'a [String])>);
+
+/// A command with its args and named subcommands, e.g. `mytool [ARGS] fetch [ARGS]`.
+///
+/// The first bare token that names a subcommand, and that no key of the
+/// current level takes as its value, ends the current level. Everything
+/// after it is parsed by the subcommand.
+#[derive(Debug, Clone, Default)]
+pub struct CliSubcommands {
+    args: CliArgs,
+    commands: Vec<(String, CliSubcommands)>,
+    selected: Option<usize>,
+    version: Option<String>,
+    version_long: Option<String>,
+    default_env: Option<String>,
+    aliases: Vec<String>,
+    abbreviations: bool,
+    no_help_command: bool,
+    fallback: bool,
+}
+
+impl CliSubcommands {
+    pub fn new(args: CliArgs) -> Self {
+        Self { args, ..Default::default() }
+    }
+
+    pub fn command(&mut self, name: &str, command: CliSubcommands) -> &mut Self {
+        self.commands.push((name.to_string(), command));
+        self
+    }
+
+    /// Another name the parent selects this command by, e.g. `rm` for
+    /// `remove`. A name of the parent's commands wins over an alias.
+    pub fn alias(&mut self, alias: &str) -> &mut Self {
+        self.aliases.push(alias.to_string());
+        self
+    }
+
+    /// Selects a subcommand by an unambiguous prefix of its name or of an
+    /// alias, `stat` for `status`, after exact names and aliases. A prefix
+    /// of several fails the parse with [`ParseError::AmbiguousCommand`].
+    pub fn allow_abbreviations(&mut self, allow: bool) -> &mut Self {
+        self.abbreviations = allow;
+        self
+    }
+
+    /// Leaves out the `help` subcommand, which otherwise every level with
+    /// subcommands has unless it registers a `help` command itself.
+    pub fn disable_help_command(&mut self, disable: bool) -> &mut Self {
+        self.no_help_command = disable;
+        self
+    }
+
+    /// Lets this level run without a subcommand: only the first value
+    /// without a key can name one, and if it names none, it and every later
+    /// token are this level's, e.g. for its positionals. Both `mytool --fast`
+    /// and `mytool build --fast` then parse, the second selecting `build`.
+    pub fn fall_back_to_args(&mut self, fallback: bool) -> &mut Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// The version printed for `--version` or `-V`. Subcommands without a
+    /// version of their own report their parent's.
+    pub fn version(&mut self, version: &str) -> &mut Self {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    /// A longer version, e.g. with the git hash and build date, printed for
+    /// `-VV` or `--version --verbose`. Inherited like [`CliSubcommands::version`].
+    pub fn version_long(&mut self, version_long: &str) -> &mut Self {
+        self.version_long = Some(version_long.to_string());
+        self
+    }
+
+    /// Selects the subcommand named by the environment variable `var` when
+    /// the command line names none, e.g. `MYTOOL_COMMAND=fetch` for wrapper
+    /// scripts. A subcommand on the command line takes precedence and an
+    /// empty variable is ignored. A name that is no subcommand fails the
+    /// parse with [`ParseError::UnknownCommand`].
+    pub fn default_from_env(&mut self, var: &str) -> &mut Self {
+        self.default_env = Some(var.to_string());
+        self
+    }
+
+    pub fn args(&self) -> &CliArgs {
+        &self.args
+    }
+
+    pub fn args_mut(&mut self) -> &mut CliArgs {
+        &mut self.args
+    }
+
+    /// The subcommand given on the command line or by
+    /// [`CliSubcommands::default_from_env`], if any.
+    pub fn selected(&self) -> Option<(&str, &CliSubcommands)> {
+        self.selected.map(|ind| {
+            let (name, command) = &self.commands[ind];
+            (name.as_str(), command)
+        })
+    }
+
+    /// This level's [`CliArgs::help`] followed by its subcommands, each
+    /// with its aliases in parentheses and its about line.
+    pub fn help(&self) -> String {
+        self.commands_help(self.args.help())
+    }
+
+    /// The help of a subcommand, with its path from the root as the name in
+    /// its usage line, e.g. `mytool remote add`.
+    fn help_at(&self, path: &[String]) -> String {
+        if path.len() <= 1 {
+            return self.help();
+        }
+        let mut args = self.args.clone();
+        args.bin_name(&path.join(" "));
+        self.commands_help(args.help())
+    }
+
+    fn commands_help(&self, mut out: String) -> String {
+        if self.commands.is_empty() {
+            return out;
+        }
+        let mut labels: Vec<(String, Option<&str>)> = self.commands.iter()
+            .map(|(name, command)| match command.aliases.is_empty() {
+                true => (name.clone(), command.args.meta_about()),
+                false => (format!("{} ({})", name, command.aliases.join(", ")), command.args.meta_about()),
+            })
+            .collect();
+        if self.has_help_command() {
+            labels.push((HELP_COMMAND.to_string(), Some("Print this help or the help of the given subcommand")));
+        }
+        let width = labels.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+        out.push_str("\nCOMMANDS:\n");
+        for (label, about) in labels {
+            let _ = match about {
+                Some(about) => writeln!(out, "    {label:width$}    {about}"),
+                None => writeln!(out, "    {label}"),
+            };
+        }
+        out
+    }
+
+    /// The completion candidates for the last of `words` at the level the
+    /// words before it select, see [`CliArgs::complete`]. A word that is no
+    /// key also completes to subcommand names and aliases.
+    pub fn complete(&self, words: &[String]) -> Vec<String> {
+        let before = words.split_last().map_or(words, |(_, before)| before);
+        if let Ok((_, Some((ind, rest)))) = self.split(before) {
+            return self.commands[ind].1.complete(&words[words.len() - rest.len() - 1..]);
+        }
+        let mut candidates = self.args.complete(words);
+        let cur = words.last().map_or("", String::as_str);
+        let pending = before.last()
+            .and_then(|prev| self.args.keys.get(prev.as_str()))
+            .is_some_and(|ind| !self.args.args[*ind].is_bool());
+        if !pending && !cur.starts_with('-') {
+            candidates.extend(self.command_names().filter(|name| name.starts_with(cur)).map(str::to_string));
+        }
+        candidates
+    }
+
+    /// Parses the args of every level down to the selected subcommand.
+    ///
+    /// A version flag at any level that has a version, its own or inherited,
+    /// wins over every other error and is returned as [`ParseError::Version`]
+    /// holding e.g. `mytool fetch 1.2.0`. Version flags are `--version`, `-V`
+    /// and `-VV`, unless the level registers those keys itself.
+    ///
+    /// Asking for help wins the same way and is returned as [`ParseError::Help`]:
+    /// `--help` or `-h` at any level, unless the level registers the key,
+    /// for that level's help, and `help [COMMAND]..` at a level with
+    /// subcommands for the help of the level the path names. A path naming
+    /// no subcommand is [`ParseError::NoSuchCommand`].
+    pub fn parse_from(&mut self, tokens: &[String]) -> Result<(), ParseError> {
+        let mut path = vec![self.args.resolved_name()];
+        if let Some(version) = self.find_version(tokens, &mut path, None, None) {
+            return Err(ParseError::Version(version));
+        }
+        if let Some(help) = self.find_help(tokens, &mut path)? {
+            return Err(ParseError::Help(help));
+        }
+        self.parse_level(tokens)
+    }
+
+    /// Parses the process arguments, without the program path.
+    pub fn parse_cmd(&mut self) -> Result<(), ParseError> {
+        let tokens: Vec<String> = env::args().skip(1).collect();
+        self.parse_from(&tokens)
+    }
+
+    /// Parses the process arguments like [`CliSubcommands::parse_cmd`]. A
+    /// requested version or help is printed to stdout and exits with code 0,
+    /// an error is printed to stderr and exits with the code of the root's
+    /// [`CliArgs::exit_code`].
+    pub fn parse_or_exit(&mut self) {
+        if let Err(e) = self.parse_cmd() {
+            self.args.exit_with(e);
+        }
+    }
+
+    fn parse_level(&mut self, tokens: &[String]) -> Result<(), ParseError> {
+        let (own, sub) = self.split(tokens)?;
+        let sub = match sub {
+            Some(sub) => Some(sub),
+            None => self.env_command()?.map(|ind| (ind, &[] as &[String])),
+        };
+        self.selected = sub.map(|(ind, _)| ind);
+        self.args.parse_from(own).map_err(|e| self.unknown_command(e))?;
+        if let Some((ind, rest)) = sub {
+            self.commands[ind].1.parse_level(rest)?;
+        }
+        Ok(())
+    }
+
+    /// The subcommand named by the [`CliSubcommands::default_from_env`] variable.
+    fn env_command(&self) -> Result<Option<usize>, ParseError> {
+        let Some(name) = self.default_env.as_deref().and_then(|var| env::var(var).ok()).filter(|name| !name.is_empty()) else {
+            return Ok(None);
+        };
+        match self.commands.iter().position(|(command, _)| *command == name) {
+            Some(ind) => Ok(Some(ind)),
+            None => Err(ParseError::UnknownCommand { var: self.default_env.clone().unwrap_or_default(), name }),
+        }
+    }
+
+    /// Splits `tokens` into this level's tokens and, if a subcommand is
+    /// named, its index and the tokens after its name.
+    fn split<'a>(&self, tokens: &'a [String]) -> Result<Split<'a>, ParseError> {
+        let mut events = self.args.parse_events(tokens);
+        while let Some(event) = events.next() {
+            let ParseEvent::Positional { value } = event else {
+                continue;
+            };
+            if let Some(ind) = self.find_command(value)? {
+                let (own, rest) = tokens.split_at(tokens.len() - events.remaining().len());
+                return Ok((&own[..own.len() - 1], Some((ind, rest))));
+            }
+            if self.fallback {
+                break;
+            }
+        }
+        Ok((tokens, None))
+    }
+
+    /// The subcommand named `name` exactly, by an alias or, with
+    /// [`CliSubcommands::allow_abbreviations`], by a prefix.
+    fn find_command(&self, name: &str) -> Result<Option<usize>, ParseError> {
+        if let Some(ind) = self.commands.iter().position(|(command, _)| command == name) {
+            return Ok(Some(ind));
+        }
+        if let Some(ind) = self.commands.iter().position(|(_, command)| command.aliases.iter().any(|alias| alias == name)) {
+            return Ok(Some(ind));
+        }
+        if !self.abbreviations {
+            return Ok(None);
+        }
+        let found: Vec<usize> = self.commands.iter().enumerate()
+            .filter(|(_, (command, sub))| [command].into_iter().chain(&sub.aliases).any(|n| n.starts_with(name)))
+            .map(|(ind, _)| ind)
+            .collect();
+        match found[..] {
+            [] => Ok(None),
+            [ind] => Ok(Some(ind)),
+            _ => Err(ParseError::AmbiguousCommand {
+                name: name.to_string(),
+                candidates: found.iter().map(|ind| self.commands[*ind].0.clone()).collect(),
+            }),
+        }
+    }
+
+    /// A stray value at a level with subcommands as [`ParseError::NoSuchCommand`],
+    /// suggesting the closest name or alias, see [`rank_candidates`].
+    fn unknown_command(&self, e: ParseError) -> ParseError {
+        let ParseError::UnexpectedValue(name) = e else {
+            return e;
+        };
+        if self.commands.is_empty() || self.fallback {
+            return ParseError::UnexpectedValue(name);
+        }
+        let suggestion = rank_candidates(&name, self.command_names()).first().map(|candidate| candidate.to_string());
+        let mut commands: Vec<String> = self.commands.iter().map(|(command, _)| command.clone()).collect();
+        if self.has_help_command() {
+            commands.push(HELP_COMMAND.to_string());
+        }
+        ParseError::NoSuchCommand { name, suggestion, commands }
+    }
+
+    /// The version text for the deepest level given a version flag, if that
+    /// level has a version.
+    fn find_version<'s>(
+        &'s self,
+        tokens: &[String],
+        path: &mut Vec<String>,
+        inherited: Option<&'s str>,
+        inherited_long: Option<&'s str>,
+    ) -> Option<String> {
+        let version = self.version.as_deref().or(inherited);
+        let version_long = self.version_long.as_deref().or(inherited_long);
+        let (own, sub) = self.split(tokens).unwrap_or((tokens, None)); // reported by the parse
+        if let Some((ind, rest)) = sub {
+            let (name, command) = &self.commands[ind];
+            path.push(name.clone());
+            if let Some(found) = command.find_version(rest, path, version, version_long) {
+                return Some(found);
+            }
+            path.pop();
+        }
+
+        let long = self.version_flag(own)?;
+        let text = if long { version_long.or(version)? } else { version? };
+        Some(format!("{} {}", path.join(" "), text))
+    }
+
+    /// The help asked for at the deepest level given `--help`, `-h` or the
+    /// `help` subcommand.
+    fn find_help(&self, tokens: &[String], path: &mut Vec<String>) -> Result<Option<String>, ParseError> {
+        if let Some(topic) = self.help_topic(tokens) {
+            let mut level = self;
+            for name in topic {
+                let ind = level.find_command(name)?.ok_or_else(|| level.unknown_command(ParseError::UnexpectedValue(name.clone())))?;
+                path.push(level.commands[ind].0.clone());
+                level = &level.commands[ind].1;
+            }
+            return Ok(Some(level.help_at(path)));
+        }
+        let (own, sub) = self.split(tokens).unwrap_or((tokens, None)); // reported by the parse
+        if let Some((ind, rest)) = sub {
+            let (name, command) = &self.commands[ind];
+            path.push(name.clone());
+            if let Some(found) = command.find_help(rest, path)? {
+                return Ok(Some(found));
+            }
+            path.pop();
+        }
+
+        let asked = self.args.parse_events(own).any(|event| matches!(
+            event,
+            ParseEvent::Error { token: "--help" | "-h", error: ParseError::UnknownKey(_) }
+        ));
+        Ok(asked.then(|| self.help_at(path)))
+    }
+
+    /// The command path after `help`, if it is the first value without a key.
+    fn help_topic<'a>(&self, tokens: &'a [String]) -> Option<&'a [String]> {
+        if !self.has_help_command() {
+            return None;
+        }
+        let mut events = self.args.parse_events(tokens);
+        while let Some(event) = events.next() {
+            if let ParseEvent::Positional { value } = event {
+                return (value == HELP_COMMAND).then(|| &tokens[tokens.len() - events.remaining().len()..]);
+            }
+        }
+        None
+    }
+
+    /// The names and aliases of the subcommands, then `help` if there, the
+    /// pool [`ParseError::NoSuchCommand`] suggests from.
+    pub fn command_names(&self) -> impl Iterator<Item = &str> {
+        self.commands.iter()
+            .flat_map(|(name, command)| [name].into_iter().chain(&command.aliases))
+            .map(String::as_str)
+            .chain(self.has_help_command().then_some(HELP_COMMAND))
+    }
+
+    fn has_help_command(&self) -> bool {
+        !self.no_help_command && !self.commands.is_empty() && self.commands.iter().all(|(name, _)| name != HELP_COMMAND)
+    }
+
+    /// Whether a version flag is among this level's tokens, and whether the
+    /// long version is asked for.
+    fn version_flag(&self, tokens: &[String]) -> Option<bool> {
+        let unclaimed = |key: &str| !self.args.keys.contains_key(key);
+        let own = tokens.iter().take_while(|t| *t != "--");
+        let mut found = None;
+        let mut verbose = false;
+        for token in own {
+            match token.as_str() {
+                "-VV" if unclaimed("-VV") => found = Some(true),
+                "--version" | "-V" if unclaimed(token) => found = found.or(Some(false)),
+                "--verbose" => verbose = true,
+                _ => {},
+            }
+        }
+        found.map(|long| long || verbose)
+    }
+}
+
+/// The subcommand printing help, see [`CliSubcommands::disable_help_command`].
+const HELP_COMMAND: &str = "help";
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::CliSubcommands;
+    use crate::args::{CliArgs, ParseError};
+
+    fn tokens(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn mytool() -> CliSubcommands {
+        let mut root = CliArgs::new();
+        root.name("mytool").with("--config/-c=s");
+
+        let mut fetch = CliArgs::new();
+        fetch.with("--remote=s?").with("--verbose=b?");
+        let mut push = CliSubcommands::new(CliArgs::new());
+        push.version("2.0.0");
+
+        let mut tool = CliSubcommands::new(root);
+        tool
+            .version("1.2.0")
+            .version_long("1.2.0 (abc1234 2026-10-16)")
+            .command("fetch", CliSubcommands::new(fetch))
+            .command("push", push);
+        tool
+    }
+
+    fn version(tool: &mut CliSubcommands, line: &str) -> String {
+        match tool.parse_from(&tokens(line)) {
+            Err(ParseError::Version(version)) => version,
+            other => panic!("expected a version for `{}`, got {:?}", line, other),
+        }
+    }
+
+    #[test]
+    fn selects_subcommand() {
+        let mut tool = mytool();
+        tool.parse_from(&tokens("-c fetch fetch --remote origin")).unwrap();
+        assert_eq!(tool.args().get_str("--config").unwrap(), Some("fetch"));
+        let (name, fetch) = tool.selected().unwrap();
+        assert_eq!(name, "fetch");
+        assert_eq!(fetch.args().get_str("--remote").unwrap(), Some("origin"));
+        assert!(fetch.selected().is_none());
+    }
+
+    #[test]
+    fn falls_back_to_own_args() {
+        let mut root = CliArgs::new();
+        root.with("--fast=b?").with_positional("target=s?").allow_extra_positionals(true);
+        let mut tool = CliSubcommands::new(root);
+        tool.fall_back_to_args(true).command("build", CliSubcommands::new(CliArgs::new()));
+
+        tool.parse_from(&tokens("--fast")).unwrap();
+        assert!(tool.selected().is_none());
+        assert_eq!(tool.args().get_bool("--fast").unwrap(), Some(true));
+
+        tool.args_mut().reset();
+        tool.parse_from(&tokens("--fast build")).unwrap();
+        assert_eq!(tool.selected().map(|(name, _)| name), Some("build"));
+
+        tool.args_mut().reset();
+        tool.parse_from(&tokens("site build")).unwrap();
+        assert!(tool.selected().is_none());
+        assert_eq!(tool.args().get_positional::<String>("target").unwrap(), "site");
+        assert_eq!(tool.args().remaining_positionals(), ["build"]);
+
+        let mut tool = CliSubcommands::new(CliArgs::new());
+        tool.fall_back_to_args(true).command("build", CliSubcommands::new(CliArgs::new()));
+        assert_eq!(tool.parse_from(&tokens("biuld")), Err(ParseError::UnexpectedValue("biuld".to_string())));
+    }
+
+    #[test]
+    fn subcommand_from_env() {
+        let var = format!("CLITRS_TEST_COMMAND_{}", std::process::id());
+        let mut tool = mytool();
+        tool.default_from_env(&var);
+        env::set_var(&var, "fetch");
+        tool.parse_from(&tokens("-c x")).unwrap();
+        assert_eq!(tool.selected().map(|(name, _)| name), Some("fetch"));
+
+        tool.parse_from(&tokens("-c x push")).unwrap();
+        assert_eq!(tool.selected().map(|(name, _)| name), Some("push"));
+
+        env::set_var(&var, "pull");
+        let e = tool.parse_from(&tokens("-c x")).unwrap_err();
+        assert_eq!(e, ParseError::UnknownCommand { var: var.clone(), name: "pull".to_string() });
+
+        env::set_var(&var, "");
+        tool.parse_from(&tokens("-c x")).unwrap();
+        assert!(tool.selected().is_none());
+        env::remove_var(&var);
+    }
+
+    #[test]
+    fn version_is_inherited_and_overridden() {
+        let mut tool = mytool();
+        assert_eq!(version(&mut tool, "--version"), "mytool 1.2.0");
+        assert_eq!(version(&mut tool, "fetch --version"), "mytool fetch 1.2.0");
+        assert_eq!(version(&mut tool, "-c x push -V"), "mytool push 2.0.0");
+        assert_eq!(version(&mut tool, "fetch -VV"), "mytool fetch 1.2.0 (abc1234 2026-10-16)");
+        assert_eq!(version(&mut tool, "-V --verbose"), "mytool 1.2.0 (abc1234 2026-10-16)");
+        assert_eq!(version(&mut tool, "push -VV"), "mytool push 1.2.0 (abc1234 2026-10-16)");
+    }
+
+    #[test]
+    fn version_needs_a_version() {
+        let mut tool = CliSubcommands::new(CliArgs::new());
+        assert_eq!(tool.parse_from(&tokens("--version")), Err(ParseError::UnknownKey("--version".to_string())));
+
+        let mut args = CliArgs::new();
+        args.with("-V=b?");
+        let mut tool = CliSubcommands::new(args);
+        tool.version("1.0.0");
+        tool.parse_from(&tokens("-V")).unwrap();
+        assert_eq!(tool.args().get_bool("-V").unwrap(), Some(true));
+    }
+
+    fn git() -> CliSubcommands {
+        let mut status = CliArgs::new();
+        status.about("Show the working tree status");
+        let mut remove = CliSubcommands::new(CliArgs::new());
+        remove.alias("rm");
+        let mut stash = CliArgs::new();
+        stash.with("--message/-m=s?");
+
+        let mut root = CliArgs::new();
+        root.with("--config/-c=s?");
+        let mut git = CliSubcommands::new(root);
+        git
+            .command("status", CliSubcommands::new(status))
+            .command("remove", remove)
+            .command("stash", CliSubcommands::new(stash));
+        git
+    }
+
+    #[test]
+    fn aliases_and_abbreviations() {
+        let mut git = git();
+        let selected = |git: &CliSubcommands| git.selected().map(|(name, _)| name.to_string());
+        git.parse_from(&tokens("rm")).unwrap();
+        assert_eq!(selected(&git).as_deref(), Some("remove"));
+        let e = git.parse_from(&tokens("stat")).unwrap_err();
+        assert!(matches!(e, ParseError::NoSuchCommand { suggestion: Some(s), .. } if s == "status"));
+
+        git.allow_abbreviations(true);
+        git.parse_from(&tokens("stat")).unwrap();
+        assert_eq!(selected(&git).as_deref(), Some("status"));
+        git.parse_from(&tokens("-c x r")).unwrap();
+        assert_eq!(selected(&git).as_deref(), Some("remove"));
+        let e = git.parse_from(&tokens("st")).unwrap_err();
+        assert_eq!(e, ParseError::AmbiguousCommand { name: "st".to_string(), candidates: vec!["status".to_string(), "stash".to_string()] });
+        assert_eq!(e.to_string(), "`st` could be any of `status`, `stash`");
+    }
+
+    #[test]
+    fn unknown_command_suggestions() {
+        let mut git = git();
+        let e = git.parse_from(&tokens("-c x stauts")).unwrap_err();
+        assert_eq!(e.to_string(), "unknown subcommand `stauts`, did you mean `status`?");
+        let e = git.parse_from(&tokens("mr")).unwrap_err();
+        assert!(matches!(e, ParseError::NoSuchCommand { suggestion: Some(s), .. } if s == "rm"));
+        assert_eq!(git.parse_from(&tokens("fetch")).unwrap_err().to_string(), "unknown subcommand `fetch`, expected one of `status`, `remove`, `stash`, `help`");
+        // a short unrelated name is not close to the short alias `rm`
+        assert_eq!(git.parse_from(&tokens("x")).unwrap_err().to_string(), "unknown subcommand `x`, expected one of `status`, `remove`, `stash`, `help`");
+    }
+
+    #[test]
+    fn help_and_completions_list_aliases() {
+        let git = git();
+        let help = git.help();
+        assert!(help.ends_with(concat!(
+            "COMMANDS:\n",
+            "    status         Show the working tree status\n",
+            "    remove (rm)\n",
+            "    stash\n",
+            "    help           Print this help or the help of the given subcommand\n",
+        )), "{help}");
+
+        assert_eq!(git.complete(&tokens("r")), ["remove", "rm"]);
+        assert_eq!(git.complete(&tokens("-c x s")), ["status", "stash"]);
+        assert_eq!(git.complete(&tokens("h")), ["help"]);
+        let mut words = tokens("rm");
+        words.push(String::new());
+        assert!(git.complete(&words).is_empty());
+        assert_eq!(git.complete(&tokens("stash --me")), ["--message"]);
+        let mut words = tokens("-c");
+        words.push(String::new());
+        assert!(git.complete(&words).is_empty());
+        assert!(git.complete(&tokens("x")).is_empty() && git.complete(&tokens("m")).is_empty());
+    }
+
+    fn help(tool: &mut CliSubcommands, line: &str) -> String {
+        match tool.parse_from(&tokens(line)) {
+            Err(ParseError::Help(help)) => help,
+            other => panic!("expected help for `{}`, got {:?}", line, other),
+        }
+    }
+
+    #[test]
+    fn help_command_and_flags() {
+        let mut add = CliArgs::new();
+        add.about("Add a remote").with("--fetch/-f=b?");
+        let mut remote = CliSubcommands::new(CliArgs::new());
+        remote.command("add", CliSubcommands::new(add));
+        let mut git = git();
+        git.args_mut().name("git").bin_name("git");
+        git.command("remote", remote);
+
+        let top = help(&mut git, "help");
+        assert_eq!(top, git.help());
+        assert_eq!(top.matches("    help ").count(), 1);
+        assert_eq!(help(&mut git, "-c x --help"), top);
+        assert_eq!(help(&mut git, "stash -m x -h"), help(&mut git, "help stash"));
+        assert!(help(&mut git, "help stash").contains("USAGE:\n    git stash [OPTIONS]"));
+        assert_eq!(help(&mut git, "help rm"), help(&mut git, "remove --help"));
+
+        let add_help = help(&mut git, "remote help add");
+        assert_eq!(add_help, help(&mut git, "help remote add"));
+        assert_eq!(add_help, help(&mut git, "remote add -h"));
+        assert!(add_help.starts_with("Add a remote\n") && add_help.contains("git remote add [OPTIONS]"));
+        assert!(help(&mut git, "remote help").contains("COMMANDS:\n    add     Add a remote\n    help    "));
+
+        assert_eq!(git.parse_from(&tokens("help stauts")).unwrap_err().to_string(), "unknown subcommand `stauts`, did you mean `status`?");
+        assert_eq!(git.parse_from(&tokens("help remote ad")).unwrap_err().to_string(), "unknown subcommand `ad`, did you mean `add`?");
+        assert_eq!(
+            git.parse_from(&tokens("help xyz")).unwrap_err().to_string(),
+            "unknown subcommand `xyz`, expected one of `status`, `remove`, `stash`, `remote`, `help`",
+        );
+        assert_eq!(git.parse_from(&tokens("hlep")).unwrap_err().to_string(), "unknown subcommand `hlep`, did you mean `help`?");
+
+        git.disable_help_command(true);
+        assert!(!git.help().contains("    help "));
+        assert_eq!(
+            git.parse_from(&tokens("help")),
+            Err(ParseError::NoSuchCommand {
+                name: "help".to_string(),
+                suggestion: None,
+                commands: ["status", "remove", "stash", "remote"].map(String::from).to_vec(),
+            }),
+        );
+        assert!(help(&mut git, "-h").contains("COMMANDS:"));
+
+        let mut git = self::git();
+        git.args_mut().set_allow_hyphen_values("-c", true).unwrap();
+        git.parse_from(&tokens("-c --help status")).unwrap();
+        assert_eq!(git.args().get_str("--config").unwrap(), Some("--help"));
+    }
+}
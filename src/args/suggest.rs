@@ -0,0 +1,75 @@
+//! Did-you-mean suggestions for names that match nothing registered.
+
+/// Largest [`edit_distance`] of a suggested name, reached by names of 6
+/// characters and more.
+const MAX_DISTANCE: usize = 2;
+
+/// The largest [`edit_distance`] at which `candidate` is suggested for
+/// `name`: one edit per 3 characters of the longer one, at least 1, so a
+/// short name is not close to every other short name.
+fn max_distance(name: &str, candidate: &str) -> usize {
+    (name.chars().count().max(candidate.chars().count()) / 3).clamp(1, MAX_DISTANCE)
+}
+
+/// The `candidates` close enough to `name` to suggest, closest first and
+/// in the given order among equally close ones. Behind the suggestions of
+/// [`ParseError::NoSuchCommand`](super::ParseError::NoSuchCommand), and
+/// public for applications with names of their own, like plugins:
+///
+/// ```
+/// # use clitrs::args::{rank_candidates, CliArgs, CliSubcommands};
+/// let mut tool = CliSubcommands::new(CliArgs::new());
+/// tool.command("install", CliSubcommands::new(CliArgs::new()));
+/// let plugins = ["instant"];
+/// let ranked = rank_candidates("instal", tool.command_names().chain(plugins));
+/// assert_eq!(ranked, ["install", "instant"]);
+/// ```
+pub fn rank_candidates<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut ranked: Vec<(usize, &str)> = candidates.into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= max_distance(name, candidate))
+        .collect();
+    ranked.sort_by_key(|(distance, _)| *distance); // stable, keeps the order of ties
+    ranked.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+/// The number of single character insertions, deletions, substitutions
+/// and swaps of two adjacent characters turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut before: Vec<usize> = vec![0; b.len() + 1]; // the row of `a[..i - 1]`, for swaps
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substituted = prev[j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            row[j] = substituted.min(prev[j] + 1).min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut prev, row);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, rank_candidates};
+
+    #[test]
+    fn distances_and_ranking() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("instal", "install"), 1);
+        assert_eq!(edit_distance("stauts", "status"), 1);
+        assert_eq!(edit_distance("mr", "rm"), 1);
+        assert_eq!(edit_distance("abc", "ca"), 3);
+        assert_eq!(edit_distance("çay", "cay"), 1);
+        assert_eq!(rank_candidates("stat", ["stash", "status", "start", "fetch"]), ["start", "status"]);
+        assert!(rank_candidates("xyz", ["install"]).is_empty());
+        // a short name is only close to a name one edit away
+        assert!(rank_candidates("x", ["ls", "rm", "cp"]).is_empty());
+        assert!(rank_candidates("zz", ["ls", "rm", "cp"]).is_empty());
+        assert_eq!(rank_candidates("l", ["ls", "rm"]), ["ls"]);
+    }
+}
@@ -0,0 +1,116 @@
+//! Splitting of a line into words the way a POSIX shell would, behind
+//! [`CliArgs::parse_shell`] and usable without a schema.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::mem;
+
+use super::ParseError;
+#[cfg(doc)]
+use super::CliArgs;
+
+/// A line that [`tokenize`] could not split.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenizeError {
+    /// A quote, `'` or `"`, is never closed.
+    UnclosedQuote(char),
+}
+
+impl Display for TokenizeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenizeError::UnclosedQuote(quote) => write!(f, "unclosed quote `{}`", quote),
+        }
+    }
+}
+
+impl Error for TokenizeError {}
+
+impl From<TokenizeError> for ParseError {
+    fn from(e: TokenizeError) -> Self {
+        match e {
+            TokenizeError::UnclosedQuote(quote) => ParseError::UnclosedQuote(quote),
+        }
+    }
+}
+
+/// Splits `line` into words like a POSIX shell, the splitting done by
+/// [`CliArgs::parse_shell`]:
+///
+/// ```
+/// # use clitrs::args::{tokenize, TokenizeError};
+/// assert_eq!(tokenize(r#"git commit -m "it's done" a\ b"#).unwrap(), ["git", "commit", "-m", "it's done", "a b"]);
+/// assert_eq!(tokenize("echo 'a b"), Err(TokenizeError::UnclosedQuote('\'')));
+/// ```
+///
+/// Single quotes keep everything up to the next single quote literally.
+/// Double quotes keep whitespace, inside them a backslash only escapes
+/// `"`, `\`, `$` and `` ` ``. Elsewhere a backslash escapes any character.
+/// No expansion of variables, globs or `~` is done.
+pub fn tokenize(line: &str) -> Result<Vec<String>, TokenizeError> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false; // `''` is an empty word, not none
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(mem::take(&mut word));
+                    in_word = false;
+                }
+                continue;
+            },
+            '\\' => word.push(chars.next().unwrap_or('\\')),
+            '\'' => loop {
+                match chars.next().ok_or(TokenizeError::UnclosedQuote('\''))? {
+                    '\'' => break,
+                    c => word.push(c),
+                }
+            },
+            '"' => loop {
+                match chars.next().ok_or(TokenizeError::UnclosedQuote('"'))? {
+                    '"' => break,
+                    '\\' => match chars.next().ok_or(TokenizeError::UnclosedQuote('"'))? {
+                        c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                        c => {
+                            word.push('\\');
+                            word.push(c);
+                        },
+                    },
+                    c => word.push(c),
+                }
+            },
+            c => word.push(c),
+        }
+        in_word = true;
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, TokenizeError};
+
+    #[test]
+    fn shell_word_splitting() {
+        let split = |line: &str| tokenize(line).unwrap();
+        assert_eq!(split("  -n   alp  "), ["-n", "alp"]);
+        assert_eq!(split("'a b' 'it''s' '\\n'"), ["a b", "its", "\\n"]);
+        assert_eq!(split("a\\ b \\'c\\\\"), ["a b", "'c\\"]);
+        assert_eq!(split(r#""a \"b\" \$x \n" x"'y z'"\""#), ["a \"b\" $x \\n", "x'y z'\""]);
+        assert_eq!(split("'' \"\" --name="), ["", "", "--name="]);
+    }
+
+    #[test]
+    fn escaped_and_unclosed_quotes() {
+        assert_eq!(tokenize(r#"\"a b\" \'c"#).unwrap(), ["\"a", "b\"", "'c"]);
+        assert_eq!(tokenize(r#"'say "hi"' "it's""#).unwrap(), ["say \"hi\"", "it's"]);
+        assert_eq!(tokenize("'a b"), Err(TokenizeError::UnclosedQuote('\'')));
+        assert_eq!(tokenize("\"a\\\""), Err(TokenizeError::UnclosedQuote('"')));
+        assert_eq!(tokenize("ok 'it\\'s'").unwrap_err().to_string(), "unclosed quote `'`");
+    }
+}
@@ -0,0 +1,176 @@
+//! Canonicalization of raw values before they are checked and converted,
+//! and of string values once parsed.
+
+use std::borrow::Cow;
+use std::fmt::{self, Debug, Formatter};
+use std::mem;
+use std::sync::Arc;
+
+use super::{Arg, ArgError, ArgSettings, CliArgs};
+
+type TransformFn = dyn Fn(String) -> String + Send + Sync;
+
+/// A rewrite of an arg's raw values, see [`CliArgs::add_transform`].
+#[derive(Clone)]
+pub enum Transform {
+    /// Strips leading and trailing whitespace.
+    Trim,
+    Lowercase,
+    Uppercase,
+    /// See [`Transform::custom`].
+    Custom(Arc<TransformFn>),
+}
+
+impl Transform {
+    /// A transform running `f`, e.g. to strip a `v` prefix off versions.
+    pub fn custom(f: impl Fn(String) -> String + Send + Sync + 'static) -> Self {
+        Transform::Custom(Arc::new(f))
+    }
+
+    fn apply(&self, val: String) -> String {
+        match self {
+            Transform::Trim => val.trim().to_string(),
+            Transform::Lowercase => val.to_lowercase(),
+            Transform::Uppercase => val.to_uppercase(),
+            Transform::Custom(f) => f(val),
+        }
+    }
+}
+
+impl Debug for Transform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Transform::Trim => f.write_str("Trim"),
+            Transform::Lowercase => f.write_str("Lowercase"),
+            Transform::Uppercase => f.write_str("Uppercase"),
+            Transform::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl CliArgs {
+    /// Appends `transform` to the ones rewriting the arg's values, which run
+    /// in the order they were added. Values given on the command line, read
+    /// from the environment or passed to [`CliArgs::apply_defaults`] after
+    /// this are transformed before validators, choices and the conversion
+    /// to the arg's type see them. [`CliArgs::ordered`] keeps them as written.
+    pub fn add_transform(&mut self, key: &str, transform: Transform) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        match &mut self.args[ind] {
+            Arg::Bool { settings, .. } => settings.transforms.push(transform),
+            Arg::Int { settings, .. } => settings.transforms.push(transform),
+            Arg::String { settings, .. } => settings.transforms.push(transform),
+        }
+        Ok(())
+    }
+
+    /// Rewrites the final values of a string arg once a parse has checked
+    /// them, defaults included, e.g. to canonicalize a path. Unlike
+    /// [`CliArgs::add_transform`] validators and choices see the values as
+    /// given, and the getters return them rewritten. Replaces an earlier one.
+    pub fn set_post_transform(&mut self, key: &str, transform: Transform) -> Result<(), ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        match &mut self.args[ind] {
+            Arg::String { settings, .. } => settings.post_transform = Some(transform),
+            _ => return Err(ArgError::WrongType),
+        }
+        Ok(())
+    }
+}
+
+impl Arg {
+    /// Runs the post-parse transform of a string arg on all its values.
+    pub(super) fn post_transform(&mut self) {
+        if let Arg::String { vals, settings: ArgSettings { post_transform: Some(transform), .. } } = self {
+            for val in vals.iter_mut() {
+                *val = transform.apply(mem::take(val));
+            }
+        }
+    }
+
+    /// `val` rewritten by the arg's transforms, borrowed if it has none.
+    pub(super) fn transformed<'v>(&self, val: &'v str) -> Cow<'v, str> {
+        let transforms = match self {
+            Arg::Bool { settings, .. } => &settings.transforms,
+            Arg::Int { settings, .. } => &settings.transforms,
+            Arg::String { settings, .. } => &settings.transforms,
+        };
+        if transforms.is_empty() {
+            return Cow::Borrowed(val);
+        }
+        Cow::Owned(transforms.iter().fold(val.to_string(), |val, transform| transform.apply(val)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::Transform;
+    use crate::args::{ArgError, CliArgs, OrderedArg};
+
+    #[test]
+    fn transforms_in_order() {
+        let mut args = CliArgs::new();
+        args
+            .with("--env=s")
+            .with("--tag=s?")
+            .with("--count=i?")
+            .with("--force=b?");
+        args.set_choices("--env", &["prod", "dev"], false).unwrap();
+        args.add_transform("--env", Transform::Trim).unwrap();
+        args.add_transform("--env", Transform::Lowercase).unwrap();
+        args.add_transform("--tag", Transform::custom(|val| val.trim_start_matches('v').to_string())).unwrap();
+        args.add_transform("--tag", Transform::Uppercase).unwrap();
+        args.add_transform("--count", Transform::Trim).unwrap();
+        args.add_transform("--force", Transform::Lowercase).unwrap();
+        args.with_validator("--env", |val| if val == val.trim() { Ok(()) } else { Err("untrimmed".to_string()) });
+        assert!(matches!(args.add_transform("--nope", Transform::Trim), Err(ArgError::WrongKey)));
+
+        let tokens: Vec<String> = ["--env", " PROD ", "--tag=v1.2-rc", "--count", " 3", "--force=TRUE"].map(String::from).into();
+        args.parse_interleaved(&tokens).unwrap();
+        assert_eq!(args.get_str("--env").unwrap(), Some("prod"));
+        assert_eq!(args.get_str("--tag").unwrap(), Some("1.2-RC"));
+        assert_eq!(args.get_int("--count").unwrap(), Some(3));
+        assert_eq!(args.get_bool("--force").unwrap(), Some(true));
+        assert_eq!(args.ordered()[0], OrderedArg { key: Some("--env".to_string()), value: Some(" PROD ".to_string()) });
+    }
+
+    #[test]
+    fn post_transform_after_checks() {
+        let mut args = CliArgs::new();
+        args.with("--email=s").with("--code=s? ::>ab").with("--n=i?");
+        args.set_choices("--code", &["ab", "cd"], false).unwrap();
+        args.set_post_transform("--email", Transform::Lowercase).unwrap();
+        args.set_post_transform("--code", Transform::custom(|val| val.to_uppercase())).unwrap();
+        assert!(matches!(args.set_post_transform("--n", Transform::Trim), Err(ArgError::WrongType)));
+        args.with_validator("--email", |val| match val.contains('A') {
+            true => Ok(()),
+            false => Err("validators see the value as given".to_string()),
+        });
+
+        args.parse("--email Alp@Example.com").unwrap();
+        assert_eq!(args.get_str("--email").unwrap(), Some("alp@example.com"));
+        assert_eq!(args.get_str("--code").unwrap(), Some("AB"));
+
+        args.reset();
+        args.parse("--email A --code cd").unwrap();
+        assert_eq!(args.get_str("--code").unwrap(), Some("CD"));
+    }
+
+    #[test]
+    fn transforms_env_and_config_values() {
+        let var = format!("CLITRS_TEST_TRANSFORM_{}", std::process::id());
+        std::env::set_var(&var, "  Alp\n");
+        let mut args = CliArgs::new();
+        args.with("--user=s").with("--region=s");
+        args.set_env("--user", &var).unwrap();
+        args.add_transform("--user", Transform::Trim).unwrap();
+        args.add_transform("--region", Transform::Lowercase).unwrap();
+        args.apply_defaults(&HashMap::from([("--region".to_string(), "EU-West".to_string())])).unwrap();
+
+        args.parse("").unwrap();
+        assert_eq!(args.get_str("--user").unwrap(), Some("Alp"));
+        assert_eq!(args.get_str("--region").unwrap(), Some("eu-west"));
+    }
+}
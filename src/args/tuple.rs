@@ -0,0 +1,209 @@
+//! Composite values split from a single token, like `--size=800x600`, see
+//! [`CliArgs::with_tuple`].
+
+use super::{Arg, ArgError, CliArgs, FromArg, ParseError, SchemaError};
+
+/// The shape of a [`CliArgs::with_tuple`] value: the separator between its
+/// components and their names, 2 or 3 of them, which are either all ints or
+/// all strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tuple {
+    sep: String,
+    names: Vec<String>,
+    ints: bool,
+}
+
+impl Tuple {
+    /// Int components, `Tuple::ints("x", &["W", "H"])` takes `800x600`.
+    pub fn ints(sep: &str, names: &[&str]) -> Self {
+        Self { sep: sep.to_string(), names: names.iter().map(|name| name.to_string()).collect(), ints: true }
+    }
+
+    /// String components, `Tuple::strings(":", &["HOST", "PORT"])` takes `localhost:80`.
+    pub fn strings(sep: &str, names: &[&str]) -> Self {
+        Self { sep: sep.to_string(), names: names.iter().map(|name| name.to_string()).collect(), ints: false }
+    }
+
+    /// The value placeholder in help, e.g. `<W>x<H>`.
+    pub(super) fn placeholder(&self) -> String {
+        let names: Vec<String> = self.names.iter().map(|name| format!("<{name}>")).collect();
+        names.join(&self.sep)
+    }
+
+    /// The components of `val`. The error is the name of the first component
+    /// that is no int, or `None` if there are too few or too many.
+    fn split<'v>(&self, val: &'v str) -> Result<Vec<&'v str>, Option<&str>> {
+        let parts: Vec<&str> = val.split(self.sep.as_str()).collect();
+        if parts.len() != self.names.len() {
+            return Err(None);
+        }
+        match parts.iter().zip(&self.names).find(|(part, _)| self.ints && i32::from_arg(part).is_none()) {
+            Some((_, name)) => Err(Some(name)),
+            None => Ok(parts),
+        }
+    }
+
+    /// Checks a value of the arg `key`, `secret` values are not repeated.
+    pub(super) fn check(&self, key: &str, val: &str, secret: bool) -> Result<(), ParseError> {
+        self.split(val).map(|_| ()).map_err(|component| match secret {
+            true => ParseError::InvalidSecret { key: key.to_string(), reason: Some(format!("expected {}", self.placeholder())) },
+            false => ParseError::InvalidTuple {
+                key: key.to_string(),
+                value: val.to_string(),
+                expected: self.placeholder(),
+                component: component.map(str::to_string),
+            },
+        })
+    }
+}
+
+impl CliArgs {
+    /// Makes the string arg for `key` take a composite value of the given
+    /// shape, read back with [`CliArgs::get_pair_int`] and its siblings.
+    /// Its default is written the same way:
+    ///
+    /// ```
+    /// # use clitrs::args::{CliArgs, Tuple};
+    /// let mut args = CliArgs::new();
+    /// args.with("--size=s? ::>800x600").with_tuple("--size", Tuple::ints("x", &["W", "H"]));
+    /// args.parse("").unwrap();
+    /// assert_eq!(args.get_pair_int("--size").unwrap(), Some((800, 600)));
+    /// ```
+    ///
+    /// An unknown key, an arg of another type, a shape without a separator
+    /// or with other than 2 or 3 components and a default not of the shape
+    /// are schema errors, see [`CliArgs::schema_error`].
+    pub fn with_tuple(&mut self, key: &str, tuple: Tuple) -> &mut Self {
+        let Some(&ind) = self.keys.get(key) else {
+            self.schema_error.get_or_insert(SchemaError::UnknownKey(key.to_string()));
+            return self;
+        };
+        let Arg::String { settings, .. } = &mut self.args[ind] else {
+            self.schema_error.get_or_insert(SchemaError::WrongType(key.to_string()));
+            return self;
+        };
+        if tuple.sep.is_empty() || !(2..=3).contains(&tuple.names.len()) {
+            self.schema_error.get_or_insert(SchemaError::InvalidTuple(key.to_string()));
+            return self;
+        }
+        if let Some(default) = settings.default_val.as_ref().filter(|default| tuple.split(default).is_err()) {
+            let e = SchemaError::InvalidDefault { schema: key.to_string(), default: default.clone() };
+            self.schema_error.get_or_insert(e);
+            return self;
+        }
+        settings.tuple = Some(tuple);
+        self
+    }
+
+    /// The first value of a [`Tuple::ints`] arg of 2 components. An arg
+    /// of another shape is [`ArgError::WrongType`].
+    pub fn get_pair_int(&self, key: &str) -> Result<Option<(i32, i32)>, ArgError> {
+        Ok(self.tuple_ints(key, 2)?.map(|c| (c[0], c[1])))
+    }
+
+    /// The first value of a [`Tuple::ints`] arg of 3 components.
+    pub fn get_triple_int(&self, key: &str) -> Result<Option<(i32, i32, i32)>, ArgError> {
+        Ok(self.tuple_ints(key, 3)?.map(|c| (c[0], c[1], c[2])))
+    }
+
+    /// The first value of a [`Tuple::strings`] arg of 2 components.
+    pub fn get_pair_str(&self, key: &str) -> Result<Option<(&str, &str)>, ArgError> {
+        Ok(self.tuple_parts(key, false, 2)?.map(|c| (c[0], c[1])))
+    }
+
+    /// The first value of a [`Tuple::strings`] arg of 3 components.
+    pub fn get_triple_str(&self, key: &str) -> Result<Option<(&str, &str, &str)>, ArgError> {
+        Ok(self.tuple_parts(key, false, 3)?.map(|c| (c[0], c[1], c[2])))
+    }
+
+    fn tuple_ints(&self, key: &str, len: usize) -> Result<Option<Vec<i32>>, ArgError> {
+        let parts = self.tuple_parts(key, true, len)?;
+        // every component was checked to be an int when the value was pushed
+        Ok(parts.map(|parts| parts.iter().map(|part| i32::from_arg(part).unwrap_or_default()).collect()))
+    }
+
+    /// The components of the first value of `key`, if its shape matches.
+    fn tuple_parts(&self, key: &str, ints: bool, len: usize) -> Result<Option<Vec<&str>>, ArgError> {
+        let Arg::String { vals, settings } = self.get_arg(key).ok_or(ArgError::WrongKey)? else {
+            return Err(ArgError::WrongType);
+        };
+        let tuple = settings.tuple.as_ref()
+            .filter(|tuple| tuple.ints == ints && tuple.names.len() == len)
+            .ok_or(ArgError::WrongType)?;
+        Ok(vals.first().and_then(|val| tuple.split(val).ok()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tuple;
+    use crate::args::{ArgError, CliArgs, ParseError, SchemaError};
+
+    fn shapes() -> CliArgs {
+        let mut args = CliArgs::new();
+        args
+            .with("--size=s? ::>800x600")
+            .with("--rgb=s?")
+            .with("--addr=s?")
+            .with_tuple("--size", Tuple::ints("x", &["W", "H"]))
+            .with_tuple("--rgb", Tuple::ints(",", &["R", "G", "B"]))
+            .with_tuple("--addr", Tuple::strings(":", &["HOST", "PORT"]));
+        args
+    }
+
+    #[test]
+    fn pairs_and_triples() {
+        let mut args = shapes();
+        args.parse("--rgb=0xFF,-1,0 --addr=localhost:80").unwrap();
+        assert_eq!(args.get_pair_int("--size").unwrap(), Some((800, 600)));
+        assert_eq!(args.get_triple_int("--rgb").unwrap(), Some((255, -1, 0)));
+        assert_eq!(args.get_pair_str("--addr").unwrap(), Some(("localhost", "80")));
+        assert!(matches!(args.get_triple_int("--size"), Err(ArgError::WrongType)));
+        assert!(matches!(args.get_pair_int("--addr"), Err(ArgError::WrongType)));
+
+        args.reset();
+        args.parse("--size 1024x768").unwrap();
+        assert_eq!(args.get_pair_int("--size").unwrap(), Some((1024, 768)));
+        assert_eq!(args.get_triple_str("--rgb").ok(), None);
+        assert!(args.help().contains("--size <W>x<H>") && args.help().contains("--rgb <R>,<G>,<B>"));
+    }
+
+    #[test]
+    fn invalid_components() {
+        let mut args = shapes();
+        let e = args.parse("--size 800xabc --rgb 1,2").unwrap_err();
+        assert_eq!(e, ParseError::Multiple(vec![
+            ParseError::InvalidTuple {
+                key: "--size".to_string(),
+                value: "800xabc".to_string(),
+                expected: "<W>x<H>".to_string(),
+                component: Some("H".to_string()),
+            },
+            ParseError::InvalidTuple {
+                key: "--rgb".to_string(),
+                value: "1,2".to_string(),
+                expected: "<R>,<G>,<B>".to_string(),
+                component: None,
+            },
+        ]));
+        assert_eq!(
+            e.to_string(),
+            "2 errors:\n  - invalid `H` in `800xabc` for `--size`, expected <W>x<H>\n  - invalid value `1,2` for `--rgb`, expected <R>,<G>,<B>",
+        );
+    }
+
+    #[test]
+    fn invalid_shapes() {
+        let mut args = CliArgs::new();
+        args.with("--size=s? ::>800").with_tuple("--size", Tuple::ints("x", &["W", "H"]));
+        assert_eq!(args.schema_error(), Some(&SchemaError::InvalidDefault { schema: "--size".to_string(), default: "800".to_string() }));
+
+        let mut args = CliArgs::new();
+        args.with("--size=s?").with_tuple("--size", Tuple::ints("x", &["W"]));
+        assert_eq!(args.schema_error(), Some(&SchemaError::InvalidTuple("--size".to_string())));
+
+        let mut args = CliArgs::new();
+        args.with("--size=i?").with_tuple("--size", Tuple::ints("x", &["W", "H"]));
+        assert_eq!(args.schema_error(), Some(&SchemaError::WrongType("--size".to_string())));
+    }
+}
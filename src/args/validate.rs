@@ -0,0 +1,243 @@
+//! Checks attached to args after registration, run on every given value:
+//! length limits and validators.
+
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use super::{Arg, ArgError, ArgSettings, CliArgs, FromArg, ParseError, SchemaError};
+
+type ValidateFn = dyn Fn(&str) -> Result<(), String> + Send + Sync;
+
+/// A check of a raw value, see [`CliArgs::with_validator`].
+#[derive(Clone)]
+pub(super) struct Validator(Arc<ValidateFn>);
+
+impl Debug for Validator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("Validator")
+    }
+}
+
+impl CliArgs {
+    /// Rejects values of the int arg for `key` outside `min..=max`. An
+    /// unknown key or an arg of another type is a schema error, see
+    /// [`CliArgs::schema_error`].
+    pub fn with_range(&mut self, key: &str, min: i32, max: i32) -> &mut Self {
+        match self.keys.get(key).map(|ind| &self.args[*ind]) {
+            Some(Arg::Int { .. }) => {},
+            Some(_) => {
+                self.schema_error.get_or_insert(SchemaError::WrongType(key.to_string()));
+                return self;
+            },
+            None => {
+                self.schema_error.get_or_insert(SchemaError::UnknownKey(key.to_string()));
+                return self;
+            },
+        }
+        // a value that is no int at all is reported as invalid by the arg itself
+        self.with_validator(key, move |val| match i32::from_arg(val) {
+            Some(n) if !(min..=max).contains(&n) => Err(format!("must be between {} and {}", min, max)),
+            _ => Ok(()),
+        })
+    }
+
+    /// Runs `f` on every value given for `key`, before it is converted to
+    /// the arg's type. An `Err` rejects the value with its reason as
+    /// [`ParseError::Rejected`]. An unknown key is a schema error.
+    ///
+    /// ```
+    /// # use clitrs::args::CliArgs;
+    /// let mut args = CliArgs::new();
+    /// args.with("--branch=s").with_validator("--branch", |val| match val.contains("..") {
+    ///     true => Err("must not contain `..`".to_string()),
+    ///     false => Ok(()),
+    /// });
+    /// assert!(args.parse("--branch a..b").is_err());
+    /// ```
+    pub fn with_validator(
+        &mut self,
+        key: &str,
+        f: impl Fn(&str) -> Result<(), String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let Some(&ind) = self.keys.get(key) else {
+            self.schema_error.get_or_insert(SchemaError::UnknownKey(key.to_string()));
+            return self;
+        };
+        let validator = Validator(Arc::new(f));
+        match &mut self.args[ind] {
+            Arg::Bool { settings, .. } => settings.validators.push(validator),
+            Arg::Int { settings, .. } => settings.validators.push(validator),
+            Arg::String { settings, .. } => settings.validators.push(validator),
+        }
+        self
+    }
+}
+
+impl CliArgs {
+    /// Rejects empty values of a string arg, and values of only whitespace.
+    /// Like the length limits this is checked for every value, including
+    /// defaults and values from the environment, before any validator.
+    pub fn set_non_empty(&mut self, key: &str, non_empty: bool) -> Result<(), ArgError> {
+        self.string_settings(key).map(|settings| settings.non_empty = non_empty)
+    }
+
+    /// Rejects values of a string arg shorter than `min` characters.
+    pub fn set_min_len(&mut self, key: &str, min: usize) -> Result<(), ArgError> {
+        self.string_settings(key).map(|settings| settings.min_len = Some(min))
+    }
+
+    /// Rejects values of a string arg longer than `max` characters.
+    pub fn set_max_len(&mut self, key: &str, max: usize) -> Result<(), ArgError> {
+        self.string_settings(key).map(|settings| settings.max_len = Some(max))
+    }
+
+    fn string_settings(&mut self, key: &str) -> Result<&mut ArgSettings<String>, ArgError> {
+        let ind = *self.keys.get(key).ok_or(ArgError::WrongKey)?;
+        match &mut self.args[ind] {
+            Arg::String { settings, .. } => Ok(settings),
+            _ => Err(ArgError::WrongType),
+        }
+    }
+}
+
+impl ArgSettings<String> {
+    /// The `non_empty` and length limits, lengths counted in characters.
+    fn check_len(&self, key: &str, val: &str) -> Result<(), ParseError> {
+        if self.non_empty && val.trim().is_empty() {
+            return Err(ParseError::Empty(key.to_string()));
+        }
+        if self.min_len.is_none() && self.max_len.is_none() {
+            return Ok(());
+        }
+        let len = val.chars().count();
+        match (self.min_len, self.max_len) {
+            (Some(min), _) if len < min => Err(ParseError::TooShort { key: key.to_string(), min, len }),
+            (_, Some(max)) if len > max => Err(ParseError::TooLong { key: key.to_string(), max, len }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Arg {
+    /// Checks the defaults the arg got, which were not checked as values.
+    pub(super) fn check_defaults(&self, key: &str) -> Result<(), ParseError> {
+        let Arg::String { vals, settings } = self else {
+            return Ok(());
+        };
+        vals.iter().try_for_each(|val| {
+            settings.check_len(key, val)?;
+            settings.check_map_key(key, val)?;
+            match &settings.tuple {
+                Some(tuple) => tuple.check(key, val, settings.secret),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Runs the arg's length limits and then its validators on `val`, the
+    /// first rejection wins.
+    pub(super) fn validate(&self, key: &str, val: &str) -> Result<(), ParseError> {
+        if let Arg::String { settings, .. } = self {
+            settings.check_len(key, val)?;
+        }
+        let validators = match self {
+            Arg::Bool { settings, .. } => &settings.validators,
+            Arg::Int { settings, .. } => &settings.validators,
+            Arg::String { settings, .. } => &settings.validators,
+        };
+        validators.iter()
+            .try_for_each(|Validator(f)| f(val))
+            .map_err(|reason| match self.is_secret() {
+                true => ParseError::InvalidSecret { key: key.to_string(), reason: Some(reason) },
+                false => ParseError::Rejected { key: key.to_string(), value: val.to_string(), reason },
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::{ArgError, CliArgs, ParseError, SchemaError};
+
+    #[test]
+    fn range_violation() {
+        let mut args = CliArgs::new();
+        args.with("--port/-p=i").with_range("--port", 1, 65535);
+        args.parse("-p 8080").unwrap();
+        assert_eq!(args.get_int("--port").unwrap(), Some(8080));
+
+        args.reset();
+        assert_eq!(args.parse("-p 0"), Err(ParseError::Rejected {
+            key: "-p".to_string(),
+            value: "0".to_string(),
+            reason: "must be between 1 and 65535".to_string(),
+        }));
+        args.reset();
+        assert_eq!(args.parse("-p x"), Err(ParseError::InvalidValue { key: "-p".to_string(), value: "x".to_string() }));
+    }
+
+    #[test]
+    fn validator_rejection() {
+        let mut args = CliArgs::new();
+        args
+            .with("--name=s*")
+            .with_validator("--name", |val| if val.is_empty() { Err("is empty".to_string()) } else { Ok(()) })
+            .with_validator("--name", |val| if val.len() > 3 { Err("is too long".to_string()) } else { Ok(()) });
+        args.parse("--name ab abc").unwrap();
+
+        args.reset();
+        let e = args.parse("--name abcd --name=").unwrap_err();
+        assert_eq!(e.to_string(), "2 errors:\n  - invalid value `abcd` for `--name`: is too long\n  - invalid value `` for `--name`: is empty");
+    }
+
+    #[test]
+    fn length_limits() {
+        let var = format!("CLITRS_TEST_LEN_{}", std::process::id());
+        std::env::set_var(&var, "toolongvalue");
+        let mut args = CliArgs::new();
+        args
+            .with("--name=s? ::>x")
+            .with("--tag=s?")
+            .with("--user=s?")
+            .with("--count=i?");
+        args.set_non_empty("--name", true).unwrap();
+        args.set_min_len("--name", 2).unwrap();
+        args.set_max_len("--name", 4).unwrap();
+        args.set_max_len("--user", 8).unwrap();
+        args.set_env("--user", &var).unwrap();
+        args.set_non_empty("--tag", true).unwrap();
+        args.with_validator("--tag", |_| Err("never reached".to_string()));
+        assert!(matches!(args.set_min_len("--count", 1), Err(ArgError::WrongType)));
+        assert!(args.help().contains("[non-empty] [length: 2-4] [default: x]"), "{}", args.help());
+
+        assert_eq!(args.parse("--name çağrı --user ok"), Err(ParseError::TooLong { key: "--name".to_string(), max: 4, len: 5 }));
+        args.reset();
+        args.parse("--name çağ --user ok").unwrap();
+        assert_eq!(args.get_str("--name").unwrap(), Some("çağ"));
+
+        args.reset();
+        let e = args.parse("--tag=").unwrap_err();
+        assert_eq!(e, ParseError::Multiple(vec![
+            ParseError::Empty("--tag".to_string()),
+            ParseError::TooShort { key: "--name".to_string(), min: 2, len: 1 },
+            ParseError::TooLong { key: "--user".to_string(), max: 8, len: 12 },
+        ]));
+        assert_eq!(
+            e.to_string(),
+            "3 errors:\n  - `--tag` must not be empty\n  - `--name` needs at least 2 characters, got 1\n  - `--user` takes at most 8 characters, got 12",
+        );
+        args.reset();
+        let tokens: Vec<String> = ["--name", "ab", "--user", "ok", "--tag", " "].map(String::from).into();
+        assert_eq!(args.parse_interleaved(&tokens), Err(ParseError::Empty("--tag".to_string())));
+    }
+
+    #[test]
+    fn unknown_key_or_wrong_type() {
+        let mut args = CliArgs::new();
+        args.with("--name=s").with_range("--name", 0, 1);
+        assert_eq!(args.schema_error(), Some(&SchemaError::WrongType("--name".to_string())));
+
+        let mut args = CliArgs::new();
+        args.with_validator("--nope", |_| Ok(()));
+        assert_eq!(args.parse(""), Err(ParseError::Schema(SchemaError::UnknownKey("--nope".to_string()))));
+    }
+}
@@ -0,0 +1,94 @@
+//! The conventional `-v`/`-q` flags and their mapping to log levels.
+
+use super::{CliArgs, SchemaError};
+
+const VERBOSITY_KEYS: [&str; 4] = ["--verbose", "-v", "--quiet", "-q"];
+
+impl CliArgs {
+    /// Registers the flags `--verbose/-v` and `--quiet/-q`, both counted,
+    /// see [`CliArgs::verbosity`]. Fails with [`SchemaError::KeyTaken`] if
+    /// any of their keys is already registered.
+    pub fn with_verbosity(&mut self) -> Result<&mut Self, SchemaError> {
+        if let Some(key) = VERBOSITY_KEYS.iter().find(|key| self.keys.contains_key(key)) {
+            return Err(SchemaError::KeyTaken(key.to_string()));
+        }
+        Ok(self
+            .with("--verbose/-v=b? # More output, repeat for even more")
+            .with("--quiet/-q=b? # Less output, repeat for even less"))
+    }
+
+    /// How often `-v` was given minus how often `-q` was, e.g. 2 for `-vv`
+    /// and -1 for `-q`. Zero without [`CliArgs::with_verbosity`].
+    pub fn verbosity(&self) -> i8 {
+        let count = |key| {
+            let given = self.get_bool_multi(key).unwrap_or_default().iter().filter(|given| **given).count();
+            given.min(i8::MAX as usize) as i8
+        };
+        count("--verbose").saturating_sub(count("--quiet"))
+    }
+
+    /// The log level [`CliArgs::verbosity`] steps away from
+    /// [`log::LevelFilter::Warn`]: `-v` is info, `-vv` debug, `-q` error.
+    #[cfg(feature = "log")]
+    pub fn log_level_filter(&self) -> log::LevelFilter {
+        self.log_level_filter_from(log::LevelFilter::Warn)
+    }
+
+    /// Like [`CliArgs::log_level_filter`], starting at `base` instead of warn.
+    #[cfg(feature = "log")]
+    pub fn log_level_filter_from(&self, base: log::LevelFilter) -> log::LevelFilter {
+        let level = (base as i16 + self.verbosity() as i16).clamp(0, log::LevelFilter::max() as i16);
+        log::LevelFilter::iter().nth(level as usize).unwrap_or(base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::{CliArgs, SchemaError};
+
+    fn verbosity(line: &str) -> i8 {
+        let mut args = CliArgs::new();
+        args.with_verbosity().unwrap().with("--name/-n=s?");
+        args.parse(line).unwrap();
+        args.verbosity()
+    }
+
+    #[test]
+    fn counts_verbose_and_quiet() {
+        assert_eq!(verbosity(""), 0);
+        assert_eq!(verbosity("-v -n x"), 1);
+        assert_eq!(verbosity("-vv --verbose"), 3);
+        assert_eq!(verbosity("-q"), -1);
+        assert_eq!(verbosity("-vvq"), 1);
+        assert_eq!(CliArgs::new().verbosity(), 0);
+    }
+
+    #[test]
+    fn refuses_taken_keys() {
+        let mut args = CliArgs::new();
+        args.with("--version/-v=b?");
+        assert_eq!(args.with_verbosity().err(), Some(SchemaError::KeyTaken("-v".to_string())));
+        let mut args = CliArgs::new();
+        args.flag("--quiet", None);
+        assert_eq!(args.with_verbosity().err(), Some(SchemaError::KeyTaken("--quiet".to_string())));
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn maps_to_log_levels() {
+        use log::LevelFilter;
+
+        let level = |line: &str, base: LevelFilter| {
+            let mut args = CliArgs::new();
+            args.with_verbosity().unwrap();
+            args.parse(line).unwrap();
+            args.log_level_filter_from(base)
+        };
+        assert_eq!(level("", LevelFilter::Warn), LevelFilter::Warn);
+        assert_eq!(level("-v", LevelFilter::Warn), LevelFilter::Info);
+        assert_eq!(level("-vvvvvv", LevelFilter::Warn), LevelFilter::Trace);
+        assert_eq!(level("-qq", LevelFilter::Warn), LevelFilter::Off);
+        assert_eq!(level("-qqqq", LevelFilter::Info), LevelFilter::Off);
+        assert_eq!(level("-v", LevelFilter::Info), LevelFilter::Debug);
+    }
+}
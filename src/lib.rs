@@ -1,74 +1,252 @@
-use std::{marker::PhantomData, collections::HashMap, any::TypeId};
+use std::{collections::HashMap, error::Error, fmt, io::BufRead, path::PathBuf};
 
 use derive_builder::Builder;
 
-pub mod args;
+use self::prompt::{Prompt, StrengthCheck};
+pub use self::prompt::PathRequirement;
+use self::term::Stream;
 
-pub struct CliStep<PrevOut, ThisOut> {
-    input: PrevOut,
-    _marker: PhantomData<(PrevOut, ThisOut)>,
+pub mod args;
+mod prompt;
+pub mod pipeline;
+pub mod spinner;
+pub mod term;
+pub mod testing;
+
+/// A chain of steps, each handed the output of the one before. `T` is the
+/// value the next step is given, so every closure is checked against what
+/// the chain actually carries.
+///
+/// ```
+/// # use clitrs::CliStep;
+/// CliStep::new("42")
+///     .then(|s: &str| s.parse::<u32>().unwrap())
+///     .end(|n: u32| assert_eq!(n, 42));
+/// ```
+pub struct CliStep<T> {
+    input: T,
 }
 
-impl<PrevOut, ThisOut> CliStep<PrevOut, ThisOut> {
-    pub fn new(input: PrevOut) -> Self {
-        Self { input, _marker: PhantomData }
+impl<T> CliStep<T> {
+    pub fn new(input: T) -> Self {
+        Self { input }
     }
 
-    pub fn then<NextOut, F>(self, mut this_step: F) -> CliStep<ThisOut, NextOut>
-    where
-        F: FnMut(PrevOut) -> ThisOut
-    {
-        let this_out = this_step(self.input);
-        CliStep::new(this_out)
+    pub fn then<U>(self, this_step: impl FnOnce(T) -> U) -> CliStep<U> {
+        CliStep::new(this_step(self.input))
     }
-}
 
-impl<PrevOut> CliStep<PrevOut, ()> {
-    pub fn end(self, mut end_step: impl FnMut(PrevOut) -> ()) -> CliStep<(), ()> {
+    /// Runs the last step, the chain takes no further steps after it.
+    pub fn end(self, end_step: impl FnOnce(T)) -> CliStepEnd {
         end_step(self.input);
-        CliStep::new(())
+        CliStepEnd { _ended: () }
     }
 }
 
+/// A [`CliStep`] chain that has ended, only made by [`CliStep::end`].
+#[derive(Debug)]
+pub struct CliStepEnd {
+    _ended: (),
+}
+
+
+/// How a [`CliDataBuilder`] flow ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WizardOutcome<T> {
+    /// Every question was answered.
+    Completed(T),
+    /// The user hit Ctrl-C during a prompt, holds the data as far as it got.
+    /// Needs the `ctrlc` feature on Unix, otherwise Ctrl-C ends the process
+    /// as usual. Ctrl-C outside a prompt is never caught.
+    Interrupted(T),
+}
+
+/// A [`CliDataBuilder`] flow was to read its answers from stdin, which is
+/// not a terminal, so nobody would see the questions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotATty;
+
+impl fmt::Display for NotATty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("stdin is not a terminal, cannot ask interactively")
+    }
+}
+
+impl Error for NotATty {}
 
 pub struct CliDataBuilder<T> {
     data: T,
     question: String,
     default: Option<String>,
+    secret: bool,
+    path: Option<PathRequirement>,
+    confirm: Option<String>,
+    strength: Option<Box<StrengthCheck>>,
+    input: Option<Box<dyn BufRead>>,
+    interrupted: bool,
+    not_a_tty: bool,
 }
 
 impl<T> CliDataBuilder<T> {
+    /// How often an [`CliDataBuilder::ask_secret_confirm`] question is asked.
+    pub const SECRET_ATTEMPTS: usize = 3;
+
     pub fn new(data: T) -> Self {
-        Self { data, question: String::new(), default: None }
+        Self {
+            data,
+            question: String::new(),
+            default: None,
+            secret: false,
+            path: None,
+            confirm: None,
+            strength: None,
+            input: None,
+            interrupted: false,
+            not_a_tty: false,
+        }
+    }
+
+    /// Reads the answers from `input` instead of stdin, which also lifts the
+    /// terminal requirement. Pass `io::stdin().lock()` to take piped answers.
+    pub fn with_input(mut self, input: impl BufRead + 'static) -> Self {
+        self.input = Some(Box::new(input));
+        self
     }
 
     pub fn ask(mut self, q: String) -> Self {
         self.question = q;
+        self.default = None;
+        self.secret = false;
+        self.path = None;
+        self.confirm = None;
+        self.strength = None;
         self
     }
 
     pub fn ask_with_default(mut self, q: String, d: String) -> Self {
         self.question = q;
         self.default = Some(d);
+        self.secret = false;
+        self.path = None;
+        self.confirm = None;
+        self.strength = None;
+        self
+    }
+
+    /// Asks `q` without echoing the answer, the terminal echo is restored
+    /// however the prompt ends.
+    pub fn ask_secret(mut self, q: String) -> Self {
+        self.question = q;
+        self.default = None;
+        self.secret = true;
+        self.path = None;
+        self.confirm = None;
+        self.strength = None;
+        self
+    }
+
+    /// Asks `q` for a path meeting `requirement`, asking again until one
+    /// does. A leading `~` is expanded, and on a terminal Tab completes the
+    /// path typed so far. Read with [`CliDataBuilder::then_path`], or as
+    /// text with [`CliDataBuilder::then`]. At the end of input the default
+    /// is taken like by [`CliDataBuilder::ask`], ending the flow like Ctrl-C
+    /// if it does not meet `requirement`.
+    pub fn ask_path(mut self, q: String, requirement: PathRequirement) -> Self {
+        self.question = q;
+        self.default = None;
+        self.secret = false;
+        self.path = Some(requirement);
+        self.confirm = None;
+        self.strength = None;
         self
     }
 
+    /// Asks `q` for a secret and `confirm` for it again, both without echo,
+    /// asking both anew while the entries differ, at most
+    /// [`CliDataBuilder::SECRET_ATTEMPTS`] times before the flow ends like
+    /// Ctrl-C. The entries are overwritten once handed over or rejected,
+    /// which is best-effort: copies left in the buffer of stdin are not.
+    pub fn ask_secret_confirm(mut self, q: String, confirm: String) -> Self {
+        self.question = q;
+        self.default = None;
+        self.secret = true;
+        self.path = None;
+        self.confirm = Some(confirm);
+        self.strength = None;
+        self
+    }
+
+    /// Judges the first entry of an [`CliDataBuilder::ask_secret_confirm`]
+    /// question before the confirmation is asked for, a weak one is rejected
+    /// with the returned reason and counts as a failed attempt.
+    pub fn with_strength(mut self, check: impl Fn(&str) -> Result<(), String> + 'static) -> Self {
+        self.strength = Some(Box::new(check));
+        self
+    }
+
+    /// Reads the answer to the last question and hands it to `f`. Skipped
+    /// once the flow was interrupted, or if stdin is not a terminal and no
+    /// [`CliDataBuilder::with_input`] was given.
     pub fn then(mut self, mut f: impl FnMut(&str, &mut T)) -> Self {
-        let ans = Self::get_ans(&self.question, self.default.as_ref().map(|x| &**x));       
-        f(&ans, &mut self.data);
+        if let Some(ans) = self.answer() {
+            f(&ans, &mut self.data);
+            if self.secret {
+                prompt::wipe(ans);
+            }
+        }
+        self
+    }
+
+    /// Like [`CliDataBuilder::then`] for an [`CliDataBuilder::ask_path`]
+    /// question, handing the checked path to `f`.
+    pub fn then_path(mut self, mut f: impl FnMut(PathBuf, &mut T)) -> Self {
+        if let Some(ans) = self.answer() {
+            f(PathBuf::from(ans), &mut self.data);
+        }
         self
     }
 
+    /// The answer to the last question, `None` if it is skipped.
+    fn answer(&mut self) -> Option<String> {
+        if self.interrupted || self.not_a_tty {
+            return None;
+        }
+        if self.input.is_none() && !term::is_tty(Stream::Stdin) {
+            self.not_a_tty = true;
+            return None;
+        }
+        let prompt = Prompt { question: &self.question, default: self.default.as_deref(), secret: self.secret };
+        let input = self.input.as_mut().map(|input| input.as_mut() as &mut dyn BufRead);
+        let ans = match (&self.confirm, self.path) {
+            (Some(confirm), _) => prompt.ask_confirmed(input, confirm, self.strength.as_deref(), Self::SECRET_ATTEMPTS),
+            (None, Some(requirement)) => prompt.ask_path(input, requirement).map(|path| path.to_string_lossy().into_owned()),
+            (None, None) => prompt.ask(input),
+        };
+        self.interrupted = ans.is_none();
+        ans
+    }
+
     pub fn build(&self) -> &Self {
         self
     }
 
-    pub fn end(self) -> T {
-        self.data
+    /// The data, whether or not the flow was interrupted, or [`NotATty`]
+    /// if no question could be asked, see [`CliDataBuilder::finish`].
+    pub fn end(self) -> Result<T, NotATty> {
+        match self.not_a_tty {
+            true => Err(NotATty),
+            false => Ok(self.data),
+        }
     }
 
-    fn get_ans(q: &str, d: Option<&str>) -> String {
-        format!("q: {}, d: {:?}\n", q, d)
+    /// The data, as [`WizardOutcome::Interrupted`] if the user hit Ctrl-C,
+    /// or [`NotATty`] if no question could be asked.
+    pub fn finish(self) -> Result<WizardOutcome<T>, NotATty> {
+        match (self.not_a_tty, self.interrupted) {
+            (true, _) => Err(NotATty),
+            (false, true) => Ok(WizardOutcome::Interrupted(self.data)),
+            (false, false) => Ok(WizardOutcome::Completed(self.data)),
+        }
     }
 }
 
@@ -148,9 +326,14 @@ impl CliArg {
     }
 }
 
-#[derive(Builder)]
+/// Built with [`ArgSettings::builder`], fields not set keep their default:
+/// required without a default.
+#[derive(Builder, Debug, Clone, PartialEq, Eq)]
+#[builder(build_fn(validate = "Self::validate"))]
 pub struct ArgSettings<T> {
+    #[builder(default)]
     pub optional: bool,
+    #[builder(default)]
     pub default_value: Option<T>,
 }
 
@@ -170,8 +353,32 @@ impl<T: Clone> ArgSettings<T> {
             default_value: None,
         }
     }
+
+    /// An optional arg taking `value` when not given.
+    pub fn optional_with_default(value: T) -> Self {
+        Self { optional: true, default_value: Some(value) }
+    }
+
+    /// An arg that must be given.
+    pub fn required() -> Self {
+        Self { optional: false, default_value: None }
+    }
 }
 
+impl<T: Clone> ArgSettingsBuilder<T> {
+    /// Rejects a default on a required arg, which would never be used.
+    /// It is the only inconsistent combination of these fields: a required
+    /// arg without a default is satisfied by giving it, and an optional one
+    /// is satisfied with or without a default.
+    fn validate(&self) -> Result<(), String> {
+        match (self.optional.unwrap_or_default(), &self.default_value) {
+            (false, Some(Some(_))) => Err("a required arg never takes its default, make it optional".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
 pub struct CliArgsParser {
     args_ind: HashMap<String, usize>,
     args: Vec<CliArg>,
@@ -180,7 +387,7 @@ pub struct CliArgsParser {
 
 impl CliArgsParser {
     pub fn new() -> Self {
-        Self { args_ind: Default::default(), args: Vec::new() }
+        Default::default()
     }
 
     pub fn with<T>(&mut self, key: String, settings: Option<ArgSettings<T>>) -> &mut Self
@@ -198,7 +405,7 @@ impl CliArgsParser {
         self
     }
 
-    pub fn parse(&mut self, cmd: &str) {
+    pub fn parse(&mut self, _cmd: &str) {
         todo!()
     }
 
@@ -215,7 +422,10 @@ impl CliArgsParser {
 
 #[cfg(test)]
 mod tests {
-    use crate::{CliStep, CliDataBuilder};
+    use std::io::Cursor;
+
+    use crate::{ArgSettings, ArgSettingsBuilderError, CliStep, CliDataBuilder, NotATty, PathRequirement, WizardOutcome};
+    use crate::term::{self, Stream};
 
     #[test]
     fn it_works() {
@@ -225,12 +435,91 @@ mod tests {
             .end(|n| println!("n + 10 = {}", n + 10));
 
         let data = CliDataBuilder::new(String::new())
+            .with_input(Cursor::new("a1\na2\n"))
             .ask("q1".to_string())
             .then(|a, data| data.push_str(a))
             .ask("q2".to_string())
             .then(|a, data| data.push_str(a))
             .end();
 
-        println!("{}", data);
+        assert_eq!(data, Ok("a1a2".to_string()));
+
+        let outcome = CliDataBuilder::new(Vec::new())
+            .with_input(Cursor::new("s3cret\n"))
+            .ask_secret("password".to_string())
+            .then(|a, data| data.push(a.to_string()))
+            .ask_with_default("user".to_string(), "root".to_string())
+            .then(|a, data| data.push(a.to_string()))
+            .finish();
+        assert_eq!(outcome, Ok(WizardOutcome::Completed(vec!["s3cret".to_string(), "root".to_string()])));
+    }
+
+    #[test]
+    fn secrets_are_confirmed() {
+        let outcome = CliDataBuilder::new(Vec::new())
+            .with_input(Cursor::new("abc\nabcdef\nabcdeg\nabcdef\nabcdef\n"))
+            .ask_secret_confirm("password".to_string(), "again".to_string())
+            .with_strength(|pw| if pw.len() >= 6 { Ok(()) } else { Err("too short".to_string()) })
+            .then(|a, data| data.push(a.to_string()))
+            .finish();
+        assert_eq!(outcome, Ok(WizardOutcome::Completed(vec!["abcdef".to_string()])));
+
+        let outcome = CliDataBuilder::new(Vec::new())
+            .with_input(Cursor::new("a\nb\nc\nd\ne\nf\ng\ng\n"))
+            .ask_secret_confirm("password".to_string(), "again".to_string())
+            .then(|a, data| data.push(a.to_string()))
+            .finish();
+        assert_eq!(outcome, Ok(WizardOutcome::Interrupted(Vec::new())));
+    }
+
+    #[test]
+    fn paths_are_handed_over_checked() {
+        let dir = std::env::temp_dir();
+        let outcome = CliDataBuilder::new(Vec::new())
+            .with_input(Cursor::new(format!("{}\n", dir.display())))
+            .ask_path("file".to_string(), PathRequirement::ExistingFile)
+            .then_path(|path, data| data.push(path))
+            .finish();
+        assert_eq!(outcome, Ok(WizardOutcome::Interrupted(Vec::new())));
+
+        let outcome = CliDataBuilder::new(Vec::new())
+            .with_input(Cursor::new(format!("{}\n", dir.display())))
+            .ask_path("dir".to_string(), PathRequirement::ExistingDir)
+            .then_path(|path, data| data.push(path))
+            .finish();
+        assert_eq!(outcome, Ok(WizardOutcome::Completed(vec![dir])));
+    }
+
+    #[test]
+    fn arg_settings_builder() {
+        assert_eq!(ArgSettings::<i32>::builder().build().unwrap(), ArgSettings::required());
+        assert_eq!(
+            ArgSettings::builder().optional(true).default_value(Some(8080)).build().unwrap(),
+            ArgSettings::optional_with_default(8080),
+        );
+        assert_eq!(ArgSettings::<i32>::builder().optional(true).build().unwrap(), ArgSettings { optional: true, default_value: None });
+
+        let e = ArgSettings::builder().default_value(Some(1)).build().unwrap_err();
+        assert!(matches!(&e, ArgSettingsBuilderError::ValidationError(reason) if reason.contains("never takes its default")));
+        assert!(ArgSettings::builder().optional(false).default_value(Some(1)).build().is_err());
+        assert_eq!(ArgSettings::<i32>::builder().optional(false).default_value(None).build().unwrap(), ArgSettings::required());
+    }
+
+    #[test]
+    fn refuses_without_terminal() {
+        if term::is_tty(Stream::Stdin) {
+            return; // run interactively, nothing to check
+        }
+        let outcome = CliDataBuilder::new(String::new())
+            .ask("q1".to_string())
+            .then(|a, data| data.push_str(a))
+            .finish();
+        assert_eq!(outcome, Err(NotATty));
+
+        let data = CliDataBuilder::new(String::new())
+            .ask_with_default("q1".to_string(), "a1".to_string())
+            .then(|a, data| data.push_str(a))
+            .end();
+        assert_eq!(data, Err(NotATty));
     }
 }
@@ -0,0 +1,458 @@
+//! Deferred pipelines of named steps, which unlike a [`CliStep`](crate::CliStep)
+//! chain run only when asked to, describe a run without doing it and, with
+//! the `checkpoint` feature, can resume from a checkpoint file.
+
+use std::any::Any;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "checkpoint")]
+pub use self::checkpoint::Checkpointed;
+pub use self::report::{RunReport, StepReport};
+
+#[cfg(feature = "checkpoint")]
+mod checkpoint;
+mod report;
+
+/// The output of a step on its way to the next, always of the type the
+/// next step takes, as steps are only chained by [`Pipeline::step`] and its siblings.
+type Carried = Box<dyn Any>;
+/// A step, which leaves the report of a pipeline it embeds in the slot.
+type Step = Box<dyn FnMut(Carried, &mut Option<RunReport>) -> Result<Carried, PipelineError>>;
+type Describe = Box<dyn Fn(Option<Carried>) -> String>;
+type Summary = Box<dyn Fn(&dyn Any) -> Option<String>>;
+type BeforeHook = Box<dyn Fn(&StepInfo)>;
+type AfterHook = Box<dyn Fn(&StepInfo, StepOutcome)>;
+
+/// How a [`Pipeline`] hands outputs from step to step: as they are with
+/// [`Plain`], or also recorded to a file with `Checkpointed`, which needs
+/// the `checkpoint` feature.
+pub trait Mode: sealed::Sealed + 'static {
+    #[doc(hidden)]
+    type Codec;
+}
+
+/// Outputs are handed from step to step as they are, of any type.
+#[derive(Debug, Clone, Copy)]
+pub struct Plain;
+
+impl Mode for Plain {
+    type Codec = ();
+}
+
+/// A type a step of a pipeline in mode `M` can output: any type for
+/// [`Plain`], one serde can record for `Checkpointed`.
+pub trait StepValue<M: Mode>: Sized + 'static {
+    #[doc(hidden)]
+    fn codec() -> M::Codec;
+}
+
+impl<T: 'static> StepValue<Plain> for T {
+    fn codec() {}
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::Plain {}
+    #[cfg(feature = "checkpoint")]
+    impl Sealed for super::Checkpointed {}
+}
+
+/// The step a [`Pipeline::before_each`] or [`Pipeline::after_each`] hook
+/// runs around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepInfo {
+    pub name: String,
+    /// Counting from 0.
+    pub index: usize,
+    /// The number of steps of the pipeline.
+    pub total: usize,
+}
+
+/// How a step ended, for [`Pipeline::after_each`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepOutcome {
+    Succeeded(Duration),
+    Failed { reason: String, duration: Duration },
+    /// Not run, as it finished before the checkpoint a run resumed from,
+    /// or, in a [`RunReport`], as a step before it failed.
+    Skipped,
+}
+
+/// Why a pipeline did not run to its end. All but [`PipelineError::Step`]
+/// concern checkpoints, which need the `checkpoint` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipelineError {
+    /// The step failed, the steps before it are checkpointed.
+    Step { name: String, reason: String },
+    /// The checkpoint was written by a pipeline with other steps, see
+    /// `Pipeline::run_from_scratch`.
+    ShapeChanged { path: PathBuf, expected: Vec<String>, found: Vec<String> },
+    /// The checkpoint could not be read or written.
+    Io { path: PathBuf, reason: String },
+    /// The checkpoint is no checkpoint, or a value is not of the step's type.
+    Corrupt { path: PathBuf, reason: String },
+    /// The output of the step cannot be recorded in a checkpoint.
+    Unrecordable { name: String, reason: String },
+}
+
+impl Display for PipelineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::Step { name, reason } => write!(f, "step `{}` failed: {}", name, reason),
+            PipelineError::ShapeChanged { path, expected, found } => write!(
+                f,
+                "checkpoint `{}` is of the steps {} but the pipeline has {}, run it from scratch",
+                path.display(), found.join(" > "), expected.join(" > "),
+            ),
+            PipelineError::Io { path, reason } => write!(f, "cannot access checkpoint `{}`: {}", path.display(), reason),
+            PipelineError::Corrupt { path, reason } => write!(f, "checkpoint `{}` is corrupt: {}", path.display(), reason),
+            PipelineError::Unrecordable { name, reason } => write!(f, "cannot checkpoint the output of step `{}`: {}", name, reason),
+        }
+    }
+}
+
+impl Error for PipelineError {}
+
+/// Named steps from an `In` to an `Out`, each taking the output of the one
+/// before, handed over as it is. In `Checkpointed` mode the outputs are
+/// also recorded, see `Pipeline::run_with_checkpoints`.
+///
+/// ```
+/// # use clitrs::pipeline::Pipeline;
+/// let mut provision = Pipeline::<String, String>::new()
+///     .step("resolve", |host: String| Ok::<_, String>(format!("{host}.internal")))
+///     .step("connect", |addr: String| Ok::<_, String>(addr.len()))
+///     .step("install", |n: usize| Ok::<_, String>(format!("{n} packages")));
+/// assert_eq!(provision.run("web1".to_string()).unwrap(), "13 packages");
+/// ```
+pub struct Pipeline<In, Out, M: Mode = Plain> {
+    names: Vec<String>,
+    steps: Vec<Step>,
+    /// Of the output of each step.
+    codecs: Vec<M::Codec>,
+    descriptions: Vec<Option<Describe>>,
+    summaries: Vec<Option<Summary>>,
+    before: Vec<BeforeHook>,
+    after: Vec<AfterHook>,
+    types: PhantomData<fn(In) -> Out>,
+}
+
+impl<In: 'static> Pipeline<In, In> {
+    pub fn new() -> Self {
+        Pipeline::empty()
+    }
+}
+
+impl<In: 'static> Default for Pipeline<In, In> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<In: 'static, Out: 'static, M: Mode> Pipeline<In, Out, M> {
+    fn empty() -> Self {
+        Pipeline {
+            names: Vec::new(),
+            steps: Vec::new(),
+            codecs: Vec::new(),
+            descriptions: Vec::new(),
+            summaries: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+            types: PhantomData,
+        }
+    }
+
+    /// Appends the step `name`, whose error ends a run as [`PipelineError::Step`].
+    pub fn step<Next, E>(self, name: &str, mut f: impl FnMut(Out) -> Result<Next, E> + 'static) -> Pipeline<In, Next, M>
+    where
+        Next: StepValue<M>,
+        E: Display,
+    {
+        let step_name = name.to_string();
+        self.push_step(name, Box::new(move |value, _| {
+            let output = f(take(value)).map_err(|e| PipelineError::Step { name: step_name.clone(), reason: e.to_string() })?;
+            Ok(Box::new(output))
+        }))
+    }
+
+    /// Appends the step `name` running the steps of `inner`, whose
+    /// [`RunReport`] nests in the one of [`Pipeline::run_with_report`].
+    pub fn step_pipeline<Next, N: Mode>(self, name: &str, mut inner: Pipeline<Out, Next, N>) -> Pipeline<In, Next, M>
+    where
+        Next: StepValue<M>,
+    {
+        let step_name = name.to_string();
+        self.push_step(name, Box::new(move |value, nested| {
+            let (output, report) = inner.run_with_report(take(value));
+            *nested = Some(report);
+            let output = output.map_err(|e| PipelineError::Step { name: step_name.clone(), reason: e.to_string() })?;
+            Ok(Box::new(output))
+        }))
+    }
+
+    fn push_step<Next: StepValue<M>>(mut self, name: &str, step: Step) -> Pipeline<In, Next, M> {
+        self.steps.push(step);
+        self.names.push(name.to_string());
+        self.codecs.push(Next::codec());
+        self.descriptions.push(None);
+        self.summaries.push(None);
+        let Pipeline { names, steps, codecs, descriptions, summaries, before, after, .. } = self;
+        Pipeline { names, steps, codecs, descriptions, summaries, before, after, types: PhantomData }
+    }
+
+    /// Summarizes the output of the last step for [`Pipeline::run_with_report`].
+    pub fn report(mut self, summarize: impl Fn(&Out) -> String + 'static) -> Self {
+        if let Some(summary) = self.summaries.last_mut() {
+            *summary = Some(Box::new(move |value| value.downcast_ref().map(&summarize)));
+        }
+        self
+    }
+
+    /// Runs `hook` before every step that runs, after the hooks registered before it.
+    pub fn before_each(mut self, hook: impl Fn(&StepInfo) + 'static) -> Self {
+        self.before.push(Box::new(hook));
+        self
+    }
+
+    /// Runs `hook` after every step with how it ended, also for a failed
+    /// step and for those skipped when resuming from a checkpoint, after
+    /// the hooks registered before it.
+    pub fn after_each(mut self, hook: impl Fn(&StepInfo, StepOutcome) + 'static) -> Self {
+        self.after.push(Box::new(hook));
+        self
+    }
+
+    /// Like [`Pipeline::step`], with `describe` telling what the step would
+    /// do for [`Pipeline::dry_run`]. It is handed the step's input when that
+    /// is known without running the steps before, which is for the first.
+    pub fn step_described<Next, E>(
+        self,
+        name: &str,
+        describe: impl Fn(Option<Out>) -> String + 'static,
+        f: impl FnMut(Out) -> Result<Next, E> + 'static,
+    ) -> Pipeline<In, Next, M>
+    where
+        Next: StepValue<M>,
+        E: Display,
+    {
+        let mut pipeline = self.step(name, f);
+        if let Some(description) = pipeline.descriptions.last_mut() {
+            *description = Some(Box::new(move |value| describe(value.map(take))));
+        }
+        pipeline
+    }
+
+    /// The step names in order.
+    pub fn step_names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// What a run with `input` would do, a line per step from its
+    /// description or else its name, without running any step.
+    ///
+    /// ```
+    /// # use clitrs::pipeline::Pipeline;
+    /// let deploy = Pipeline::<Vec<String>, Vec<String>>::new()
+    ///     .step("build", |hosts: Vec<String>| Ok::<_, String>(hosts))
+    ///     .step_described(
+    ///         "clean",
+    ///         |hosts: Option<Vec<String>>| format!("delete temp files on {}", hosts.map_or("the hosts".to_string(), |h| h.join(", "))),
+    ///         |hosts: Vec<String>| Ok::<_, String>(hosts),
+    ///     );
+    /// assert_eq!(deploy.dry_run(vec!["web1".to_string()]), ["build", "delete temp files on the hosts"]);
+    /// ```
+    pub fn dry_run(&self, input: In) -> Vec<String> {
+        let mut input = Some(Box::new(input) as Carried);
+        self.names.iter().zip(&self.descriptions)
+            .map(|(name, description)| match description {
+                Some(describe) => describe(input.take()),
+                None => {
+                    input = None;
+                    name.clone()
+                },
+            })
+            .collect()
+    }
+
+    /// Runs every step.
+    pub fn run(&mut self, input: In) -> Result<Out, PipelineError> {
+        let mut value: Carried = Box::new(input);
+        for ind in 0..self.steps.len() {
+            value = self.run_step(ind, value).0?;
+        }
+        Ok(take(value))
+    }
+
+    /// Runs every step like [`Pipeline::run`], also returning what each did.
+    pub fn run_with_report(&mut self, input: In) -> (Result<Out, PipelineError>, RunReport) {
+        let mut report = RunReport::default();
+        let mut value: Carried = Box::new(input);
+        for ind in 0..self.steps.len() {
+            let (result, step) = self.run_step(ind, value);
+            report.steps.push(step);
+            match result {
+                Ok(output) => value = output,
+                Err(e) => {
+                    report.steps.extend(self.names[ind + 1..].iter().map(|name| StepReport {
+                        name: name.clone(),
+                        outcome: StepOutcome::Skipped,
+                        summary: None,
+                        nested: None,
+                    }));
+                    return (Err(e), report);
+                },
+            }
+        }
+        (Ok(take(value)), report)
+    }
+
+    /// Runs the step at `ind` between the hooks.
+    fn run_step(&mut self, ind: usize, value: Carried) -> (Result<Carried, PipelineError>, StepReport) {
+        let info = self.step_info(ind);
+        for hook in &self.before {
+            hook(&info);
+        }
+        let started = Instant::now();
+        let mut nested = None;
+        let result = (self.steps[ind])(value, &mut nested);
+        let outcome = match &result {
+            Ok(_) => StepOutcome::Succeeded(started.elapsed()),
+            Err(PipelineError::Step { reason, .. }) => StepOutcome::Failed { reason: reason.clone(), duration: started.elapsed() },
+            Err(e) => StepOutcome::Failed { reason: e.to_string(), duration: started.elapsed() },
+        };
+        self.notify_after(ind, outcome.clone());
+        let summary = match (&result, &self.summaries[ind]) {
+            (Ok(output), Some(summarize)) => summarize(&**output),
+            _ => None,
+        };
+        (result, StepReport { name: info.name, outcome, summary, nested })
+    }
+
+    fn notify_after(&self, ind: usize, outcome: StepOutcome) {
+        let info = self.step_info(ind);
+        for hook in &self.after {
+            hook(&info, outcome.clone());
+        }
+    }
+
+    fn step_info(&self, ind: usize) -> StepInfo {
+        StepInfo { name: self.names[ind].clone(), index: ind, total: self.names.len() }
+    }
+}
+
+/// The value a step carried over, of the type the typed chaining guarantees.
+fn take<T: 'static>(value: Carried) -> T {
+    *value.downcast().unwrap_or_else(|_| panic!("a pipeline step got a value of another type than it takes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    use super::{Mode, Pipeline, PipelineError, StepOutcome, StepValue};
+    use crate::pipeline::RunReport;
+
+    pub(super) fn provision<M: Mode>(start: Pipeline<String, String, M>, flaky: Rc<Cell<bool>>, runs: Rc<Cell<usize>>) -> Pipeline<String, String, M>
+    where
+        String: StepValue<M>,
+        Vec<String>: StepValue<M>,
+    {
+        let counted = runs.clone();
+        start
+            .step("resolve", move |host: String| {
+                counted.set(counted.get() + 1);
+                Ok::<_, String>(format!("{host}.internal"))
+            })
+            .step("install", move |addr: String| match flaky.get() {
+                true => Err("connection reset"),
+                false => Ok(vec![addr, "nginx".to_string()]),
+            })
+            .step("report", |done: Vec<String>| Ok::<_, String>(done.join(": ")))
+    }
+
+    #[test]
+    fn hooks_run_around_steps() {
+        struct Conn(Rc<Cell<usize>>); // no serde, handed over as it is
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let (first, second) = (log.clone(), log.clone());
+        let mut pipeline = Pipeline::<usize, usize>::new()
+            .step("open", |n: usize| Ok::<_, String>(Conn(Rc::new(Cell::new(n)))))
+            .step("query", |conn: Conn| match conn.0.get() {
+                0 => Err("no rows"),
+                n => Ok(n * 2),
+            })
+            .before_each(move |info| first.borrow_mut().push(format!("before {} {}/{}", info.name, info.index + 1, info.total)))
+            .after_each(move |info, outcome| second.borrow_mut().push(match outcome {
+                StepOutcome::Succeeded(_) => format!("ok {}", info.name),
+                StepOutcome::Failed { reason, .. } => format!("failed {}: {}", info.name, reason),
+                StepOutcome::Skipped => format!("skipped {}", info.name),
+            }));
+
+        assert_eq!(pipeline.run(2), Ok(4));
+        assert_eq!(pipeline.run(0), Err(PipelineError::Step { name: "query".to_string(), reason: "no rows".to_string() }));
+        assert_eq!(*log.borrow(), [
+            "before open 1/2", "ok open", "before query 2/2", "ok query",
+            "before open 1/2", "ok open", "before query 2/2", "failed query: no rows",
+        ]);
+    }
+
+    #[test]
+    fn reports_nest() {
+        let flaky = Rc::new(Cell::new(false));
+        let install = Pipeline::<String, String>::new()
+            .step("download", |addr: String| Ok::<_, String>(addr))
+            .report(|addr| format!("from {addr}"))
+            .step("unpack", |addr: String| Ok::<_, String>(addr));
+        let mut pipeline = provision(Pipeline::new(), flaky.clone(), Rc::new(Cell::new(0)))
+            .step_pipeline("finish", install)
+            .report(|report| report.to_uppercase());
+
+        let (out, report) = pipeline.run_with_report("web1".to_string());
+        assert_eq!(out.unwrap(), "web1.internal: nginx");
+        assert!(report.succeeded());
+        let names: Vec<&str> = report.steps.iter().map(|step| step.name.as_str()).collect();
+        assert_eq!(names, ["resolve", "install", "report", "finish"]);
+        assert_eq!(report.steps[3].summary.as_deref(), Some("WEB1.INTERNAL: NGINX"));
+        let nested = report.steps[3].nested.as_ref().unwrap();
+        assert_eq!(nested.steps[0].summary.as_deref(), Some("from web1.internal: nginx"));
+        let table = report.to_string();
+        assert!(table.starts_with("STEP        STATUS     TIME  SUMMARY\nresolve     succeeded  0.0s\n"), "{table}");
+        assert!(table.contains("\n  download  succeeded  0.0s  from web1.internal: nginx\n"), "{table}");
+
+        flaky.set(true);
+        let (out, report) = pipeline.run_with_report("web1".to_string());
+        assert!(out.is_err() && !report.succeeded());
+        assert_eq!(report.steps[3].outcome, StepOutcome::Skipped);
+        assert_eq!(RunReport::default().to_string(), "STEP  STATUS  TIME  SUMMARY\nsucceeded in 0.0s");
+        #[cfg(feature = "checkpoint")]
+        {
+            let json = serde_json::to_value(&report).unwrap();
+            assert_eq!(json["succeeded"], false);
+            assert_eq!(json["steps"][1]["status"], "failed");
+            assert_eq!(json["steps"][1]["reason"], "connection reset");
+            assert_eq!(json["steps"][3]["status"], "skipped");
+        }
+    }
+
+    #[test]
+    fn dry_run_describes_steps() {
+        let ran = Rc::new(Cell::new(false));
+        let touched = ran.clone();
+        let pipeline = Pipeline::<u32, u32>::new()
+            .step_described("scale", |n: Option<u32>| format!("scale to {} replicas", n.unwrap()), |n: u32| Ok::<_, String>(n * 2))
+            .step("migrate", move |n: u32| {
+                touched.set(true);
+                Ok::<_, String>(n)
+            })
+            .step_described("notify", |n: Option<u32>| format!("notify about {n:?} replicas"), |n: u32| Ok::<_, String>(n));
+        assert_eq!(pipeline.dry_run(3), ["scale to 3 replicas", "migrate", "notify about None replicas"]);
+        assert!(!ran.get());
+    }
+}
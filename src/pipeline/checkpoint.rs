@@ -0,0 +1,208 @@
+//! Resuming a [`Pipeline`] from the output of its last finished step,
+//! recorded as JSON `{"steps": [..], "step": "install", "output": ..}`.
+
+use std::any::Any;
+use std::fmt::Display;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::{Carried, Mode, Pipeline, PipelineError, StepOutcome, StepValue};
+
+/// Outputs are handed from step to step as they are and recorded after
+/// each step, see [`Pipeline::run_with_checkpoints`].
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpointed;
+
+impl Mode for Checkpointed {
+    type Codec = Codec;
+}
+
+/// Records the output of a step and reads it back.
+#[doc(hidden)]
+pub struct Codec {
+    save: fn(&dyn Any) -> Result<Value, String>,
+    load: fn(Value) -> Result<Carried, String>,
+}
+
+impl<T: Serialize + DeserializeOwned + 'static> StepValue<Checkpointed> for T {
+    fn codec() -> Codec {
+        Codec {
+            save: |value| serde_json::to_value(value.downcast_ref::<T>()).map_err(|e| e.to_string()),
+            load: |value| serde_json::from_value::<T>(value).map(|value| Box::new(value) as Carried).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+impl<In: 'static> Pipeline<In, In, Checkpointed> {
+    /// A pipeline whose steps output what serde can record, which can
+    /// [`Pipeline::run_with_checkpoints`].
+    ///
+    /// ```no_run
+    /// # use clitrs::pipeline::Pipeline;
+    /// # use std::path::Path;
+    /// let mut provision = Pipeline::<String, String, _>::checkpointed()
+    ///     .step("resolve", |host: String| Ok::<_, String>(format!("{host}.internal")))
+    ///     .step("install", |addr: String| Ok::<_, String>(format!("nginx on {addr}")));
+    /// let done = provision.run_with_checkpoints("web1".to_string(), Path::new(".provision")).unwrap();
+    /// ```
+    pub fn checkpointed() -> Self {
+        Pipeline::empty()
+    }
+}
+
+impl<In: 'static, Out: 'static> Pipeline<In, Out, Checkpointed> {
+    /// Runs the steps after the last one recorded at `path`, starting from
+    /// its output instead of `input`, or every step if there is no file.
+    /// Each finished step replaces the record, which is removed once the
+    /// last one finishes. A record of other step names or another order is
+    /// [`PipelineError::ShapeChanged`].
+    pub fn run_with_checkpoints(&mut self, input: In, path: &Path) -> Result<Out, PipelineError> {
+        let (done, mut value) = match self.read_checkpoint(path)? {
+            Some(resumed) => resumed,
+            None => (0, Box::new(input) as Carried),
+        };
+        for ind in 0..done {
+            self.notify_after(ind, StepOutcome::Skipped);
+        }
+        for ind in done..self.steps.len() {
+            value = self.run_step(ind, value).0?;
+            self.write_checkpoint(path, ind, &*value)?;
+        }
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(io_error(path, e)),
+            _ => {}, // nothing is recorded for a pipeline without steps
+        }
+        Ok(super::take(value))
+    }
+
+    /// Like [`Pipeline::run_with_checkpoints`], ignoring a record at `path`.
+    pub fn run_from_scratch(&mut self, input: In, path: &Path) -> Result<Out, PipelineError> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(io_error(path, e)),
+            _ => self.run_with_checkpoints(input, path),
+        }
+    }
+
+    /// The number of steps done and the output of the last, if recorded.
+    fn read_checkpoint(&self, path: &Path) -> Result<Option<(usize, Carried)>, PipelineError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error(path, e)),
+        };
+        let mut record: Value = serde_json::from_str(&contents).map_err(|e| corrupt(path, e))?;
+        let shape: Vec<String> = serde_json::from_value(record["steps"].take()).map_err(|e| corrupt(path, e))?;
+        if shape != self.names {
+            return Err(PipelineError::ShapeChanged { path: path.to_path_buf(), expected: self.names.clone(), found: shape });
+        }
+        let done = record["step"].as_str()
+            .and_then(|name| self.names.iter().position(|step| step == name))
+            .ok_or_else(|| corrupt(path, "no finished step is recorded"))?;
+        let output = (self.codecs[done].load)(record["output"].take()).map_err(|e| corrupt(path, e))?;
+        Ok(Some((done + 1, output)))
+    }
+
+    /// Replaces the record at `path` by one through the step at `ind`. It is
+    /// written aside and moved over, so a crash leaves the last record whole.
+    fn write_checkpoint(&self, path: &Path, ind: usize, output: &dyn Any) -> Result<(), PipelineError> {
+        let output = (self.codecs[ind].save)(output)
+            .map_err(|reason| PipelineError::Unrecordable { name: self.names[ind].clone(), reason })?;
+        let record = json!({ "steps": self.names, "step": self.names[ind], "output": output });
+        let aside = aside(path);
+        fs::write(&aside, record.to_string())
+            .and_then(|()| fs::rename(&aside, path))
+            .map_err(|e| {
+                let _ = fs::remove_file(&aside);
+                io_error(path, e)
+            })
+    }
+}
+
+/// `path` with `.tmp` appended, in the same directory so a rename is atomic.
+fn aside(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> PipelineError {
+    PipelineError::Io { path: path.to_path_buf(), reason: e.to_string() }
+}
+
+fn corrupt(path: &Path, reason: impl Display) -> PipelineError {
+    PipelineError::Corrupt { path: path.to_path_buf(), reason: reason.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::fs;
+    use std::rc::Rc;
+
+    use crate::pipeline::tests::provision;
+    use crate::pipeline::{Pipeline, PipelineError, StepOutcome};
+
+    #[test]
+    fn resumes_after_failed_step() {
+        let path = std::env::temp_dir().join(format!("clitrs-checkpoint-{}", std::process::id()));
+        let (flaky, runs) = (Rc::new(Cell::new(true)), Rc::new(Cell::new(0)));
+        let mut pipeline = provision(Pipeline::checkpointed(), flaky.clone(), runs.clone());
+        assert_eq!(pipeline.step_names(), ["resolve", "install", "report"]);
+
+        let e = pipeline.run_with_checkpoints("web1".to_string(), &path).unwrap_err();
+        assert_eq!(e, PipelineError::Step { name: "install".to_string(), reason: "connection reset".to_string() });
+        assert!(path.exists());
+        assert!(!super::aside(&path).exists());
+
+        flaky.set(false);
+        assert_eq!(pipeline.run_with_checkpoints("ignored".to_string(), &path).unwrap(), "web1.internal: nginx");
+        assert_eq!(runs.get(), 1);
+        assert!(!path.exists());
+
+        flaky.set(true);
+        let _ = pipeline.run_with_checkpoints("web2".to_string(), &path);
+        let mut reshaped = Pipeline::<String, String, _>::checkpointed().step("resolve", |host: String| Ok::<_, String>(host));
+        assert!(matches!(
+            reshaped.run_with_checkpoints("web2".to_string(), &path),
+            Err(PipelineError::ShapeChanged { found, .. }) if found == ["resolve", "install", "report"]
+        ));
+        assert_eq!(reshaped.run_from_scratch("web2".to_string(), &path).unwrap(), "web2");
+        assert_eq!(pipeline.run("web3".to_string()), Err(PipelineError::Step { name: "install".to_string(), reason: "connection reset".to_string() }));
+
+        fs::write(&path, "not json").unwrap();
+        assert!(matches!(pipeline.run_with_checkpoints("web4".to_string(), &path), Err(PipelineError::Corrupt { .. })));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn hooks_see_skipped_steps() {
+        let path = std::env::temp_dir().join(format!("clitrs-hooks-{}", std::process::id()));
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let (first, second, third) = (log.clone(), log.clone(), log.clone());
+        let flaky = Rc::new(Cell::new(true));
+        let mut pipeline = provision(Pipeline::checkpointed(), flaky.clone(), Rc::new(Cell::new(0)))
+            .before_each(move |info| first.borrow_mut().push(format!("before {} {}/{}", info.name, info.index + 1, info.total)))
+            .after_each(move |info, outcome| second.borrow_mut().push(match outcome {
+                StepOutcome::Succeeded(_) => format!("ok {}", info.name),
+                StepOutcome::Failed { reason, .. } => format!("failed {}: {}", info.name, reason),
+                StepOutcome::Skipped => format!("skipped {}", info.name),
+            }))
+            .after_each(move |info, _| third.borrow_mut().push(format!("then {}", info.name)));
+
+        assert!(pipeline.run_with_checkpoints("web1".to_string(), &path).is_err());
+        flaky.set(false);
+        pipeline.run_with_checkpoints("web1".to_string(), &path).unwrap();
+        assert_eq!(*log.borrow(), [
+            "before resolve 1/3", "ok resolve", "then resolve",
+            "before install 2/3", "failed install: connection reset", "then install",
+            "skipped resolve", "then resolve",
+            "before install 2/3", "ok install", "then install",
+            "before report 3/3", "ok report", "then report",
+        ]);
+    }
+}
@@ -0,0 +1,127 @@
+//! What a run did, see [`Pipeline::run_with_report`](super::Pipeline::run_with_report).
+
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+#[cfg(feature = "checkpoint")]
+use serde::{Serialize, Serializer};
+#[cfg(feature = "checkpoint")]
+use serde_json::{json, Value};
+
+use super::StepOutcome;
+
+/// The steps of a run in order, each with how it ended, a failed step
+/// followed by the others as [`StepOutcome::Skipped`].
+///
+/// Displayed as a table, serialized with the `checkpoint` feature as e.g.
+///
+/// ```text
+/// { "succeeded": false, "duration_ms": 1200, "steps": [
+///     { "name": "resolve", "status": "succeeded", "duration_ms": 3, "summary": "web1.internal", "steps": null },
+///     { "name": "install", "status": "failed", "duration_ms": 1197, "reason": "connection reset", "summary": null, "steps": null },
+///     { "name": "report", "status": "skipped", "duration_ms": 0, "summary": null, "steps": null } ] }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RunReport {
+    pub steps: Vec<StepReport>,
+}
+
+/// A step of a [`RunReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepReport {
+    pub name: String,
+    pub outcome: StepOutcome,
+    /// What [`Pipeline::report`](super::Pipeline::report) made of the output.
+    pub summary: Option<String>,
+    /// The run of a pipeline the step embeds, see [`Pipeline::step_pipeline`](super::Pipeline::step_pipeline).
+    pub nested: Option<RunReport>,
+}
+
+impl RunReport {
+    /// Whether no step failed.
+    pub fn succeeded(&self) -> bool {
+        self.steps.iter().all(|step| !matches!(step.outcome, StepOutcome::Failed { .. }))
+    }
+
+    /// The time the steps took together.
+    pub fn duration(&self) -> Duration {
+        self.steps.iter().map(|step| step.outcome.duration()).sum()
+    }
+
+    #[cfg(feature = "checkpoint")]
+    fn to_value(&self) -> Value {
+        let steps: Vec<Value> = self.steps.iter()
+            .map(|step| {
+                let mut value = json!({
+                    "name": step.name,
+                    "status": step.outcome.status(),
+                    "duration_ms": step.outcome.duration().as_millis() as u64,
+                    "summary": step.summary,
+                    "steps": step.nested.as_ref().map(RunReport::to_value),
+                });
+                if let StepOutcome::Failed { reason, .. } = &step.outcome {
+                    value["reason"] = json!(reason);
+                }
+                value
+            })
+            .collect();
+        json!({ "succeeded": self.succeeded(), "duration_ms": self.duration().as_millis() as u64, "steps": steps })
+    }
+
+    /// The table rows of the steps, nested ones indented under their step.
+    fn rows(&self, depth: usize, rows: &mut Vec<[String; 4]>) {
+        for step in &self.steps {
+            let note = match &step.outcome {
+                StepOutcome::Failed { reason, .. } => reason.clone(),
+                _ => step.summary.clone().unwrap_or_default(),
+            };
+            let time = match step.outcome {
+                StepOutcome::Skipped => String::new(),
+                _ => format!("{:.1}s", step.outcome.duration().as_secs_f64()),
+            };
+            rows.push([format!("{}{}", "  ".repeat(depth), step.name), step.outcome.status().to_string(), time, note]);
+            if let Some(nested) = &step.nested {
+                nested.rows(depth + 1, rows);
+            }
+        }
+    }
+}
+
+impl Display for RunReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut rows = vec![["STEP", "STATUS", "TIME", "SUMMARY"].map(String::from)];
+        self.rows(0, &mut rows);
+        let widths: Vec<usize> = (0..3).map(|col| rows.iter().map(|row| row[col].chars().count()).max().unwrap_or(0)).collect();
+        for row in &rows {
+            let line = format!("{:w0$}  {:w1$}  {:>w2$}  {}", row[0], row[1], row[2], row[3], w0 = widths[0], w1 = widths[1], w2 = widths[2]);
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        let status = if self.succeeded() { "succeeded" } else { "failed" };
+        write!(f, "{} in {:.1}s", status, self.duration().as_secs_f64())
+    }
+}
+
+#[cfg(feature = "checkpoint")]
+impl Serialize for RunReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl StepOutcome {
+    fn status(&self) -> &'static str {
+        match self {
+            StepOutcome::Succeeded(_) => "succeeded",
+            StepOutcome::Failed { .. } => "failed",
+            StepOutcome::Skipped => "skipped",
+        }
+    }
+
+    /// How long the step ran, zero if it was skipped.
+    pub fn duration(&self) -> Duration {
+        match self {
+            StepOutcome::Succeeded(duration) | StepOutcome::Failed { duration, .. } => *duration,
+            StepOutcome::Skipped => Duration::ZERO,
+        }
+    }
+}
@@ -0,0 +1,516 @@
+//! Reading the answers of a [`CliDataBuilder`](crate::CliDataBuilder) flow:
+//! the question goes to stderr and the answer is a line of stdin, or of the
+//! input given to [`CliDataBuilder::with_input`](crate::CliDataBuilder::with_input).
+
+use std::io::{self, BufRead, Read};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::term::{self, Stream};
+
+pub use self::path::PathRequirement;
+
+mod path;
+
+/// Set by the Ctrl-C handler, taken by the prompt it interrupts.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// One question of a flow.
+pub(crate) struct Prompt<'q> {
+    pub question: &'q str,
+    pub default: Option<&'q str>,
+    pub secret: bool,
+}
+
+impl Prompt<'_> {
+    /// Asks the question and reads the answer, `None` if the user hit Ctrl-C.
+    /// An empty answer, or none at the end of input, is the default if any.
+    pub fn ask(&self, input: Option<&mut dyn BufRead>) -> Option<String> {
+        self.ask_with(input, &INTERRUPTED)
+    }
+
+    fn ask_with(&self, input: Option<&mut dyn BufRead>, interrupted: &AtomicBool) -> Option<String> {
+        let answer = self.read_answer(input, interrupted)?;
+        Some(answer.or_else(|| self.default.map(str::to_string)).unwrap_or_default())
+    }
+
+    /// Asks for a path until the answer meets `requirement`, with a leading
+    /// `~` expanded. On a terminal Tab completes the path typed so far, see
+    /// [`Prompt::read_path`]. From other input an answer ending in Tab lists
+    /// the entries completing it and asks again with their common start as
+    /// the default. At the end of input the default is taken like by
+    /// [`Prompt::ask`], `None` if it does not meet `requirement` or the
+    /// user hit Ctrl-C.
+    pub fn ask_path(&self, input: Option<&mut dyn BufRead>, requirement: PathRequirement) -> Option<PathBuf> {
+        self.ask_path_with(input, requirement, &INTERRUPTED)
+    }
+
+    fn ask_path_with(&self, mut input: Option<&mut dyn BufRead>, requirement: PathRequirement, interrupted: &AtomicBool) -> Option<PathBuf> {
+        let mut default = self.default.map(str::to_string);
+        loop {
+            let prompt = Prompt { question: self.question, default: default.as_deref(), secret: false };
+            let answer = match input.as_mut() {
+                None if term::is_tty(Stream::Stdin) => prompt.read_path_from_terminal(interrupted),
+                input => prompt.read_answer(input.map(|input| &mut **input as &mut dyn BufRead), interrupted),
+            };
+            let Some(answer) = answer? else { // the end of input, nothing is asked again
+                let path = path::expand(default.as_deref().unwrap_or_default());
+                return requirement.check(&path).is_ok().then_some(path);
+            };
+            if let Some(partial) = answer.strip_suffix('\t') {
+                let matches = path::complete(partial);
+                for entry in &matches {
+                    eprintln!("    {entry}");
+                }
+                default = (!matches.is_empty()).then(|| path::common_prefix(&matches));
+                continue;
+            }
+            let path = path::expand(&answer);
+            match requirement.check(&path) {
+                Ok(()) => return Some(path),
+                Err(reason) => eprintln!("`{}` {}", path.display(), reason),
+            }
+        }
+    }
+
+    /// [`Prompt::read_path`] from stdin with the terminal taking keys one
+    /// by one, or [`Prompt::read_answer`] if it cannot.
+    fn read_path_from_terminal(&self, interrupted: &AtomicBool) -> Option<Option<String>> {
+        let Some(_unbuffered) = EchoGuard::unbuffered() else {
+            return self.read_answer(None, interrupted);
+        };
+        self.read_path(&mut io::stdin().lock(), interrupted)
+    }
+
+    /// Asks the question and reads a path key by key, echoing it: Tab
+    /// completes it to the only entry completing it, or to the start all of
+    /// them share, listed when there are several. Backspace removes the last
+    /// character, other control keys are ignored. Returns like
+    /// [`Prompt::read_answer`], Ctrl-D on an empty line ending the input and
+    /// Ctrl-C, read as a key, interrupting.
+    fn read_path(&self, keys: &mut dyn Read, interrupted: &AtomicBool) -> Option<Option<String>> {
+        self.show();
+        let mut line = String::new();
+        let mut char_bytes = Vec::new(); // of a character not read whole yet
+        loop {
+            let mut byte = [0];
+            match keys.read(&mut byte) {
+                Ok(0) => return Some(None),
+                Ok(_) => {},
+                Err(e) if e.kind() == io::ErrorKind::Interrupted && !interrupted.load(Ordering::SeqCst) => continue,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => break,
+                Err(_) => return Some(None),
+            }
+            match byte[0] {
+                b'\n' | b'\r' => break,
+                0x04 if line.is_empty() => {
+                    eprintln!();
+                    return Some(None);
+                },
+                0x03 => {
+                    eprintln!();
+                    return None;
+                },
+                0x7f | 0x08 => if line.pop().is_some() {
+                    eprint!("\x08 \x08");
+                },
+                b'\t' => {
+                    let matches = path::complete(&line);
+                    if matches.len() > 1 {
+                        eprintln!();
+                        for entry in &matches {
+                            eprintln!("    {entry}");
+                        }
+                        self.show();
+                        eprint!("{line}");
+                    }
+                    let common = path::common_prefix(&matches);
+                    if let Some(rest) = common.strip_prefix(line.as_str()).filter(|rest| !rest.is_empty()) {
+                        eprint!("{rest}");
+                        line = common.clone();
+                    }
+                },
+                0x1b => skip_escape(keys), // arrow keys and the like
+                byte if byte < 0x20 => {},
+                byte => {
+                    char_bytes.push(byte);
+                    if let Ok(c) = std::str::from_utf8(&char_bytes) {
+                        eprint!("{c}");
+                        line.push_str(c);
+                        char_bytes.clear();
+                    }
+                    else if char_bytes.len() >= 4 {
+                        char_bytes.clear(); // not UTF-8, dropped
+                    }
+                },
+            }
+        }
+        eprintln!();
+        if interrupted.swap(false, Ordering::SeqCst) {
+            return None;
+        }
+        match (line.as_str(), self.default) {
+            ("", Some(default)) => Some(Some(default.to_string())),
+            _ => Some(Some(line)),
+        }
+    }
+
+    /// Asks for a secret and then for it again with `confirm` as the
+    /// question, starting over while the entries differ, at most `attempts`
+    /// times. `check` judges the first entry before the second is asked for,
+    /// its error is shown. `None` if the user hit Ctrl-C, the input ended or
+    /// no attempt succeeded. Rejected entries are wiped as far as
+    /// possible, see [`wipe`].
+    pub fn ask_confirmed(&self, input: Option<&mut dyn BufRead>, confirm: &str, check: Option<&StrengthCheck>, attempts: usize) -> Option<String> {
+        self.ask_confirmed_with(input, confirm, check, attempts, &INTERRUPTED)
+    }
+
+    fn ask_confirmed_with(
+        &self,
+        mut input: Option<&mut dyn BufRead>,
+        confirm: &str,
+        check: Option<&StrengthCheck>,
+        attempts: usize,
+        interrupted: &AtomicBool,
+    ) -> Option<String> {
+        let again = Prompt { question: confirm, default: None, secret: true };
+        for _ in 0..attempts {
+            let first = self.read_answer(input.as_mut().map(|input| &mut **input as &mut dyn BufRead), interrupted)??;
+            if let Some(Err(reason)) = check.map(|check| check(&first)) {
+                wipe(first);
+                eprintln!("{reason}");
+                continue;
+            }
+            let second = match again.read_answer(input.as_mut().map(|input| &mut **input as &mut dyn BufRead), interrupted) {
+                Some(Some(second)) => second,
+                _ => {
+                    wipe(first);
+                    return None;
+                },
+            };
+            let same = first == second;
+            wipe(second);
+            if same {
+                return Some(first);
+            }
+            wipe(first);
+            eprintln!("The entries do not match, please try again.");
+        }
+        eprintln!("Too many attempts.");
+        None
+    }
+
+    /// Shows the question, with the default if any.
+    fn show(&self) {
+        match self.default {
+            Some(default) => eprint!("{} [{}]: ", self.question, default),
+            None => eprint!("{}: ", self.question),
+        }
+    }
+
+    /// Asks the question and reads a line: `None` if the user hit Ctrl-C,
+    /// `Some(None)` at the end of input, and an empty line is the default.
+    fn read_answer(&self, input: Option<&mut dyn BufRead>, interrupted: &AtomicBool) -> Option<Option<String>> {
+        self.show();
+        let line = match input {
+            Some(input) => read_line(input),
+            None => {
+                let _echo = self.secret.then(EchoGuard::hide);
+                read_stdin_line(interrupted)
+            },
+        };
+        if interrupted.swap(false, Ordering::SeqCst) {
+            eprintln!();
+            return None;
+        }
+        let Some(mut line) = line else {
+            return Some(None);
+        };
+        let len = line.strip_suffix('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).unwrap_or(&line).len();
+        line.truncate(len); // in place, a secret is not copied
+        match (line.as_str(), self.default) {
+            ("", Some(default)) => Some(Some(default.to_string())),
+            _ => Some(Some(line)),
+        }
+    }
+}
+
+/// Reads the rest of an escape sequence after its `ESC`, like `[A` of the
+/// up arrow, so it is not taken as typed text.
+fn skip_escape(keys: &mut dyn Read) {
+    let mut byte = [0];
+    if !matches!(keys.read(&mut byte), Ok(1)) || !matches!(byte[0], b'[' | b'O') {
+        return;
+    }
+    while matches!(keys.read(&mut byte), Ok(1)) && !(0x40..=0x7e).contains(&byte[0]) {}
+}
+
+/// Judges a secret before it is confirmed, the error saying why it is too weak.
+pub type StrengthCheck = dyn Fn(&str) -> Result<(), String>;
+
+/// Overwrites a secret with zeros before its memory is freed. Best-effort:
+/// only this buffer is wiped, copies left behind in the buffer of stdin or
+/// by a buffer that grew while the line was read are not.
+pub(crate) fn wipe(secret: String) {
+    let mut bytes = secret.into_bytes();
+    bytes.fill(0);
+    std::hint::black_box(&bytes); // keeps the writes from being optimized away
+}
+
+/// A line including its line ending, `None` at the end of input.
+fn read_line(input: &mut dyn BufRead) -> Option<String> {
+    let mut line = String::new();
+    match input.read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line),
+    }
+}
+
+/// A line of stdin. Without the `ctrlc` feature Ctrl-C ends the process as usual.
+#[cfg(not(all(unix, feature = "ctrlc")))]
+fn read_stdin_line(_interrupted: &AtomicBool) -> Option<String> {
+    read_line(&mut io::stdin().lock())
+}
+
+/// A line of stdin, or `None` as soon as Ctrl-C is hit. Ctrl-C is only
+/// caught while the line is read, see [`SigintGuard`], and then breaks the
+/// blocked read instead of ending the process. Stdin is read through its
+/// shared buffer, so what follows the line is left to the application.
+#[cfg(all(unix, feature = "ctrlc"))]
+fn read_stdin_line(interrupted: &AtomicBool) -> Option<String> {
+    let Some(_sigint) = SigintGuard::install() else {
+        return read_line(&mut io::stdin().lock()); // the application handles Ctrl-C itself
+    };
+    let mut stdin = io::stdin().lock();
+    let mut line = Vec::new();
+    loop {
+        if interrupted.load(Ordering::SeqCst) {
+            return None;
+        }
+        let buf = match stdin.fill_buf() {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        if buf.is_empty() {
+            break;
+        }
+        let taken = buf.iter().position(|b| *b == b'\n').map_or(buf.len(), |end| end + 1);
+        line.extend_from_slice(&buf[..taken]);
+        stdin.consume(taken);
+        if line.ends_with(b"\n") {
+            break;
+        }
+    }
+    match line.is_empty() {
+        true => None,
+        false => Some(String::from_utf8(line).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())),
+    }
+}
+
+/// Sets [`INTERRUPTED`], only an atomic store as it runs in a signal handler.
+#[cfg(all(unix, feature = "ctrlc"))]
+extern "C" fn on_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Catches Ctrl-C for as long as a prompt reads stdin and puts the
+/// previous handling back when dropped.
+#[cfg(all(unix, feature = "ctrlc"))]
+struct SigintGuard {
+    saved: libc::sigaction,
+}
+
+#[cfg(all(unix, feature = "ctrlc"))]
+impl SigintGuard {
+    /// `None` if the application installed a handler or ignores SIGINT,
+    /// it keeps handling Ctrl-C then.
+    fn install() -> Option<Self> {
+        // SAFETY: sigaction is plain data, the calls only read and fill it in,
+        // and on_sigint only does an atomic store, which is async-signal-safe.
+        unsafe {
+            let mut saved: libc::sigaction = std::mem::zeroed();
+            if libc::sigaction(libc::SIGINT, std::ptr::null(), &mut saved) != 0 || saved.sa_sigaction != libc::SIG_DFL {
+                return None;
+            }
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            libc::sigemptyset(&mut action.sa_mask);
+            action.sa_flags = 0; // no SA_RESTART, so the blocked read returns
+            (libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut()) == 0).then_some(Self { saved })
+        }
+    }
+}
+
+#[cfg(all(unix, feature = "ctrlc"))]
+impl Drop for SigintGuard {
+    fn drop(&mut self) {
+        // SAFETY: restores the action read in install
+        unsafe {
+            libc::sigaction(libc::SIGINT, &self.saved, std::ptr::null_mut());
+        }
+    }
+}
+
+/// Turns off the terminal echo of stdin for a secret answer and turns it
+/// back on when dropped, whether the prompt returns, is interrupted or panics.
+struct EchoGuard {
+    #[cfg(unix)]
+    saved: Option<libc::termios>,
+}
+
+impl EchoGuard {
+    #[cfg(unix)]
+    fn hide() -> Self {
+        // SAFETY: termios is plain data that tcgetattr fills in, and both
+        // calls only read the pointer for the duration of the call.
+        unsafe {
+            let mut term: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut term) != 0 {
+                return Self { saved: None }; // not a terminal, nothing to hide
+            }
+            let saved = term;
+            term.c_lflag &= !libc::ECHO;
+            term.c_lflag |= libc::ECHONL;
+            let hidden = libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &term) == 0;
+            Self { saved: hidden.then_some(saved) }
+        }
+    }
+
+    /// Input is not hidden on this platform.
+    #[cfg(not(unix))]
+    fn hide() -> Self {
+        Self {}
+    }
+
+    /// Also hands stdin over key by key instead of by line, Ctrl-C included
+    /// as a key rather than SIGINT, so the terminal is always restored by
+    /// the guard. `None` if stdin is no terminal.
+    #[cfg(unix)]
+    fn unbuffered() -> Option<Self> {
+        // SAFETY: as in hide
+        unsafe {
+            let mut term: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut term) != 0 {
+                return None;
+            }
+            let saved = term;
+            term.c_lflag &= !(libc::ECHO | libc::ICANON | libc::ISIG);
+            term.c_cc[libc::VMIN] = 1;
+            term.c_cc[libc::VTIME] = 0;
+            (libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &term) == 0).then_some(Self { saved: Some(saved) })
+        }
+    }
+
+    /// Keys cannot be read one by one on this platform.
+    #[cfg(not(unix))]
+    fn unbuffered() -> Option<Self> {
+        None
+    }
+}
+
+impl Drop for EchoGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(saved) = &self.saved {
+            // SAFETY: restores the attributes read by tcgetattr in hide
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, saved);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Cursor;
+    use std::sync::atomic::AtomicBool;
+
+    use super::{PathRequirement, Prompt};
+
+    #[test]
+    fn answers_and_defaults() {
+        let mut input = Cursor::new("alp\r\n\n");
+        let prompt = Prompt { question: "name", default: Some("anon"), secret: false };
+        let not_interrupted = AtomicBool::new(false);
+        assert_eq!(prompt.ask_with(Some(&mut input), &not_interrupted).as_deref(), Some("alp"));
+        assert_eq!(prompt.ask_with(Some(&mut input), &not_interrupted).as_deref(), Some("anon"));
+        assert_eq!(prompt.ask_with(Some(&mut input), &not_interrupted).as_deref(), Some("anon"));
+        let prompt = Prompt { question: "name", default: None, secret: true };
+        assert_eq!(prompt.ask_with(Some(&mut input), &not_interrupted).as_deref(), Some(""));
+    }
+
+    #[test]
+    fn interrupt_is_taken_once() {
+        let mut input = Cursor::new("a\nb\n");
+        let prompt = Prompt { question: "q", default: None, secret: false };
+        let interrupted = AtomicBool::new(true);
+        assert_eq!(prompt.ask_with(Some(&mut input), &interrupted), None);
+        assert_eq!(prompt.ask_with(Some(&mut input), &interrupted).as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn paths_until_valid() {
+        let dir = std::env::temp_dir().join(format!("clitrs-ask-path-{}", std::process::id()));
+        fs::create_dir_all(dir.join("reports")).unwrap();
+        fs::write(dir.join("readme.md"), "").unwrap();
+        let base = format!("{}/", dir.display());
+        let not_interrupted = AtomicBool::new(false);
+        let prompt = Prompt { question: "file", default: None, secret: false };
+
+        // a directory, a missing file, then a partial path completed by Tab and taken with Enter
+        let mut input = Cursor::new(format!("{base}reports\n{base}nope\n{base}rea\t\n\n"));
+        let path = prompt.ask_path_with(Some(&mut input), PathRequirement::ExistingFile, &not_interrupted);
+        assert_eq!(path, Some(dir.join("readme.md")));
+
+        let mut input = Cursor::new(format!("{base}readme.md\n"));
+        assert_eq!(prompt.ask_path_with(Some(&mut input), PathRequirement::ExistingDir, &not_interrupted), None);
+        // the default at the end of input, like an empty answer
+        let with_default = Prompt { question: "dir", default: Some(&base), secret: false };
+        let mut input = Cursor::new(format!("{base}readme.md\n"));
+        assert_eq!(with_default.ask_path_with(Some(&mut input), PathRequirement::ExistingDir, &not_interrupted), Some(dir.clone()));
+
+        // Tab completing the shared start, then the only entry, with an arrow key and a Backspace left out
+        let mut keys = Cursor::new(format!("{base}r\t\x1b[Ax\x7fp\t\n"));
+        assert_eq!(prompt.read_path(&mut keys, &not_interrupted), Some(Some(format!("{base}reports/"))));
+        assert_eq!(prompt.read_path(&mut Cursor::new("\x04"), &not_interrupted), Some(None));
+        assert_eq!(prompt.read_path(&mut Cursor::new("rep\x03"), &not_interrupted), None);
+        assert_eq!(with_default.read_path(&mut Cursor::new("\r"), &not_interrupted), Some(Some(base.clone())));
+        let mut input = Cursor::new("~\n");
+        if let Some(home) = std::env::var_os("HOME").filter(|_| cfg!(unix)) {
+            assert_eq!(prompt.ask_path_with(Some(&mut input), PathRequirement::Any, &not_interrupted), Some(home.into()));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn confirmed_secrets() {
+        let not_interrupted = AtomicBool::new(false);
+        let prompt = Prompt { question: "new password", default: None, secret: true };
+        let long_enough = |pw: &str| if pw.len() >= 4 { Ok(()) } else { Err("at least 4 characters".to_string()) };
+
+        let mut input = Cursor::new("abc\nabcd\nabce\nabcd\nabcd\n");
+        let secret = prompt.ask_confirmed_with(Some(&mut input), "again", Some(&long_enough), 3, &not_interrupted);
+        assert_eq!(secret.as_deref(), Some("abcd"));
+
+        let mut input = Cursor::new("abcd\nx\nabcd\ny\nabcd\nabcd\n");
+        assert_eq!(prompt.ask_confirmed_with(Some(&mut input), "again", None, 2, &not_interrupted), None);
+        let mut input = Cursor::new("abcd\n");
+        assert_eq!(prompt.ask_confirmed_with(Some(&mut input), "again", None, 3, &not_interrupted), None);
+    }
+
+    #[cfg(all(unix, feature = "ctrlc"))]
+    #[test]
+    fn sigint_caught_only_while_reading() {
+        let handler = || unsafe {
+            let mut current: libc::sigaction = std::mem::zeroed();
+            libc::sigaction(libc::SIGINT, std::ptr::null(), &mut current);
+            current.sa_sigaction
+        };
+        let guard = super::SigintGuard::install().unwrap();
+        assert_eq!(handler(), super::on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        drop(guard);
+        assert_eq!(handler(), libc::SIG_DFL);
+    }
+}
@@ -0,0 +1,121 @@
+//! Path answers of [`CliDataBuilder::ask_path`](crate::CliDataBuilder::ask_path):
+//! `~` expansion, the checks of a [`PathRequirement`] and the completion
+//! of a partial path ended with Tab.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::args::expand_home;
+
+/// What the answer to [`CliDataBuilder::ask_path`](crate::CliDataBuilder::ask_path) must name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathRequirement {
+    /// Any path, existing or not.
+    Any,
+    /// An existing file or directory.
+    Exists,
+    /// An existing file.
+    ExistingFile,
+    /// An existing directory.
+    ExistingDir,
+    /// A path that does not exist yet, e.g. for an output file.
+    New,
+}
+
+impl PathRequirement {
+    /// Why `path` does not meet the requirement, if it does not.
+    pub(crate) fn check(self, path: &Path) -> Result<(), &'static str> {
+        let meta = fs::metadata(path).ok();
+        match (self, meta) {
+            (PathRequirement::Any, _) => Ok(()),
+            (PathRequirement::New, None) => Ok(()),
+            (PathRequirement::New, Some(_)) => Err("already exists"),
+            (_, None) => Err("does not exist"),
+            (PathRequirement::ExistingFile, Some(meta)) if !meta.is_file() => Err("is no file"),
+            (PathRequirement::ExistingDir, Some(meta)) if !meta.is_dir() => Err("is no directory"),
+            (PathRequirement::Exists | PathRequirement::ExistingFile | PathRequirement::ExistingDir, Some(_)) => Ok(()),
+        }
+    }
+}
+
+/// `answer` with a leading `~` expanded, as is if the home directory is unknown.
+pub(crate) fn expand(answer: &str) -> PathBuf {
+    PathBuf::from(expand_home(answer).unwrap_or_else(|| answer.to_string()))
+}
+
+/// The entries completing `partial`, as typed: those in its directory whose
+/// names start with its last component, sorted, directories ending in `/`.
+/// Hidden entries only complete a component starting with `.`.
+pub(crate) fn complete(partial: &str) -> Vec<String> {
+    let split = partial.rfind(['/', std::path::MAIN_SEPARATOR]).map_or(0, |ind| ind + 1);
+    let (dir, prefix) = partial.split_at(split);
+    let Ok(entries) = fs::read_dir(if dir.is_empty() { PathBuf::from(".") } else { expand(dir) }) else {
+        return Vec::new();
+    };
+    let mut matches: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.path().is_dir(); // follows links, unlike the entry's file type
+            Some(format!("{dir}{name}{}", if is_dir { "/" } else { "" }))
+        })
+        .collect();
+    matches.sort();
+    matches
+}
+
+/// The longest start all of `matches` share.
+pub(crate) fn common_prefix(matches: &[String]) -> String {
+    let Some((first, rest)) = matches.split_first() else {
+        return String::new();
+    };
+    let mut prefix = first.as_str();
+    for other in rest {
+        let len = prefix.char_indices()
+            .zip(other.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(prefix.len().min(other.len()), |((ind, _), _)| ind);
+        prefix = &prefix[..len];
+    }
+    prefix.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{common_prefix, complete, PathRequirement};
+
+    #[test]
+    fn requirements_and_completion() {
+        let dir = std::env::temp_dir().join(format!("clitrs-path-{}", std::process::id()));
+        fs::create_dir_all(dir.join("reports")).unwrap();
+        fs::write(dir.join("readme.md"), "").unwrap();
+        fs::write(dir.join(".hidden"), "").unwrap();
+        let file = dir.join("readme.md");
+
+        assert_eq!(PathRequirement::ExistingFile.check(&file), Ok(()));
+        assert_eq!(PathRequirement::ExistingDir.check(&file), Err("is no directory"));
+        assert_eq!(PathRequirement::ExistingFile.check(&dir), Err("is no file"));
+        assert_eq!(PathRequirement::Exists.check(&dir.join("nope")), Err("does not exist"));
+        assert_eq!(PathRequirement::New.check(&file), Err("already exists"));
+        assert_eq!(PathRequirement::Any.check(Path::new("")), Ok(()));
+
+        let base = format!("{}/", dir.display());
+        assert_eq!(complete(&format!("{base}re")), [format!("{base}readme.md"), format!("{base}reports/")]);
+        assert_eq!(complete(&format!("{base}rep")), [format!("{base}reports/")]);
+        assert_eq!(complete(&format!("{base}.h")), [format!("{base}.hidden")]);
+        assert!(complete(&format!("{base}x")).is_empty() && complete(&format!("{base}nope/x")).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let strings = |words: &[&str]| words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        assert_eq!(common_prefix(&strings(&["a/readme.md", "a/reports/"])), "a/re");
+        assert_eq!(common_prefix(&strings(&["abc", "ab"])), "ab");
+        assert_eq!(common_prefix(&strings(&["çay", "çok"])), "ç");
+        assert_eq!(common_prefix(&[]), "");
+    }
+}
@@ -0,0 +1,169 @@
+//! A spinner on stderr while a long step runs, e.g. inside a
+//! [`CliStep::then`](crate::CliStep::then) or [`CliDataBuilder::then`](crate::CliDataBuilder::then).
+
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::term::{self, Stream};
+
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const TICK: Duration = Duration::from_millis(80);
+
+thread_local! {
+    /// Set while a spinner runs on this thread, a nested one is not shown.
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
+
+type Out = Arc<Mutex<dyn Write + Send>>;
+
+/// Runs `f` on the current thread with a spinner and `message` on stderr,
+/// replaced by `✓ message (1.2s)` once it returns, or `✗` if it panics.
+/// When stderr is not a terminal the message is printed as a plain line
+/// before and after. A spinner within a running one only runs `f`.
+///
+/// ```no_run
+/// # use clitrs::spinner::with_spinner;
+/// let bytes = with_spinner("Downloading…", || vec![0u8; 1024]);
+/// ```
+pub fn with_spinner<R>(message: &str, f: impl FnOnce() -> R) -> R {
+    spin(message, f, |_| true)
+}
+
+/// Like [`with_spinner`] for a step that may fail, marked `✗` if it
+/// returns an error.
+pub fn try_with_spinner<T, E>(message: &str, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    spin(message, f, Result::is_ok)
+}
+
+fn spin<R>(message: &str, f: impl FnOnce() -> R, succeeded: impl FnOnce(&R) -> bool) -> R {
+    if ACTIVE.replace(true) {
+        return f();
+    }
+    let _active = Active;
+    let out: Out = Arc::new(Mutex::new(io::stderr()));
+    let mut spinner = Spinner::start(message, term::is_tty(Stream::Stderr), out, next_tick);
+    let result = f();
+    spinner.finish(succeeded(&result));
+    result
+}
+
+/// A running spinner, finished as failed if dropped before [`Spinner::finish`],
+/// which is when the step panics.
+struct Spinner {
+    message: String,
+    live: bool,
+    out: Out,
+    started: Instant,
+    ticker: Option<(Sender<()>, JoinHandle<()>)>,
+    finished: bool,
+}
+
+/// Waits a tick for the next frame, `false` once the spinner is stopped.
+fn next_tick(stopped: &Receiver<()>) -> bool {
+    matches!(stopped.recv_timeout(TICK), Err(RecvTimeoutError::Timeout))
+}
+
+impl Spinner {
+    /// Starts drawing frames if `live`, waiting with `tick` between them.
+    fn start(message: &str, live: bool, out: Out, mut tick: impl FnMut(&Receiver<()>) -> bool + Send + 'static) -> Self {
+        let ticker = match live {
+            true => {
+                let (stop, stopped) = mpsc::channel();
+                let (message, out) = (message.to_string(), Arc::clone(&out));
+                let handle = thread::spawn(move || {
+                    for frame in FRAMES.iter().cycle() {
+                        if let Ok(mut out) = out.lock() {
+                            let _ = write!(out, "\r{frame} {message}");
+                            let _ = out.flush();
+                        }
+                        if !tick(&stopped) {
+                            break;
+                        }
+                    }
+                });
+                Some((stop, handle))
+            },
+            false => {
+                if let Ok(mut out) = out.lock() {
+                    let _ = writeln!(out, "{message}");
+                }
+                None
+            },
+        };
+        Spinner { message: message.to_string(), live, out, started: Instant::now(), ticker, finished: false }
+    }
+
+    /// Stops the ticker and replaces the spinner line by the outcome, once.
+    fn finish(&mut self, succeeded: bool) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        if let Some((stop, handle)) = self.ticker.take() {
+            let _ = stop.send(());
+            let _ = handle.join();
+        }
+        let mark = if succeeded { '✓' } else { '✗' };
+        let clear = if self.live { "\r\x1b[2K" } else { "" };
+        if let Ok(mut out) = self.out.lock() {
+            let _ = writeln!(out, "{clear}{mark} {} ({:.1}s)", self.message, self.started.elapsed().as_secs_f64());
+        }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.finish(false);
+    }
+}
+
+/// Clears [`ACTIVE`] when the outermost spinner ends, even by a panic.
+struct Active;
+
+impl Drop for Active {
+    fn drop(&mut self) {
+        ACTIVE.set(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::{next_tick, with_spinner, Spinner, ACTIVE};
+
+    fn lines(out: &Arc<Mutex<Vec<u8>>>) -> String {
+        String::from_utf8(out.lock().unwrap().clone()).unwrap()
+    }
+
+    #[test]
+    fn plain_and_live_spinners() {
+        let out = Arc::new(Mutex::new(Vec::new()));
+        let mut spinner = Spinner::start("Building", false, out.clone(), next_tick);
+        spinner.finish(true);
+        drop(spinner);
+        let written = lines(&out);
+        assert!(written.starts_with("Building\n✓ Building (") && written.ends_with("s)\n"), "{written}");
+
+        let out = Arc::new(Mutex::new(Vec::new()));
+        let mut ticks = 0;
+        // two ticks pass, then the clock stands still until the spinner is stopped
+        let spinner = Spinner::start("Downloading", true, out.clone(), move |stopped| {
+            ticks += 1;
+            ticks <= 2 || stopped.recv().is_err()
+        });
+        drop(spinner);
+        let written = lines(&out);
+        assert!(written.starts_with("\r⠋ Downloading\r⠙ Downloading\r⠹ Downloading\r\x1b[2K✗ Downloading ("), "{written}");
+
+        assert_eq!(with_spinner("outer", || with_spinner("inner", || 1) + 1), 2);
+        // a spinner on another thread is not nested and runs
+        let other = with_spinner("outer", || thread::spawn(|| with_spinner("other", || ACTIVE.get())).join().unwrap());
+        assert!(other);
+    }
+}
@@ -0,0 +1,21 @@
+//! Whether the standard streams are terminals, which decides if prompts
+//! can be asked and if output is colored.
+
+use std::io::{self, IsTerminal};
+
+/// One of the standard streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdin,
+    Stdout,
+    Stderr,
+}
+
+/// Whether `stream` is connected to a terminal rather than a file or pipe.
+pub fn is_tty(stream: Stream) -> bool {
+    match stream {
+        Stream::Stdin => io::stdin().is_terminal(),
+        Stream::Stdout => io::stdout().is_terminal(),
+        Stream::Stderr => io::stderr().is_terminal(),
+    }
+}
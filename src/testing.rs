@@ -0,0 +1,261 @@
+//! Helpers for testing command lines built on this crate, meant for
+//! downstream integration tests as much as for the crate's own.
+//!
+//! ```
+//! use clitrs::args::{CliArgs, ParseError};
+//! use clitrs::{assert_parse_err, assert_parses, parse_str};
+//! use clitrs::testing::NoValue;
+//!
+//! let mut args = CliArgs::new();
+//! args.with("--name/-n=s").with("--age=i? ::>18").with("--files=s*?").with("-v=b?");
+//!
+//! assert_parses!(args, ["-n", "alp", "--files", "a", "b"], {
+//!     "--name" => "alp",
+//!     "--age" => 18,
+//!     "--files" => ["a", "b"],
+//!     "-v" => NoValue,
+//! });
+//! args.reset();
+//! assert_parses!(args, "-n 'alp y' -v", { "--name" => "alp y", "-v" => true });
+//! args.reset();
+//! assert_parse_err!(args, ["--bogus"], ParseError::UnknownKey(..));
+//! assert_eq!(parse_str!("-n 'alp y' --age 3"), ["-n", "alp y", "--age", "3"]);
+//! ```
+
+use std::fmt::Debug;
+
+use crate::args::CliArgs;
+
+/// Splits a command line into tokens the way a POSIX shell would, see
+/// [`tokenize`](crate::args::tokenize), panicking on an unclosed quote.
+#[macro_export]
+macro_rules! parse_str {
+    ($line:expr) => {
+        match $crate::args::tokenize($line) {
+            Ok(tokens) => tokens,
+            Err(e) => panic!("{:?} failed to tokenize: {}", $line, e),
+        }
+    };
+}
+
+/// Parses the tokens, or a command line split like [`parse_str!`] does,
+/// and asserts the value of each listed key, panicking with the parse error
+/// or the mismatching key. Expected values can be a `&str`, `String`, `i32`
+/// or `bool` for the first value, an array or slice of those for all
+/// values, or [`NoValue`](crate::testing::NoValue) for no value.
+#[macro_export]
+macro_rules! assert_parses {
+    ($args:expr, [$($token:expr),* $(,)?], { $($key:expr => $expected:expr),* $(,)? }) => {
+        $crate::assert_parses!(@tokens $args, vec![$(String::from($token)),*], { $($key => $expected),* })
+    };
+    ($args:expr, $line:expr, { $($key:expr => $expected:expr),* $(,)? }) => {
+        $crate::assert_parses!(@tokens $args, $crate::parse_str!($line), { $($key => $expected),* })
+    };
+    (@tokens $args:expr, $tokens:expr, { $($key:expr => $expected:expr),* }) => {{
+        let args: &mut $crate::args::CliArgs = &mut $args;
+        let tokens: Vec<String> = $tokens;
+        if let Err(e) = args.parse_from(&tokens) {
+            panic!("{:?} failed to parse: {}", tokens, e);
+        }
+        $($crate::testing::assert_value(args, $key, $expected);)*
+    }};
+}
+
+/// Parses the tokens and asserts that it fails with an error matching the
+/// pattern, with an optional `if` guard like in a `match` arm.
+#[macro_export]
+macro_rules! assert_parse_err {
+    ($args:expr, [$($token:expr),* $(,)?], $pattern:pat $(if $guard:expr)?) => {{
+        let args: &mut $crate::args::CliArgs = &mut $args;
+        let tokens: Vec<String> = vec![$(String::from($token)),*];
+        match args.parse_from(&tokens) {
+            Err($pattern) $(if $guard)? => {},
+            other => panic!("{:?} was expected to fail with `{}`, got {:?}", tokens, stringify!($pattern), other),
+        }
+    }};
+}
+
+/// A value [`assert_parses!`] can compare an arg's values against.
+pub trait ExpectedValue: Debug {
+    /// Whether the values of `key` in `args` are the expected ones.
+    fn matches_values(&self, args: &CliArgs, key: &str) -> bool;
+}
+
+impl ExpectedValue for &str {
+    fn matches_values(&self, args: &CliArgs, key: &str) -> bool {
+        args.get_str(key).is_ok_and(|v| v == Some(*self))
+    }
+}
+
+impl ExpectedValue for String {
+    fn matches_values(&self, args: &CliArgs, key: &str) -> bool {
+        self.as_str().matches_values(args, key)
+    }
+}
+
+impl ExpectedValue for i32 {
+    fn matches_values(&self, args: &CliArgs, key: &str) -> bool {
+        args.get_int(key).is_ok_and(|v| v == Some(*self))
+    }
+}
+
+impl ExpectedValue for bool {
+    fn matches_values(&self, args: &CliArgs, key: &str) -> bool {
+        args.get_bool(key).is_ok_and(|v| v == Some(*self))
+    }
+}
+
+impl ExpectedValue for [&str] {
+    fn matches_values(&self, args: &CliArgs, key: &str) -> bool {
+        args.get_string_multi(key).is_ok_and(|v| v == self)
+    }
+}
+
+impl ExpectedValue for [i32] {
+    fn matches_values(&self, args: &CliArgs, key: &str) -> bool {
+        args.get_int_multi(key).is_ok_and(|v| v == self)
+    }
+}
+
+impl ExpectedValue for [bool] {
+    fn matches_values(&self, args: &CliArgs, key: &str) -> bool {
+        args.get_bool_multi(key).is_ok_and(|v| v == self)
+    }
+}
+
+impl<T: ExpectedValue + ?Sized> ExpectedValue for &T {
+    fn matches_values(&self, args: &CliArgs, key: &str) -> bool {
+        (**self).matches_values(args, key)
+    }
+}
+
+impl<T: Debug, const N: usize> ExpectedValue for [T; N]
+where
+    [T]: ExpectedValue,
+{
+    fn matches_values(&self, args: &CliArgs, key: &str) -> bool {
+        self[..].matches_values(args, key)
+    }
+}
+
+/// Expects no value at all, whatever the arg's type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoValue;
+
+impl ExpectedValue for NoValue {
+    fn matches_values(&self, args: &CliArgs, key: &str) -> bool {
+        args.get_bool_multi(key).is_ok_and(<[_]>::is_empty)
+            || args.get_int_multi(key).is_ok_and(<[_]>::is_empty)
+            || args.get_string_multi(key).is_ok_and(<[_]>::is_empty)
+    }
+}
+
+/// `Some` expects the value, `None` no value like [`NoValue`]. A bare
+/// `None` needs its type spelled out, as in `None::<&str>`.
+impl<T: ExpectedValue> ExpectedValue for Option<T> {
+    fn matches_values(&self, args: &CliArgs, key: &str) -> bool {
+        match self {
+            Some(expected) => expected.matches_values(args, key),
+            None => NoValue.matches_values(args, key),
+        }
+    }
+}
+
+/// Panics unless the values of `key` in `args` are `expected`, see [`assert_parses!`].
+#[track_caller]
+pub fn assert_value(args: &CliArgs, key: &str, expected: impl ExpectedValue) {
+    if !expected.matches_values(args, key) {
+        panic!("`{}` was expected to be {:?}, got {}", key, expected, describe(args, key));
+    }
+}
+
+/// The values of `key` for a failure message.
+fn describe(args: &CliArgs, key: &str) -> String {
+    if let Ok(v) = args.get_bool_multi(key) {
+        return format!("{:?}", v);
+    }
+    if let Ok(v) = args.get_int_multi(key) {
+        return format!("{:?}", v);
+    }
+    match args.get_string_multi(key) {
+        Ok(v) => format!("{:?}", v),
+        Err(e) => format!("{:?}", e),
+    }
+}
+
+/// Panics unless [`CliArgs::help_plain_fixed_width`] at
+/// [`CliArgs::HELP_WIDTH`] columns is exactly `expected`, showing a line
+/// diff: `-` for expected lines that are missing, `+` for unexpected ones.
+///
+/// ```
+/// # use clitrs::args::CliArgs;
+/// let mut args = CliArgs::new();
+/// args.bin_name("mytool").with("--port=i # Port to listen on");
+/// clitrs::testing::assert_help_snapshot(&args, "\
+/// USAGE:
+///     mytool --port <PORT>
+///
+/// OPTIONS:
+///     --port <PORT>    Port to listen on
+/// ");
+/// ```
+#[track_caller]
+pub fn assert_help_snapshot(args: &CliArgs, expected: &str) {
+    let actual = args.help_plain_fixed_width(CliArgs::HELP_WIDTH);
+    if actual != expected {
+        panic!("help output does not match the snapshot:\n{}", line_diff(expected, &actual));
+    }
+}
+
+/// A line diff of `expected` to `actual` over their longest common
+/// subsequence of lines, unchanged lines prefixed with two spaces.
+fn line_diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.split('\n').collect();
+    let new: Vec<&str> = actual.split('\n').collect();
+    // lcs[i][j]: length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            out.push_str(&format!("  {}\n", old[i]));
+            i += 1;
+            j += 1;
+        }
+        else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            out.push_str(&format!("+ {}\n", new[j]));
+            j += 1;
+        }
+        else {
+            out.push_str(&format!("- {}\n", old[i]));
+            i += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assert_value, line_diff, NoValue};
+    use crate::args::CliArgs;
+
+    #[test]
+    fn diff_marks_changed_lines() {
+        assert_eq!(line_diff("a\nb\nc", "a\nx\nc"), "  a\n+ x\n- b\n  c\n");
+        assert_eq!(line_diff("a\nb", "a\nb\nc"), "  a\n  b\n+ c\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "`--name` was expected to be NoValue, got [\"alp\"]")]
+    fn no_value_rejects_a_value() {
+        let mut args = CliArgs::new();
+        args.with("--name=s?").parse("--name alp").unwrap();
+        assert_value(&args, "--name", NoValue);
+    }
+}
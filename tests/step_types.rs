@@ -0,0 +1,5 @@
+#[test]
+fn step_misuse_does_not_compile() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,6 @@
+use clitrs::CliStep;
+
+fn main() {
+    let _done: CliStep<()> = CliStep::new(1)
+        .end(|_| ());
+}
@@ -0,0 +1,5 @@
+use clitrs::CliStepEnd;
+
+fn main() {
+    let _ = CliStepEnd { _ended: () };
+}
@@ -0,0 +1,6 @@
+use clitrs::CliStep;
+
+fn main() {
+    let _step: CliStep<String> = CliStep::new(1u32)
+        .then(|n| n + 1);
+}
@@ -0,0 +1,7 @@
+use clitrs::CliStep;
+
+fn main() {
+    CliStep::new(1)
+        .end(|_| ())
+        .then(|n: i32| n + 1);
+}